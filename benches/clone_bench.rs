@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+extern crate fourai;
+
+use fourai::ai::agent::{Agent, Evolvable};
+use fourai::ai::nn::Activation;
+use fourai::ai::pool::{Pool, PoolProperties};
+use fourai::ai::NNPlayer;
+
+mod common;
+
+/// The default training network
+fn gen_agent() -> Agent<NNPlayer> {
+    Agent::new(NNPlayer::new_from_param(
+        vec![42, 128, 256, 128, 7],
+        vec![Activation::Sigmoid; 4],
+    ))
+}
+
+/// A population of the default size and structure, bred without any mutation so that only
+/// the copying is measured
+fn gen_props() -> PoolProperties {
+    PoolProperties {
+        mutation_prob: 0.0,
+        crossover_size: 30,
+        population_size: 200,
+        structure: vec![42, 128, 256, 128, 7],
+        activations: vec![Activation::Sigmoid; 4],
+        ..common::bench_props()
+    }
+}
+
+fn clone_bench(c: &mut Criterion) {
+    let agent = gen_agent();
+    c.bench_function("clone agent", |b| b.iter(|| black_box(agent.clone())));
+    let mut target = gen_agent();
+    c.bench_function("clone agent into existing agent", |b| {
+        b.iter(|| {
+            target.clone_from(&agent);
+            black_box(&target);
+        })
+    });
+}
+
+fn reproduce_bench(c: &mut Criterion) {
+    let mut pool: Pool<NNPlayer> = Pool::new(gen_props());
+    c.bench_function("select and reproduce, population 200", |b| {
+        b.iter(|| {
+            let survivors = pool.select();
+            pool.reproduce(black_box(survivors));
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = clone_bench, reproduce_bench
+}
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+//! Shared setup of the benches that run a pool
+
+use fourai::ai::agent::{IllegalMove, TieBreak};
+use fourai::ai::encoder::Encoding;
+use fourai::ai::nn::Activation;
+use fourai::ai::pool::{FitnessAggregation, PoolProperties};
+
+/// Properties of a small pool of 40 agents that trains one generation, without saving or
+/// comparing. Benches change the fields they measure with struct update syntax.
+pub fn bench_props() -> PoolProperties {
+    PoolProperties {
+        surviving_amount: 10,
+        elitism: 0,
+        mutation_range: 0.05,
+        mutation_prob: 0.1,
+        antithetic: false,
+        layer_mutation: false,
+        frozen_layers: Vec::new(),
+        crossover_size: 1,
+        crossover_enabled: true,
+        immigrant_fraction: 0.0,
+        population_size: 40,
+        population_schedule: Vec::new(),
+        structure: vec![42, 98, 98, 98, 7],
+        activations: vec![
+            Activation::Sigmoid,
+            Activation::Sigmoid,
+            Activation::Sigmoid,
+            Activation::Sigmoid,
+        ],
+        extra_architectures: Vec::new(),
+        encoding: Encoding::Scalar,
+        generations: 1,
+        max_runtime: None,
+        seed: None,
+        save_interval: 100000,
+        save_best_only: false,
+        checkpoint_full: false,
+        keep_last: -1,
+        seed_from_best: false,
+        preserve_fitness: 0,
+        compare_interval: 100000,
+        compare_games: 1,
+        opening_report: false,
+        compare_both_colors: false,
+        show_best_game: false,
+        red_perspective: false,
+        curriculum_generations: 0,
+        curriculum_weight: 0,
+        curriculum_minimax_from: 0,
+        reference_games: 0,
+        draw_penalty: 0,
+        draw_position_bonus: 0,
+        fitness_aggregation: FitnessAggregation::Sum,
+        opponent_weighted: false,
+        warn_on_saturation: false,
+        move_histogram: false,
+        constant_output_penalty: 0,
+        report_outcomes: false,
+        stats_interval: 1,
+        eval_block_size: 0,
+        batched_eval: false,
+        max_opponents: 0,
+        symmetric_eval: true,
+        games_per_color: 1,
+        tie_break: TieBreak::First,
+        illegal_move: IllegalMove::Skip,
+        snapshot_at: -1,
+        dump_games: 0,
+        fitness_cache: false,
+        profile_memory: false,
+        stats_log: false,
+        eval_opponent_from: None,
+        benchmark_against: None,
+        holdout: None,
+        file_path: std::path::PathBuf::from("dummy_path/"),
+    }
+}
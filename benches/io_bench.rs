@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::io::{Read, Write};
+
+extern crate fourai;
+
+use fourai::ai::agent::{Agent, Evolvable};
+use fourai::ai::nn::Activation;
+use fourai::ai::NNPlayer;
+
+/// A saved generation with the default training structure
+fn gen_population(size: usize) -> Vec<Agent<NNPlayer>> {
+    (0..size)
+        .map(|_| {
+            Agent::new(NNPlayer::new_from_param(
+                vec![42, 128, 256, 128, 7],
+                vec![
+                    Activation::Sigmoid,
+                    Activation::Sigmoid,
+                    Activation::Sigmoid,
+                    Activation::Sigmoid,
+                ],
+            ))
+        })
+        .collect()
+}
+
+fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn decompress(bytes: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decoded).unwrap();
+    decoded
+}
+
+fn print_sizes(population: &Vec<Agent<NNPlayer>>) {
+    let cbor = serde_cbor::to_vec(population).unwrap();
+    let json = serde_json::to_vec(population).unwrap();
+
+    println!("{:<10} {:>12}", "format", "bytes");
+    println!("{:<10} {:>12}", "cbor", cbor.len());
+    println!("{:<10} {:>12}", "cbor+gzip", compress(&cbor).len());
+    println!("{:<10} {:>12}", "json", json.len());
+    println!("{:<10} {:>12}", "json+gzip", compress(&json).len());
+}
+
+fn io_bench(c: &mut Criterion) {
+    let population = gen_population(5);
+    print_sizes(&population);
+
+    let cbor = serde_cbor::to_vec(&population).unwrap();
+    let json = serde_json::to_vec(&population).unwrap();
+    let cbor_gz = compress(&cbor);
+    let json_gz = compress(&json);
+
+    c.bench_function("serialize cbor", |b| {
+        b.iter(|| serde_cbor::to_vec(black_box(&population)).unwrap())
+    });
+    c.bench_function("serialize cbor+gzip", |b| {
+        b.iter(|| compress(&serde_cbor::to_vec(black_box(&population)).unwrap()))
+    });
+    c.bench_function("serialize json", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&population)).unwrap())
+    });
+    c.bench_function("serialize json+gzip", |b| {
+        b.iter(|| compress(&serde_json::to_vec(black_box(&population)).unwrap()))
+    });
+
+    c.bench_function("deserialize cbor", |b| {
+        b.iter(|| serde_cbor::from_slice::<Vec<Agent<NNPlayer>>>(black_box(&cbor)).unwrap())
+    });
+    c.bench_function("deserialize cbor+gzip", |b| {
+        b.iter(|| {
+            serde_cbor::from_slice::<Vec<Agent<NNPlayer>>>(&decompress(black_box(&cbor_gz)))
+                .unwrap()
+        })
+    });
+    c.bench_function("deserialize json", |b| {
+        b.iter(|| serde_json::from_slice::<Vec<Agent<NNPlayer>>>(black_box(&json)).unwrap())
+    });
+    c.bench_function("deserialize json+gzip", |b| {
+        b.iter(|| {
+            serde_json::from_slice::<Vec<Agent<NNPlayer>>>(&decompress(black_box(&json_gz)))
+                .unwrap()
+        })
+    });
+}
+
+criterion_group! {
+    name = io_benches;
+    config = Criterion::default().sample_size(10);
+    targets = io_bench
+}
+criterion_main!(io_benches);
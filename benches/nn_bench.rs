@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+extern crate fourai;
+
+use fourai::ai::nn::{Activation, ArenaNN, NN};
+
+fn gen_nn() -> NN {
+    NN::new_rand(
+        vec![42, 128, 256, 128, 7],
+        vec![
+            Activation::Sigmoid,
+            Activation::Sigmoid,
+            Activation::Sigmoid,
+            Activation::Sigmoid,
+        ],
+        &mut rand::thread_rng(),
+    )
+}
+
+fn forward_bench(c: &mut Criterion) {
+    let nn = gen_nn();
+    let arena = ArenaNN::from(nn.clone());
+    let input = (0..42).map(|x| (x % 3) as f32 - 1.0).collect::<Vec<_>>();
+
+    c.bench_function("forward, matrix per layer", |b| {
+        b.iter(|| black_box(nn.forward(black_box(input.clone()))));
+    });
+    c.bench_function("forward, contiguous arena", |b| {
+        b.iter(|| black_box(arena.forward(black_box(input.clone()))));
+    });
+}
+
+criterion_group!(benches, forward_bench);
+criterion_main!(benches);
@@ -4,41 +4,74 @@ extern crate fourai;
 
 use fourai::ai::pool::{Pool, PoolProperties};
 use fourai::ai::NNPlayer;
-use fourai::pool_props;
-
-fn gen_props(size: usize) -> PoolProperties {
-    pool_props! {
-        surviving_amount => size,
-        mutation_amount => 3,
-        mutation_range => 0.05,
-        crossover_amount => 1,
-        structure => vec![42, 98, 98, 98, 7],
-        activations => vec![
-            Activation::Sigmoid,
-            Activation::Sigmoid,
-            Activation::Sigmoid,
-            Activation::Sigmoid,
-        ],
-        generations => 1,
-        save_interval => 100000,
-        compare_interval => 100000,
-        file_path => std::path::PathBuf::from("dummy_path/")
+
+mod common;
+
+fn gen_props(size: usize, eval_block_size: usize) -> PoolProperties {
+    PoolProperties {
+        surviving_amount: size,
+        population_size: size * 4,
+        eval_block_size,
+        ..common::bench_props()
     }
 }
+
 fn big_bench(c: &mut Criterion) {
-    let pool: Pool<NNPlayer> = Pool::new(gen_props(10));
+    let pool: Pool<NNPlayer> = Pool::new(gen_props(10, 0));
     c.bench_function("run pool, 10 surviving", |b| {
         b.iter(|| black_box(pool.clone().training_loop(0)));
     });
 }
 
 fn small_bench(c: &mut Criterion) {
-    let pool_small: Pool<NNPlayer> = Pool::new(gen_props(5));
+    let pool_small: Pool<NNPlayer> = Pool::new(gen_props(5, 0));
     c.bench_function("run pool, 5 surviving", |b| {
         b.iter(|| black_box(pool_small.clone().training_loop(0)));
     });
 }
 
+fn blocked_bench(c: &mut Criterion) {
+    let pool: Pool<NNPlayer> = Pool::new(gen_props(10, 0));
+    let blocked: Pool<NNPlayer> = Pool::new(gen_props(10, 8));
+    c.bench_function("run pool, 10 surviving, unblocked", |b| {
+        b.iter(|| black_box(pool.clone().training_loop(0)));
+    });
+    c.bench_function("run pool, 10 surviving, 8x8 blocks", |b| {
+        b.iter(|| black_box(blocked.clone().training_loop(0)));
+    });
+}
+
+fn batched_bench(c: &mut Criterion) {
+    let mut props = gen_props(10, 0);
+    let pool: Pool<NNPlayer> = Pool::new(props.clone());
+    props.batched_eval = true;
+    let batched: Pool<NNPlayer> = Pool::new(props);
+    c.bench_function("run pool, 10 surviving, one game at a time", |b| {
+        b.iter(|| black_box(pool.clone().training_loop(0)));
+    });
+    c.bench_function("run pool, 10 surviving, batched lockstep games", |b| {
+        b.iter(|| black_box(batched.clone().training_loop(0)));
+    });
+}
+
+fn cache_bench(c: &mut Criterion) {
+    // Every survivor is carried over unchanged as an elite
+    let mut props = gen_props(10, 0);
+    props.elitism = 10;
+    props.generations = 4;
+    // The same starting population for both
+    props.seed = Some(0);
+    let pool: Pool<NNPlayer> = Pool::new(props.clone());
+    props.fitness_cache = true;
+    let cached: Pool<NNPlayer> = Pool::new(props);
+    c.bench_function("run pool, 4 generations, 10 elites", |b| {
+        b.iter(|| black_box(pool.clone().training_loop(0)));
+    });
+    c.bench_function("run pool, 4 generations, 10 elites, fitness cache", |b| {
+        b.iter(|| black_box(cached.clone().training_loop(0)));
+    });
+}
+
 criterion_group! {
     name = small_benches;
     config = Criterion::default().sample_size(10);
@@ -47,6 +80,6 @@ criterion_group! {
 criterion_group! {
     name = big_benches;
     config = Criterion::default().sample_size(10);
-    targets = big_bench
+    targets = big_bench, blocked_bench, batched_bench, cache_bench
 }
 criterion_main!(small_benches, big_benches);
@@ -1,21 +1,123 @@
-use super::{nn, N};
+use super::{encoder::Encoding, nn, N};
 use crate::game;
 
+use rand::{seq::SliceRandom, Rng, RngCore};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-pub trait Player {
-    fn new_from_param(structure: Vec<usize>, activations: Vec<nn::Activation>) -> Self;
-    fn mutate(&mut self, _mutation_range: N, _mutation_prob: N) {}
-    fn crossover(&mut self, _other: &Self) {}
+/// Anything that can play a game: scores the columns of a board. Object safe, so players of
+/// different types can be mixed as `Box<dyn MovePlayer>` wherever they only need to play.
+pub trait MovePlayer {
     fn get_move(&self, board: [[game::Spot; 6]; 7]) -> [N; 7];
+    /// Scores of every one of `boards`, the same as calling `get_move` on each up to float
+    /// rounding. Players that can score many boards at once faster than one by one override it.
+    fn get_moves(&self, boards: &[[[game::Spot; 6]; 7]]) -> Vec<[N; 7]> {
+        boards.iter().map(|board| self.get_move(*board)).collect()
+    }
+    /// Short label of the player for output, the name of its type unless overridden
+    fn name(&self) -> String {
+        let path = std::any::type_name::<Self>();
+        path.rsplit("::").next().unwrap_or(path).to_string()
+    }
+    /// Cost of the last move of players that search, `None` for the others
+    fn search_stats(&self) -> Option<SearchStats> {
+        None
+    }
+}
+
+/// Cost of a single move of a search player
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchStats {
+    /// Positions visited by the search
+    pub nodes: u64,
+    /// Wall-clock time the move took
+    pub time: Duration,
+}
+
+/// Creating, mutating and crossing over players, which a `Pool` needs to evolve them
+pub trait Evolvable: Sized {
+    fn new_from_param(structure: Vec<usize>, activations: Vec<nn::Activation>) -> Self;
+    /// Same as `new_from_param`, but any random initialisation draws from `rng`
+    fn new_from_param_with(
+        structure: Vec<usize>,
+        activations: Vec<nn::Activation>,
+        _rng: &mut dyn RngCore,
+    ) -> Self {
+        Self::new_from_param(structure, activations)
+    }
+    /// Every random draw of mutation and crossover comes from `rng`, so that a pool with a
+    /// seeded RNG breeds the same offspring every run
+    fn mutate(&mut self, _mutation_range: N, _mutation_prob: N, _rng: &mut dyn RngCore) {}
+    /// Mutates `self` and `sibling` with opposite perturbations, so that for two copies of
+    /// the same player their changes cancel out. Players that don't support this mutate both
+    /// independently.
+    fn mutate_antithetic(
+        &mut self,
+        sibling: &mut Self,
+        mutation_range: N,
+        mutation_prob: N,
+        rng: &mut dyn RngCore,
+    ) {
+        self.mutate(mutation_range, mutation_prob, rng);
+        sibling.mutate(mutation_range, mutation_prob, rng);
+    }
+    /// Mutates only one randomly picked layer of the player's network, leaving the others
+    /// intact. Players without layers mutate as a whole.
+    fn mutate_one_layer(&mut self, mutation_range: N, mutation_prob: N, rng: &mut dyn RngCore) {
+        self.mutate(mutation_range, mutation_prob, rng);
+    }
+    fn crossover(&mut self, _other: &Self, _rng: &mut dyn RngCore) {}
+    /// Layer sizes of the player's network, if it has one
+    fn structure(&self) -> Option<&[usize]> {
+        None
+    }
+    /// Selects how the board is fed to the player's network, for players that have one
+    fn set_encoding(&mut self, _encoding: Encoding) {}
+    /// Keeps the weight layers `layers` of the player's network (0 being the one after the
+    /// inputs) unchanged by every kind of mutation and by crossover. Players without a network
+    /// ignore it.
+    fn freeze_layers(&mut self, _layers: &[usize]) {}
+    /// Has the player's network see the board with the colors swapped whenever it plays
+    /// yellow, so that it always plays as red. Players without a network ignore it.
+    fn set_red_perspective(&mut self, _red_perspective: bool) {}
+    /// Every weight of the player's network, layer by layer. Empty for players without one.
+    fn weight_vector(&self) -> Vec<N> {
+        Vec::new()
+    }
+    /// Fraction of units in every layer of the player's network that are saturated on all of
+    /// `boards`. Empty for players without a network.
+    fn saturation(&self, _boards: &[[[game::Spot; 6]; 7]]) -> Vec<N> {
+        Vec::new()
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// A player that can both play and be evolved, as the agents of a `Pool` are
+pub trait Player: MovePlayer + Evolvable {}
+
+impl<T: MovePlayer + Evolvable> Player for T {}
+
+/// Floats compare exactly, so two agents are only equal when their weights have the same
+/// bits, as after saving and loading one
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Agent<Plr: Player> {
     pub player: Plr,
     pub fitness: i32,
 }
 
+impl<Plr: Player + Clone> Clone for Agent<Plr> {
+    fn clone(&self) -> Self {
+        Self {
+            player: self.player.clone(),
+            fitness: self.fitness,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.player.clone_from(&source.player);
+        self.fitness = source.fitness;
+    }
+}
+
 impl<Plr> Agent<Plr>
 where
     Plr: Player,
@@ -24,3 +126,231 @@ where
         Self { fitness: 0, player }
     }
 }
+
+/// How `choose_move` picks between columns that share the highest score
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum TieBreak {
+    /// The leftmost tied column
+    #[default]
+    First,
+    /// The tied column closest to the center, which is usually the stronger move
+    Center,
+    /// A uniformly random tied column, for more varied self-play
+    Random,
+}
+
+impl TieBreak {
+    pub fn from_string(s: &str) -> TieBreak {
+        match s {
+            "first" => TieBreak::First,
+            "center" => TieBreak::Center,
+            "random" => TieBreak::Random,
+            _ => panic!("invalid tie break: {}", s),
+        }
+    }
+}
+
+/// What happens when a player scores a full column above every column it can still play
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum IllegalMove {
+    /// Play the highest scored column that isn't full instead
+    #[default]
+    Skip,
+    /// Forfeit the game, so that networks are pushed to learn which columns are full
+    Lose,
+}
+
+impl IllegalMove {
+    pub fn from_string(s: &str) -> IllegalMove {
+        match s {
+            "skip" => IllegalMove::Skip,
+            "lose" => IllegalMove::Lose,
+            _ => panic!("invalid illegal move handling: {}", s),
+        }
+    }
+}
+
+/// Whether the highest of `scores` only belongs to columns of `board` that are full
+pub fn prefers_full_column(scores: &[N; 7], board: &game::Board) -> bool {
+    let best = |legal: bool| {
+        (0..scores.len())
+            .filter(|column| board.is_legal(*column) == legal)
+            .map(|column| scores[column])
+            .fold(N::NEG_INFINITY, N::max)
+    };
+    best(false) > best(true)
+}
+
+/// Picks the highest scored column that isn't full, breaking ties with `tie_break`.
+/// Returns `None` when every column is full.
+pub fn choose_move(scores: &[N; 7], board: &game::Board, tie_break: TieBreak) -> Option<usize> {
+    choose_move_with(scores, board, tie_break, &mut rand::thread_rng())
+}
+
+/// Same as `choose_move`, but random tie-breaks draw from `rng`
+pub fn choose_move_with<R: Rng + ?Sized>(
+    scores: &[N; 7],
+    board: &game::Board,
+    tie_break: TieBreak,
+    rng: &mut R,
+) -> Option<usize> {
+    let legal = (0..scores.len())
+        .filter(|column| board.is_legal(*column))
+        .collect::<Vec<_>>();
+    let best = legal
+        .iter()
+        .map(|column| scores[*column])
+        .fold(N::NEG_INFINITY, N::max);
+    let tied = legal
+        .iter()
+        .cloned()
+        .filter(|column| scores[*column] == best)
+        .collect::<Vec<_>>();
+    // Only happens when every legal score is NaN
+    if tied.is_empty() {
+        return legal.first().cloned();
+    }
+
+    match tie_break {
+        TieBreak::First => tied.first().cloned(),
+        TieBreak::Center => {
+            let center = (scores.len() - 1) as isize / 2;
+            tied.iter()
+                .cloned()
+                .min_by_key(|column| (*column as isize - center).abs())
+        }
+        TieBreak::Random => tied.choose(rng).cloned(),
+    }
+}
+
+#[cfg(test)]
+mod agent_tests {
+    use super::*;
+
+    const TIED: [N; 7] = [1.0; 7];
+
+    #[test]
+    fn tie_break_first() {
+        let board = game::Board::new();
+        assert_eq!(Some(0), choose_move(&TIED, &board, TieBreak::First));
+        let scores = [0.0, 0.5, 0.0, 0.0, 0.5, 0.0, 0.0];
+        assert_eq!(Some(1), choose_move(&scores, &board, TieBreak::First));
+    }
+
+    #[test]
+    fn tie_break_center() {
+        let mut board = game::Board::new();
+        assert_eq!(Some(3), choose_move(&TIED, &board, TieBreak::Center));
+        for _ in 0..6 {
+            board.insert_top(3, game::Spot::RED);
+        }
+        // Closest columns to a full center are equally far, the leftmost wins
+        assert_eq!(Some(2), choose_move(&TIED, &board, TieBreak::Center));
+    }
+
+    #[test]
+    fn tie_break_random() {
+        let board = game::Board::new();
+        let mut seen = [false; 7];
+        for _ in 0..500 {
+            seen[choose_move(&TIED, &board, TieBreak::Random).unwrap()] = true;
+        }
+        assert!(seen.iter().all(|column| *column));
+        // Only tied columns are picked
+        let scores = [0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        for _ in 0..50 {
+            let column = choose_move(&scores, &board, TieBreak::Random).unwrap();
+            assert!(column == 1 || column == 4);
+        }
+    }
+
+    #[test]
+    fn player_names() {
+        use crate::ai::{MinimaxPlayer, NNPlayer, RandomPlayer};
+
+        struct Unnamed;
+        impl MovePlayer for Unnamed {
+            fn get_move(&self, _: [[game::Spot; 6]; 7]) -> [N; 7] {
+                [0.0; 7]
+            }
+        }
+
+        assert_eq!("Unnamed", Unnamed.name());
+        assert_eq!("random", RandomPlayer::new().name());
+        let nn = NNPlayer::new_from_param(
+            vec![42, 8, 7],
+            vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+        );
+        assert_eq!("nn(42-8-7)", nn.name());
+        let minimax = MinimaxPlayer::new(std::time::Duration::from_millis(250));
+        assert_eq!("minimax(250ms)", minimax.name());
+    }
+
+    #[test]
+    fn cbor_round_trip() {
+        use crate::ai::NNPlayer;
+
+        let mut agent = Agent::new(NNPlayer::new_from_param(
+            vec![42, 8, 7],
+            vec![nn::Activation::RELU, nn::Activation::Sigmoid],
+        ));
+        agent.fitness = -3;
+        // Settings of the run aren't saved, and don't make a loaded agent any different
+        agent.player.freeze_layers(&[0]);
+        agent.player.set_red_perspective(true);
+        let loaded: Agent<NNPlayer> =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&agent).unwrap()).unwrap();
+        assert_eq!(agent, loaded);
+
+        let other = Agent::new(NNPlayer::new_from_param(
+            vec![42, 8, 7],
+            vec![nn::Activation::RELU, nn::Activation::Sigmoid],
+        ));
+        assert_ne!(agent.player, other.player);
+    }
+
+    #[test]
+    fn choose_move_full_board() {
+        let mut board = game::Board::new();
+        for column in 0..7 {
+            for _ in 0..6 {
+                board.insert_top(column, game::Spot::RED);
+            }
+        }
+        assert_eq!(None, choose_move(&TIED, &board, TieBreak::First));
+    }
+
+    #[test]
+    fn mixed_move_players() {
+        use crate::ai::{MinimaxPlayer, NNPlayer, RandomPlayer};
+
+        let players: Vec<Box<dyn MovePlayer>> = vec![
+            Box::new(RandomPlayer::new()),
+            Box::new(NNPlayer::new_from_param(
+                vec![42, 8, 7],
+                vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+            )),
+            Box::new(MinimaxPlayer::new(std::time::Duration::ZERO)),
+        ];
+        let names = players
+            .iter()
+            .map(|player| player.name())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["random", "nn(42-8-7)", "minimax(0ms)"], names);
+
+        // Every type plays every other through the same interface
+        for red in players.iter() {
+            for yellow in players.iter() {
+                let (_, board) = game::play_game(
+                    game::Board::new(),
+                    game::Spot::RED,
+                    red.as_ref(),
+                    yellow.as_ref(),
+                    TieBreak::First,
+                    IllegalMove::Skip,
+                );
+                assert!(board.moves() >= 7);
+            }
+        }
+    }
+}
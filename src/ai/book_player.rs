@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    agent::{choose_move, TieBreak},
+    nn, Evolvable, MinimaxPlayer, MovePlayer, N,
+};
+use crate::game::{Board, Spot};
+use crate::helpers::{self, SaveFormat};
+
+/// Search time of the fallback of a `BookPlayer` made by `new_from_param`
+const FALLBACK_MOVE_TIME: Duration = Duration::from_millis(100);
+
+/// Key of a position: for every column, 7 bits holding a set bit for each red piece from the
+/// bottom up, and a set bit just above the top piece. Every position has its own key.
+fn position_key(columns: impl Iterator<Item = [Spot; 6]>) -> u64 {
+    let mut key = 0;
+    for (i, column) in columns.enumerate() {
+        let pieces = column.iter().rev().take_while(|spot| **spot != Spot::EMPTY);
+        let mut height = 0;
+        for spot in pieces {
+            if *spot == Spot::RED {
+                key |= 1 << (i * 7 + height);
+            }
+            height += 1;
+        }
+        key |= 1 << (i * 7 + height);
+    }
+    key
+}
+
+/// Key shared by a position and its mirror image, the smaller of their two keys, and whether
+/// it is the mirror image's
+fn canonical_key(positions: &[[Spot; 6]; 7]) -> (u64, bool) {
+    let key = position_key(positions.iter().cloned());
+    let mirrored = position_key(positions.iter().rev().cloned());
+    (key.min(mirrored), mirrored < key)
+}
+
+/// Best moves of opening positions, keyed by `canonical_key`. A position and its mirror image
+/// share an entry, which holds the column for the position whose key it is.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Book {
+    /// Positions with fewer pieces than this are all in the book
+    pub depth: usize,
+    moves: HashMap<u64, usize>,
+}
+
+impl Book {
+    /// Number of positions in the book, not counting mirror images
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Records `column` as the best move in `positions`, and the mirrored column in its
+    /// mirror image
+    pub fn insert(&mut self, positions: &[[Spot; 6]; 7], column: usize) {
+        let (key, mirrored) = canonical_key(positions);
+        self.moves
+            .insert(key, if mirrored { 6 - column } else { column });
+    }
+
+    /// Best move in `positions`, if the book has it
+    pub fn lookup(&self, positions: &[[Spot; 6]; 7]) -> Option<usize> {
+        let (key, mirrored) = canonical_key(positions);
+        self.moves
+            .get(&key)
+            .map(|column| if mirrored { 6 - column } else { *column })
+    }
+
+    /// Reads a book written by `save`, in either `SaveFormat`
+    pub fn load(path: &Path) -> Result<Book, Box<dyn Error>> {
+        let book: Book = helpers::read_save(path)?;
+        if book.is_empty() {
+            return Err(format!("{} holds no positions", path.display()).into());
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        helpers::write_save(path, self, SaveFormat::Cbor)
+    }
+}
+
+/// Builds a book of every position reached in the first `depth` moves of a game that isn't
+/// over yet, each searched by a `MinimaxPlayer` for `move_time`. This isn't a solver: the
+/// moves are only as good as the time-limited search, and come closer to perfect play the
+/// longer it gets. Positions of the same number of pieces are searched in parallel.
+pub fn generate_book(depth: usize, move_time: Duration) -> Result<Book, Box<dyn Error>> {
+    if depth == 0 {
+        return Err("a book of depth 0 would hold no positions".into());
+    }
+    let mut book = Book {
+        depth,
+        moves: HashMap::new(),
+    };
+    let mut level = vec![Board::new()];
+    for pieces in 0..depth {
+        let solved = level
+            .par_iter()
+            .map(|board| {
+                let scores = MinimaxPlayer::new(move_time).get_move(board.positions);
+                let column = choose_move(&scores, board, TieBreak::Center)
+                    .expect("positions in the book aren't full");
+                (board.positions, column)
+            })
+            .collect::<Vec<_>>();
+        for (positions, column) in solved {
+            book.insert(&positions, column);
+        }
+        if pieces + 1 == depth {
+            break;
+        }
+
+        // Every position one move further that nobody has won yet, each mirror image once
+        let color = match pieces % 2 {
+            0 => Spot::RED,
+            _ => Spot::YELLOW,
+        };
+        let mut next = HashMap::new();
+        for board in &level {
+            for column in (0..7).filter(|column| board.is_legal(*column)) {
+                let mut child = board.clone();
+                if let (true, None) = child.insert_top(column, color) {
+                    next.entry(canonical_key(&child.positions).0)
+                        .or_insert(child);
+                }
+            }
+        }
+        level = next.into_values().collect();
+    }
+    Ok(book)
+}
+
+/// Plays the move of an opening book in every position the book has, and falls back to a
+/// minimax search everywhere else. A fixed opponent: it has nothing to evolve, and one made
+/// by `new_from_param` knows no openings.
+#[derive(Clone, Debug)]
+pub struct BookPlayer {
+    pub book: Book,
+    pub fallback: MinimaxPlayer,
+}
+
+impl BookPlayer {
+    pub fn new(book: Book, fallback: MinimaxPlayer) -> Self {
+        Self { book, fallback }
+    }
+}
+
+impl Evolvable for BookPlayer {
+    fn new_from_param(_structure: Vec<usize>, _activations: Vec<nn::Activation>) -> Self {
+        Self::new(Book::default(), MinimaxPlayer::new(FALLBACK_MOVE_TIME))
+    }
+}
+
+impl MovePlayer for BookPlayer {
+    fn get_move(&self, positions: [[Spot; 6]; 7]) -> [N; 7] {
+        match self.book.lookup(&positions) {
+            Some(column) => {
+                let mut scores = [0.0; 7];
+                scores[column] = 1.0;
+                scores
+            }
+            None => self.fallback.get_move(positions),
+        }
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "book({} positions, then {})",
+            self.book.len(),
+            self.fallback.name()
+        )
+    }
+}
+
+#[cfg(test)]
+mod book_player_tests {
+    use super::*;
+
+    fn play(moves: &[usize]) -> Board {
+        let mut board = Board::new();
+        for (i, column) in moves.iter().enumerate() {
+            let color = if i % 2 == 0 { Spot::RED } else { Spot::YELLOW };
+            board.insert_top(*column, color);
+        }
+        board
+    }
+
+    #[test]
+    fn plays_book_move() {
+        // Moves a search would never pick, so that only the book can explain them
+        let mut book = Book::default();
+        book.insert(&Board::new().positions, 0);
+        book.insert(&play(&[1]).positions, 6);
+        let player = BookPlayer::new(book, MinimaxPlayer::new(Duration::ZERO));
+
+        let board = Board::new();
+        let scores = player.get_move(board.positions);
+        assert_eq!(Some(0), choose_move(&scores, &board, TieBreak::Center));
+        let board = play(&[1]);
+        let scores = player.get_move(board.positions);
+        assert_eq!(Some(6), choose_move(&scores, &board, TieBreak::Center));
+        // The mirror image of a book position plays the mirrored move
+        let board = play(&[5]);
+        let scores = player.get_move(board.positions);
+        assert_eq!(Some(0), choose_move(&scores, &board, TieBreak::Center));
+
+        // Out of book, the search takes the win
+        let board = play(&[1, 2, 1, 2, 1, 3]);
+        assert_eq!(None, player.book.lookup(&board.positions));
+        let scores = player.get_move(board.positions);
+        assert_eq!(Some(1), choose_move(&scores, &board, TieBreak::Center));
+    }
+
+    #[test]
+    fn generates_book() {
+        assert!(generate_book(0, Duration::ZERO).is_err());
+        let book = generate_book(2, Duration::ZERO).unwrap();
+        // The empty board, and one move in each of columns 0 to 3 with the others mirrored
+        assert_eq!(5, book.len());
+        for column in 0..7 {
+            let board = play(&[column]);
+            assert!(board.is_legal(book.lookup(&board.positions).unwrap()));
+        }
+        assert_eq!(None, book.lookup(&play(&[3, 3]).positions));
+
+        let path = std::env::temp_dir().join(format!("fourai_book_{}", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = Book::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(book, loaded.unwrap());
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use super::N;
+use crate::game::Spot;
+
+/// Turns a board into the inputs of a network
+pub trait BoardEncoder {
+    /// Number of values `encode` returns, i.e. the input size the network needs
+    fn input_size(&self) -> usize;
+    fn encode(&self, positions: &[[Spot; 6]; 7], to_move: Spot) -> Vec<N>;
+}
+
+/// One value per spot: `1` for red, `-1` for yellow and `0` for empty (42 inputs)
+pub struct ScalarEncoder;
+
+impl BoardEncoder for ScalarEncoder {
+    fn input_size(&self) -> usize {
+        42
+    }
+
+    fn encode(&self, positions: &[[Spot; 6]; 7], _to_move: Spot) -> Vec<N> {
+        positions.iter().flatten().map(|x| x.into_rep()).collect()
+    }
+}
+
+/// A plane of red pieces followed by a plane of yellow pieces, with `1` where the plane's
+/// color has a piece and `0` elsewhere (84 inputs)
+pub struct TwoPlaneEncoder;
+
+impl BoardEncoder for TwoPlaneEncoder {
+    fn input_size(&self) -> usize {
+        84
+    }
+
+    fn encode(&self, positions: &[[Spot; 6]; 7], _to_move: Spot) -> Vec<N> {
+        [Spot::RED, Spot::YELLOW]
+            .iter()
+            .flat_map(|color| {
+                positions
+                    .iter()
+                    .flatten()
+                    .map(move |spot| (spot == color) as usize as N)
+            })
+            .collect()
+    }
+}
+
+/// Encoders that can be selected when training
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum Encoding {
+    #[default]
+    Scalar,
+    TwoPlane,
+}
+
+impl Encoding {
+    pub fn from_string(s: &str) -> Encoding {
+        match s {
+            "scalar" => Encoding::Scalar,
+            "two-plane" => Encoding::TwoPlane,
+            _ => panic!("invalid encoding: {}", s),
+        }
+    }
+
+    /// Number of inputs every spot of the board takes
+    pub fn inputs_per_spot(&self) -> usize {
+        match self {
+            Encoding::Scalar => 1,
+            Encoding::TwoPlane => 2,
+        }
+    }
+
+    pub fn encoder(&self) -> &'static dyn BoardEncoder {
+        match self {
+            Encoding::Scalar => &ScalarEncoder,
+            Encoding::TwoPlane => &TwoPlaneEncoder,
+        }
+    }
+}
+
+/// Color whose turn it is, given that red always moves first
+pub fn side_to_move(positions: &[[Spot; 6]; 7]) -> Spot {
+    let pieces = positions
+        .iter()
+        .flatten()
+        .filter(|spot| **spot != Spot::EMPTY)
+        .count();
+    match pieces % 2 {
+        0 => Spot::RED,
+        _ => Spot::YELLOW,
+    }
+}
+
+/// `positions` with every red piece turned yellow and every yellow piece turned red
+pub fn swap_colors(positions: &[[Spot; 6]; 7]) -> [[Spot; 6]; 7] {
+    let mut swapped = *positions;
+    for spot in swapped.iter_mut().flatten() {
+        *spot = match spot {
+            Spot::RED => Spot::YELLOW,
+            Spot::YELLOW => Spot::RED,
+            Spot::EMPTY => Spot::EMPTY,
+        };
+    }
+    swapped
+}
+
+#[cfg(test)]
+mod encoder_tests {
+    use super::*;
+    use crate::game::Board;
+
+    fn test_board() -> Board {
+        let mut board = Board::new();
+        board.insert_top(0, Spot::RED);
+        board.insert_top(6, Spot::YELLOW);
+        board.insert_top(0, Spot::RED);
+        board
+    }
+
+    #[test]
+    fn scalar_encoder() {
+        let board = test_board();
+        let inputs = ScalarEncoder.encode(&board.positions, Spot::YELLOW);
+        assert_eq!(ScalarEncoder.input_size(), inputs.len());
+        // Column 0, bottom two rows, then column 6's bottom row
+        assert_eq!(&[0.0, 0.0, 0.0, 0.0, 1.0, 1.0], &inputs[0..6]);
+        assert_eq!(-1.0, inputs[41]);
+        assert_eq!(1.0, inputs.iter().sum::<N>());
+    }
+
+    #[test]
+    fn two_plane_encoder() {
+        let board = test_board();
+        let inputs = TwoPlaneEncoder.encode(&board.positions, Spot::YELLOW);
+        assert_eq!(TwoPlaneEncoder.input_size(), inputs.len());
+        let (red, yellow) = inputs.split_at(42);
+        assert_eq!(&[0.0, 0.0, 0.0, 0.0, 1.0, 1.0], &red[0..6]);
+        assert_eq!(2.0, red.iter().sum::<N>());
+        assert_eq!(1.0, yellow[41]);
+        assert_eq!(1.0, yellow.iter().sum::<N>());
+    }
+
+    #[test]
+    fn side_to_move_alternates() {
+        let mut board = Board::new();
+        assert_eq!(Spot::RED, side_to_move(&board.positions));
+        board.insert_top(3, Spot::RED);
+        assert_eq!(Spot::YELLOW, side_to_move(&board.positions));
+        assert_eq!(Spot::YELLOW, side_to_move(&test_board().positions));
+    }
+
+    #[test]
+    fn swap_colors_negates_scalar_inputs() {
+        let board = test_board();
+        let inputs = ScalarEncoder.encode(&board.positions, Spot::YELLOW);
+        let swapped = ScalarEncoder.encode(&swap_colors(&board.positions), Spot::RED);
+        assert!(inputs.iter().zip(&swapped).all(|(a, b)| *a == -b));
+        assert_eq!(board.positions, swap_colors(&swap_colors(&board.positions)));
+    }
+}
@@ -0,0 +1,298 @@
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use super::{agent::SearchStats, encoder, nn, Evolvable, MovePlayer, N};
+use crate::game::{Board, Spot};
+
+/// Score of winning on the first move. Every piece on the board at the time of the win takes
+/// one away, so that faster wins score higher.
+const WIN_SCORE: i32 = 1000;
+
+/// Columns are searched center first, since central moves are usually the strongest and let
+/// alpha-beta pruning cut off more of the tree
+const MOVE_ORDER: [usize; 7] = [3, 2, 4, 1, 5, 0, 6];
+
+/// Plays by negamax search with alpha-beta pruning. Searches one ply deeper at a time until
+/// `move_time` runs out, and plays the best move of the deepest search that finished.
+#[derive(Clone, Debug)]
+pub struct MinimaxPlayer {
+    /// Wall-clock time budget of every move
+    pub move_time: Duration,
+    /// Probability of deliberately playing a random worse column instead of the best one
+    pub blunder_rate: N,
+    /// Cost of the last move, reset at the start of every move
+    stats: Cell<SearchStats>,
+    /// Decides when and how to blunder
+    rng: RefCell<StdRng>,
+}
+
+impl MinimaxPlayer {
+    pub fn new(move_time: Duration) -> Self {
+        Self {
+            move_time,
+            blunder_rate: 0.0,
+            stats: Cell::default(),
+            rng: RefCell::new(StdRng::seed_from_u64(0)),
+        }
+    }
+
+    /// Plays a random worse column instead of the best one with probability `blunder_rate`,
+    /// drawing from an RNG seeded with `seed`, for an opponent of adjustable strength
+    pub fn with_blunders(mut self, blunder_rate: N, seed: u64) -> Self {
+        self.blunder_rate = blunder_rate;
+        self.rng = RefCell::new(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    /// `scores` changed so that a random legal column scoring below the best one scores
+    /// highest, with probability `blunder_rate`. Unchanged when every legal column is best.
+    fn blunder(&self, mut scores: [N; 7]) -> [N; 7] {
+        let mut rng = self.rng.borrow_mut();
+        if self.blunder_rate <= 0.0 || rng.gen::<N>() >= self.blunder_rate {
+            return scores;
+        }
+        let best = scores.iter().cloned().fold(N::NEG_INFINITY, N::max);
+        let worse = (0..scores.len())
+            .filter(|column| scores[*column] > N::NEG_INFINITY && scores[*column] < best)
+            .collect::<Vec<_>>();
+        if let Some(column) = worse.choose(&mut *rng) {
+            scores[*column] = best + 1.0;
+        }
+        scores
+    }
+
+    /// Scores of every column for `color`, from the deepest search that finished in time.
+    /// The first ply is always searched completely, so a legal move is found however small
+    /// the budget is.
+    fn search(&self, board: &mut Board, color: Spot) -> [N; 7] {
+        let start = Instant::now();
+        let deadline = start + self.move_time;
+        let mut nodes = 0;
+        let mut scores = root_scores(board, color, 1, None, &mut nodes).unwrap();
+        for depth in 2..=board.remaining_moves() {
+            match root_scores(board, color, depth, Some(deadline), &mut nodes) {
+                Some(deeper) => scores = deeper,
+                None => break,
+            }
+        }
+        self.stats.set(SearchStats {
+            nodes,
+            time: start.elapsed(),
+        });
+        scores
+    }
+}
+
+/// A fixed opponent: there is nothing to evolve, and one made by `new_from_param` only
+/// searches the first ply
+impl Evolvable for MinimaxPlayer {
+    fn new_from_param(_structure: Vec<usize>, _activations: Vec<nn::Activation>) -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl MovePlayer for MinimaxPlayer {
+    fn get_move(&self, positions: [[Spot; 6]; 7]) -> [N; 7] {
+        let grid = positions
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .rev()
+                    .cloned()
+                    .take_while(|spot| *spot != Spot::EMPTY)
+                    .collect()
+            })
+            .collect();
+        let mut board =
+            Board::from_position(grid).expect("pieces up to the first empty spot always fit");
+        let scores = self.search(&mut board, encoder::side_to_move(&positions));
+        self.blunder(scores)
+    }
+
+    fn name(&self) -> String {
+        if self.blunder_rate > 0.0 {
+            format!(
+                "minimax({}ms, {}% blunders)",
+                self.move_time.as_millis(),
+                self.blunder_rate * 100.0
+            )
+        } else {
+            format!("minimax({}ms)", self.move_time.as_millis())
+        }
+    }
+
+    fn search_stats(&self) -> Option<SearchStats> {
+        Some(self.stats.get())
+    }
+}
+
+fn opponent(color: Spot) -> Spot {
+    match color {
+        Spot::RED => Spot::YELLOW,
+        _ => Spot::RED,
+    }
+}
+
+/// Plays `column` for `color` and scores the result for `color` with a search `depth` plies
+/// deep, or `None` if `deadline` passed first. Takes the move back before returning. Every
+/// position played is counted in `nodes`.
+fn score_move(
+    board: &mut Board,
+    column: usize,
+    color: Spot,
+    depth: usize,
+    window: (i32, i32),
+    deadline: Option<Instant>,
+    nodes: &mut u64,
+) -> Option<i32> {
+    *nodes += 1;
+    let (_, winner) = board.insert_top(column, color);
+    let score = if winner == Some(color) {
+        Some(WIN_SCORE - board.moves() as i32)
+    } else if board.is_full() || depth <= 1 {
+        Some(0)
+    } else {
+        negamax(
+            board,
+            opponent(color),
+            depth - 1,
+            (-window.1, -window.0),
+            deadline,
+            nodes,
+        )
+        .map(|score| -score)
+    };
+    board.undo_move();
+    score
+}
+
+/// Score of the position for `color`, the side to move
+fn negamax(
+    board: &mut Board,
+    color: Spot,
+    depth: usize,
+    (mut alpha, beta): (i32, i32),
+    deadline: Option<Instant>,
+    nodes: &mut u64,
+) -> Option<i32> {
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        return None;
+    }
+
+    let mut best = -WIN_SCORE;
+    for column in MOVE_ORDER {
+        if !board.is_legal(column) {
+            continue;
+        }
+        let score = score_move(board, column, color, depth, (alpha, beta), deadline, nodes)?;
+        best = best.max(score);
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    Some(best)
+}
+
+/// Scores every legal column with a full window, so that equal scores are real ties.
+/// Full columns score negative infinity.
+fn root_scores(
+    board: &mut Board,
+    color: Spot,
+    depth: usize,
+    deadline: Option<Instant>,
+    nodes: &mut u64,
+) -> Option<[N; 7]> {
+    let mut scores = [N::NEG_INFINITY; 7];
+    for column in MOVE_ORDER {
+        if board.is_legal(column) {
+            let window = (-WIN_SCORE, WIN_SCORE);
+            scores[column] = score_move(board, column, color, depth, window, deadline, nodes)? as N;
+        }
+    }
+    Some(scores)
+}
+
+#[cfg(test)]
+mod minimax_player_tests {
+    use super::*;
+    use crate::ai::agent::{choose_move, TieBreak};
+
+    fn play(moves: &[usize]) -> Board {
+        let mut board = Board::new();
+        for (i, column) in moves.iter().enumerate() {
+            let color = if i % 2 == 0 { Spot::RED } else { Spot::YELLOW };
+            board.insert_top(*column, color);
+        }
+        board
+    }
+
+    #[test]
+    fn tiny_budget_returns_legal_move() {
+        let player = MinimaxPlayer::new(Duration::ZERO);
+        // Center column full
+        let board = play(&[3, 3, 3, 3, 3, 3]);
+        let start = Instant::now();
+        let scores = player.get_move(board.positions);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let column = choose_move(&scores, &board, TieBreak::First).unwrap();
+        assert!(board.is_legal(column));
+        assert_eq!(N::NEG_INFINITY, scores[3]);
+    }
+
+    #[test]
+    fn tiny_budget_takes_immediate_win() {
+        let player = MinimaxPlayer::new(Duration::ZERO);
+        let board = play(&[0, 1, 0, 1, 0, 2]);
+        let scores = player.get_move(board.positions);
+        assert_eq!(Some(0), choose_move(&scores, &board, TieBreak::First));
+    }
+
+    #[test]
+    fn blocks_immediate_loss() {
+        // Red threatens to complete the bottom row in column 3
+        let board = play(&[0, 6, 1, 6, 2]);
+        let player = MinimaxPlayer::new(Duration::from_millis(50));
+        let scores = player.get_move(board.positions);
+        assert_eq!(Some(3), choose_move(&scores, &board, TieBreak::First));
+    }
+
+    #[test]
+    fn search_stats() {
+        let player = MinimaxPlayer::new(Duration::ZERO);
+        assert_eq!(Some(SearchStats::default()), player.search_stats());
+
+        // Without any budget only the first ply is searched, plus the first node of the
+        // second ply before the deadline is noticed
+        player.get_move(Board::new().positions);
+        assert_eq!(8, player.search_stats().unwrap().nodes);
+        player.get_move(Board::new().positions);
+        assert_eq!(8, player.search_stats().unwrap().nodes);
+        player.get_move(play(&[3, 3, 3, 3, 3, 3]).positions);
+        assert_eq!(7, player.search_stats().unwrap().nodes);
+    }
+
+    #[test]
+    fn blunder_rate() {
+        // Red wins in column 0, yellow in column 1, and every other move lets the opponent win
+        let won = play(&[0, 1, 0, 1, 0, 1]);
+        // Red completes the bottom row in column 3
+        let row = play(&[0, 6, 1, 6, 2, 5]);
+        for (board, win) in [(won, 0), (row, 3)] {
+            let perfect = MinimaxPlayer::new(Duration::from_millis(20)).with_blunders(0.0, 1);
+            for _ in 0..5 {
+                let scores = perfect.get_move(board.positions);
+                assert_eq!(Some(win), choose_move(&scores, &board, TieBreak::First));
+            }
+
+            let blundering = MinimaxPlayer::new(Duration::ZERO).with_blunders(1.0, 1);
+            let scores = blundering.get_move(board.positions);
+            let column = choose_move(&scores, &board, TieBreak::First).unwrap();
+            assert_ne!(win, column);
+            assert!(board.is_legal(column));
+        }
+    }
+}
@@ -1,4 +1,7 @@
 pub mod agent;
+mod book_player;
+pub mod encoder;
+mod minimax_player;
 mod nn_player;
 mod prec;
 mod random_player;
@@ -8,7 +11,9 @@ pub mod nn;
 #[macro_use]
 pub mod pool;
 
-use agent::Player;
+use agent::{Evolvable, MovePlayer};
+pub use book_player::{generate_book, Book, BookPlayer};
+pub use minimax_player::MinimaxPlayer;
 pub use nn_player::NNPlayer;
 pub use prec::N;
-pub use random_player::RandomPlayer;
+pub use random_player::{RandomPlayer, SeededRandomPlayer};
@@ -1,11 +1,11 @@
-use rand::Rng;
+use rand::{Rng, RngCore};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use super::N;
 use crate::matrix;
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
 pub enum Activation {
     Sigmoid,
     ELU,
@@ -37,26 +37,82 @@ impl Activation {
     }
 }
 
+impl Activation {
+    /// Whether `y`, an output of this activation, is in a region where the activation barely
+    /// responds to its input: near 0 or 1 for sigmoid, 0 for ReLU and the lower bound for ELU
+    pub fn is_saturated(&self, y: N) -> bool {
+        match self {
+            Activation::Sigmoid => !(0.01..=0.99).contains(&y),
+            Activation::RELU => y <= 0.0,
+            Activation::ELU => y <= -0.19,
+        }
+    }
+}
+
+/// Fraction of units in every layer that are saturated for all of `traces` (see
+/// `Activation::is_saturated`), where each trace holds the output of every layer for one input,
+/// as returned by `forward_trace`. A unit that is saturated for every input is effectively
+/// constant, i.e. dead.
+pub fn saturation(activations: &[Activation], traces: &[Vec<matrix::Matrix<N>>]) -> Vec<N> {
+    activations
+        .iter()
+        .enumerate()
+        .map(|(layer, activation)| {
+            let units = traces.first().map_or(0, |trace| trace[layer].values.len());
+            let saturated = (0..units)
+                .filter(|unit| {
+                    traces
+                        .iter()
+                        .all(|trace| activation.is_saturated(trace[layer].values[*unit]))
+                })
+                .count();
+            saturated as N / units.max(1) as N
+        })
+        .collect()
+}
+
 impl fmt::Debug for Activation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Activation").finish()
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct NN {
     structure: Vec<usize>,
     activations: Vec<Activation>,
     pub weights: Vec<matrix::Matrix<N>>,
 }
 
+impl Clone for NN {
+    fn clone(&self) -> Self {
+        Self {
+            structure: self.structure.clone(),
+            activations: self.activations.clone(),
+            weights: self.weights.clone(),
+        }
+    }
+
+    /// Copies the weights into the existing matrices of `self`, so breeding a population
+    /// into the agents of the last one doesn't allocate
+    fn clone_from(&mut self, source: &Self) {
+        self.structure.clone_from(&source.structure);
+        self.activations.clone_from(&source.activations);
+        self.weights.clone_from(&source.weights);
+    }
+}
+
 impl NN {
-    pub fn new_rand(structure: Vec<usize>, activations: Vec<Activation>) -> Self {
+    /// A network of random weights, drawn from `rng`
+    pub fn new_rand(
+        structure: Vec<usize>,
+        activations: Vec<Activation>,
+        rng: &mut dyn RngCore,
+    ) -> Self {
         debug_assert_eq!(structure.len() - 1, activations.len());
 
         let mut weights: Vec<matrix::Matrix<N>> = Vec::with_capacity(structure.len());
 
-        let mut rng = rand::thread_rng();
         for i in 0..structure.len() - 1 {
             weights.push(matrix::Matrix::from_rand(
                 structure[i + 1],
@@ -72,15 +128,421 @@ impl NN {
         }
     }
 
+    // With the arena feature, `NNPlayer` runs `ArenaNN` and only the conversions use `NN`
+    #[cfg_attr(feature = "arena", allow(dead_code))]
     pub fn forward(&self, input: Vec<N>) -> matrix::Matrix<N> {
+        self.run(input, None)
+    }
+
+    /// `forward` of every one of `inputs` at once, with a single matrix multiplication per
+    /// layer. Row `i` of the result is the output for `inputs[i]`, equal to `forward` of it
+    /// up to float rounding.
+    #[cfg_attr(feature = "arena", allow(dead_code))]
+    pub fn forward_batch(&self, inputs: &[Vec<N>]) -> matrix::Matrix<N> {
+        let batch = inputs.len();
+        if batch == 0 {
+            return matrix::Matrix::alloca(0, self.structure.last().copied().unwrap_or(0));
+        }
+        // One column per input
+        let mut activation = matrix::Matrix::concat_cols(
+            &inputs
+                .iter()
+                .map(|input| matrix::Matrix::into_row(input.clone()))
+                .collect::<Vec<_>>(),
+        );
+
+        for (weights, activation_fn) in self.weights.iter().zip(&self.activations) {
+            activation.push(&mut vec![1.0; batch]); // Push biases
+            activation = weights * &activation;
+            activation.map(&mut activation_fn.as_fn());
+        }
+
+        activation.T()
+    }
+
+    /// Same as `forward`, but also returns the post-activation output of every layer, the
+    /// last one being the output itself
+    #[cfg_attr(feature = "arena", allow(dead_code))]
+    pub fn forward_trace(&self, input: Vec<N>) -> (matrix::Matrix<N>, Vec<matrix::Matrix<N>>) {
+        let mut trace = Vec::with_capacity(self.weights.len());
+        let output = self.run(input, Some(&mut trace));
+        (output, trace)
+    }
+
+    /// Runs the network on `input`, recording every layer's output to `trace` if given
+    #[cfg_attr(feature = "arena", allow(dead_code))]
+    fn run(
+        &self,
+        input: Vec<N>,
+        mut trace: Option<&mut Vec<matrix::Matrix<N>>>,
+    ) -> matrix::Matrix<N> {
         let mut activation = matrix::Matrix::into_row(input);
 
         for (weights, activation_fn) in self.weights.iter().zip(&self.activations) {
             activation.push(&mut vec![1.0]); // Push bias
             activation = weights * &activation;
             activation.map(&mut activation_fn.as_fn());
+            if let Some(trace) = trace.as_mut() {
+                trace.push(activation.clone());
+            }
         }
 
         activation
     }
+
+    /// Same as `forward`, computed with plain loops over the weights instead of BLAS, to
+    /// cross-check the matrix multiplication in tests
+    #[cfg(test)]
+    pub fn forward_reference(&self, input: Vec<N>) -> matrix::Matrix<N> {
+        let mut activation = input;
+
+        for (weights, activation_fn) in self.weights.iter().zip(&self.activations) {
+            debug_assert_eq!(activation.len() + 1, weights.cols);
+            activation.push(1.0); // Bias
+            activation = (0..weights.rows)
+                .map(|row| {
+                    let mut sum = 0.0;
+                    for (col, x) in activation.iter().enumerate() {
+                        sum += weights.get(row, col) * x;
+                    }
+                    activation_fn.as_fn()(sum)
+                })
+                .collect();
+        }
+
+        matrix::Matrix::into_row(activation)
+    }
+
+    #[cfg_attr(feature = "arena", allow(dead_code))]
+    pub fn structure(&self) -> &[usize] {
+        &self.structure
+    }
+
+    #[cfg_attr(feature = "arena", allow(dead_code))]
+    pub fn activations(&self) -> &[Activation] {
+        &self.activations
+    }
+
+    /// Number of weight layers
+    pub fn layers(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Weights (and biases) of layer `i`, row-major
+    pub fn layer(&self, i: usize) -> &[N] {
+        &self.weights[i].values
+    }
+
+    #[cfg_attr(feature = "arena", allow(dead_code))]
+    pub fn layer_mut(&mut self, i: usize) -> &mut [N] {
+        &mut self.weights[i].values
+    }
+}
+
+/// An `NN` whose weights all live in one contiguous buffer, layer after layer in the same
+/// row-major order as `NN`'s matrices, so evaluating it doesn't chase a pointer per layer.
+/// It is serialized as an `NN`, so saves work with either storage.
+#[cfg(feature = "arena")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "NN", into = "NN")]
+pub struct ArenaNN {
+    structure: Vec<usize>,
+    activations: Vec<Activation>,
+    weights: Vec<N>,
+    /// Start of every layer in `weights`, followed by the length of `weights`
+    offsets: Vec<usize>,
+}
+
+#[cfg(feature = "arena")]
+impl Clone for ArenaNN {
+    fn clone(&self) -> Self {
+        Self {
+            structure: self.structure.clone(),
+            activations: self.activations.clone(),
+            weights: self.weights.clone(),
+            offsets: self.offsets.clone(),
+        }
+    }
+
+    /// Copies the weights into the existing buffer of `self`, like `NN::clone_from`
+    fn clone_from(&mut self, source: &Self) {
+        self.structure.clone_from(&source.structure);
+        self.activations.clone_from(&source.activations);
+        self.weights.clone_from(&source.weights);
+        self.offsets.clone_from(&source.offsets);
+    }
+}
+
+#[cfg(feature = "arena")]
+impl ArenaNN {
+    pub fn new_rand(
+        structure: Vec<usize>,
+        activations: Vec<Activation>,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        NN::new_rand(structure, activations, rng).into()
+    }
+
+    /// Same as `NN::forward`
+    pub fn forward(&self, input: Vec<N>) -> matrix::Matrix<N> {
+        self.run(input, None)
+    }
+
+    /// Same as `NN::forward_batch`, running the inputs one after the other
+    pub fn forward_batch(&self, inputs: &[Vec<N>]) -> matrix::Matrix<N> {
+        let outputs = inputs
+            .iter()
+            .map(|input| self.forward(input.clone()).T())
+            .collect::<Vec<_>>();
+        matrix::Matrix::concat_rows(&outputs)
+    }
+
+    /// Same as `NN::forward_trace`
+    pub fn forward_trace(&self, input: Vec<N>) -> (matrix::Matrix<N>, Vec<matrix::Matrix<N>>) {
+        let mut trace = Vec::with_capacity(self.layers());
+        let output = self.run(input, Some(&mut trace));
+        (output, trace)
+    }
+
+    fn run(
+        &self,
+        input: Vec<N>,
+        mut trace: Option<&mut Vec<matrix::Matrix<N>>>,
+    ) -> matrix::Matrix<N> {
+        let widest = self.structure.iter().max().copied().unwrap_or(0);
+        let mut activation = input;
+        let mut next = Vec::with_capacity(widest);
+
+        for i in 0..self.layers() {
+            self.forward_layer(i, &activation, &mut next);
+            std::mem::swap(&mut activation, &mut next);
+            if let Some(trace) = trace.as_mut() {
+                trace.push(matrix::Matrix::into_row(activation.clone()));
+            }
+        }
+
+        matrix::Matrix::into_row(activation)
+    }
+
+    /// Writes the output of layer `i` for `activation`, the output of the previous layer, to
+    /// `next`
+    fn forward_layer(&self, i: usize, activation: &[N], next: &mut Vec<N>) {
+        let activation_fn = self.activations[i].as_fn();
+        let cols = self.structure[i] + 1; // Add biases
+        debug_assert_eq!(activation.len() + 1, cols);
+
+        next.clear();
+        for row in self.layer(i).chunks_exact(cols) {
+            let (weights, bias) = row.split_at(cols - 1);
+            let sum = weights
+                .iter()
+                .zip(activation)
+                .fold(0.0, |sum, (weight, x)| sum + weight * x);
+            next.push(activation_fn(sum + bias[0]));
+        }
+    }
+
+    pub fn structure(&self) -> &[usize] {
+        &self.structure
+    }
+
+    pub fn activations(&self) -> &[Activation] {
+        &self.activations
+    }
+
+    pub fn layers(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn layer(&self, i: usize) -> &[N] {
+        &self.weights[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    pub fn layer_mut(&mut self, i: usize) -> &mut [N] {
+        &mut self.weights[self.offsets[i]..self.offsets[i + 1]]
+    }
+}
+
+#[cfg(feature = "arena")]
+impl From<NN> for ArenaNN {
+    fn from(nn: NN) -> Self {
+        let mut offsets = Vec::with_capacity(nn.layers() + 1);
+        let mut weights = Vec::with_capacity((0..nn.layers()).map(|i| nn.layer(i).len()).sum());
+        for i in 0..nn.layers() {
+            offsets.push(weights.len());
+            weights.extend_from_slice(nn.layer(i));
+        }
+        offsets.push(weights.len());
+
+        ArenaNN {
+            structure: nn.structure,
+            activations: nn.activations,
+            weights,
+            offsets,
+        }
+    }
+}
+
+#[cfg(feature = "arena")]
+impl From<ArenaNN> for NN {
+    fn from(arena: ArenaNN) -> Self {
+        let weights = (0..arena.layers())
+            .map(|i| {
+                matrix::Matrix::from(
+                    arena.layer(i).to_vec(),
+                    arena.structure[i + 1],
+                    arena.structure[i] + 1,
+                )
+            })
+            .collect();
+
+        NN {
+            structure: arena.structure,
+            activations: arena.activations,
+            weights,
+        }
+    }
+}
+
+#[cfg(test)]
+mod nn_tests {
+    use super::*;
+
+    fn test_nn() -> NN {
+        NN::new_rand(
+            vec![42, 16, 9, 7],
+            vec![Activation::ELU, Activation::RELU, Activation::Sigmoid],
+            &mut rand::thread_rng(),
+        )
+    }
+
+    fn test_input() -> Vec<N> {
+        (0..42).map(|x| (x % 3) as N - 1.0).collect()
+    }
+
+    #[test]
+    fn forward_batch() {
+        let nn = test_nn();
+        let inputs = (0..5)
+            .map(|i| test_input().iter().map(|x| x * i as N / 4.0).collect())
+            .collect::<Vec<Vec<N>>>();
+        let outputs = nn.forward_batch(&inputs);
+        assert_eq!((5, 7), (outputs.rows, outputs.cols));
+        for (input, output) in inputs.iter().zip(outputs.values.chunks_exact(7)) {
+            let single = nn.forward(input.clone());
+            assert!(single.approx_eq(&matrix::Matrix::into_row(output.to_vec()), 1e-6));
+        }
+        assert_eq!(0, nn.forward_batch(&[]).rows);
+    }
+
+    #[test]
+    fn forward_trace() {
+        let nn = test_nn();
+        let (output, trace) = nn.forward_trace(test_input());
+        assert_eq!(nn.layers(), trace.len());
+        assert_eq!(
+            vec![(16, 1), (9, 1), (7, 1)],
+            trace
+                .iter()
+                .map(|layer| (layer.rows, layer.cols))
+                .collect::<Vec<_>>()
+        );
+        let expected = nn.forward(test_input());
+        assert_eq!(expected, output);
+        assert_eq!(&expected, trace.last().unwrap());
+    }
+
+    #[test]
+    fn forward_reference() {
+        let mut rng = rand::thread_rng();
+        let default = NN::new_rand(
+            vec![42, 128, 256, 128, 7],
+            vec![Activation::Sigmoid; 4],
+            &mut rng,
+        );
+        for nn in [default, test_nn()].iter() {
+            for _ in 0..10 {
+                let input = (0..42)
+                    .map(|_| rng.gen_range(-1.0, 1.0))
+                    .collect::<Vec<N>>();
+                let expected = nn.forward_reference(input.clone());
+                let actual = nn.forward(input);
+                assert!(
+                    actual.approx_eq(&expected, 1e-4),
+                    "{:?} != {:?}",
+                    actual.values,
+                    expected.values
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn saturation() {
+        let activations = vec![Activation::Sigmoid, Activation::RELU];
+        let mut nn = NN::new_rand(vec![2, 4, 2], activations.clone(), &mut rand::thread_rng());
+        // Sigmoid units 0 and 1 are pushed to 1 and 0, 2 and 3 stay near 0.5
+        nn.weights[0].values = vec![
+            0.0, 0.0, 100.0, //
+            0.0, 0.0, -100.0, //
+            0.1, 0.0, 0.0, //
+            0.0, 0.1, 0.0,
+        ];
+        // The first ReLU unit is always negative, the second follows its inputs
+        nn.weights[1].values = vec![0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 1.0, 1.0, 1.0, 0.0];
+        let traces = vec![
+            nn.forward_trace(vec![1.0, -1.0]).1,
+            nn.forward_trace(vec![-1.0, 1.0]).1,
+        ];
+        assert_eq!(vec![0.5, 0.5], super::saturation(&activations, &traces));
+
+        // A unit that is only saturated for some inputs isn't counted
+        assert!(!Activation::RELU.is_saturated(0.3));
+        assert!(Activation::Sigmoid.is_saturated(0.999));
+        assert!(!Activation::Sigmoid.is_saturated(0.5));
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn arena_forward_trace() {
+        let nn = test_nn();
+        let arena = ArenaNN::from(nn.clone());
+        let (_, expected) = nn.forward_trace(test_input());
+        let (output, actual) = arena.forward_trace(test_input());
+        assert_eq!(expected.len(), actual.len());
+        assert_eq!(&output, actual.last().unwrap());
+        for (e, a) in expected.iter().zip(&actual) {
+            assert_eq!((e.rows, e.cols), (a.rows, a.cols));
+            for (e, a) in e.values.iter().zip(&a.values) {
+                assert!((e - a).abs() < 1e-5, "{} != {}", e, a);
+            }
+        }
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn arena_forward_matches() {
+        let nn = test_nn();
+        let arena = ArenaNN::from(nn.clone());
+        let expected = nn.forward(test_input());
+        let actual = arena.forward(test_input());
+        assert_eq!((expected.rows, expected.cols), (actual.rows, actual.cols));
+        for (e, a) in expected.values.iter().zip(&actual.values) {
+            assert!((e - a).abs() < 1e-5, "{} != {}", e, a);
+        }
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn arena_round_trip() {
+        let nn = test_nn();
+        let round_trip = NN::from(ArenaNN::from(nn.clone()));
+        assert_eq!(nn.weights, round_trip.weights);
+        assert_eq!(nn.structure, round_trip.structure);
+
+        // Saves are interchangeable between the two storages
+        let bytes = serde_cbor::to_vec(&nn).unwrap();
+        let arena: ArenaNN = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(bytes, serde_cbor::to_vec(&arena).unwrap());
+    }
 }
@@ -1,56 +1,498 @@
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng, RngCore};
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::{fmt, fs, path};
 
-use super::{nn, Player, N};
+use super::{
+    encoder::{self, Encoding},
+    nn, Evolvable, MovePlayer, N,
+};
 use crate::game;
+use crate::matrix::Matrix;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg(not(feature = "arena"))]
+type Network = nn::NN;
+#[cfg(feature = "arena")]
+type Network = nn::ArenaNN;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedNNPlayer")]
 pub struct NNPlayer {
-    nn: nn::NN,
+    nn: Network,
+    #[serde(default)]
+    encoding: Encoding,
+    /// Weight layers left alone by mutation and crossover, set by the pool every generation
+    #[serde(skip)]
+    frozen: Vec<usize>,
+    /// Whether the board is seen with the colors swapped when playing yellow, set only for
+    /// evaluation and play
+    #[serde(skip)]
+    red_perspective: bool,
 }
 
-impl Player for NNPlayer {
-    fn new_from_param(structure: Vec<usize>, activations: Vec<nn::Activation>) -> Self {
+impl Clone for NNPlayer {
+    fn clone(&self) -> Self {
         Self {
-            nn: nn::NN::new_rand(structure, activations),
+            nn: self.nn.clone(),
+            encoding: self.encoding,
+            frozen: self.frozen.clone(),
+            red_perspective: self.red_perspective,
         }
     }
 
+    fn clone_from(&mut self, source: &Self) {
+        self.nn.clone_from(&source.nn);
+        self.encoding = source.encoding;
+        self.frozen.clone_from(&source.frozen);
+        self.red_perspective = source.red_perspective;
+    }
+}
+
+/// Players are equal when everything that is saved is: the frozen layers and red perspective
+/// are set by the pool for a run, and left out just like they are from saves
+impl PartialEq for NNPlayer {
+    fn eq(&self, other: &Self) -> bool {
+        self.nn == other.nn && self.encoding == other.encoding
+    }
+}
+
+/// A saved `NNPlayer` whose output size hasn't been checked yet
+#[derive(Deserialize)]
+struct UncheckedNNPlayer {
+    nn: Network,
+    #[serde(default)]
+    encoding: Encoding,
+}
+
+impl TryFrom<UncheckedNNPlayer> for NNPlayer {
+    type Error = WrongOutputSize;
+
+    fn try_from(player: UncheckedNNPlayer) -> Result<Self, Self::Error> {
+        check_output_size(player.nn.structure())?;
+        Ok(Self {
+            nn: player.nn,
+            encoding: player.encoding,
+            frozen: Vec::new(),
+            red_perspective: false,
+        })
+    }
+}
+
+/// A network whose last layer doesn't have one output per column, holding the number of
+/// outputs it has
+#[derive(Debug, PartialEq)]
+pub struct WrongOutputSize(pub usize);
+
+impl fmt::Display for WrongOutputSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the network has {} outputs, but needs one per column (7)",
+            self.0
+        )
+    }
+}
+
+impl Error for WrongOutputSize {}
+
+/// The outputs of a network as the scores of the 7 columns
+pub fn to_move_array(v: Vec<N>) -> Result<[N; 7], WrongOutputSize> {
+    let len = v.len();
+    v.try_into().map_err(|_| WrongOutputSize(len))
+}
+
+fn check_output_size(structure: &[usize]) -> Result<(), WrongOutputSize> {
+    match structure.last() {
+        Some(7) => Ok(()),
+        last => Err(WrongOutputSize(last.copied().unwrap_or(0))),
+    }
+}
+
+impl NNPlayer {
+    /// A player whose weights are all zero, so it scores every column the same on any board
+    #[cfg(test)]
+    pub(crate) fn constant(structure: Vec<usize>, activations: Vec<nn::Activation>) -> Self {
+        let mut player = Self::new_from_param(structure, activations);
+        for i in 0..player.nn.layers() {
+            player.nn.layer_mut(i).iter_mut().for_each(|x| *x = 0.0);
+        }
+        player
+    }
+
+    /// A player whose weight layers are `weights`, each in the row-major layout of `NN::layer`
+    #[cfg(test)]
+    pub(crate) fn from_weights(
+        structure: Vec<usize>,
+        activations: Vec<nn::Activation>,
+        weights: &[Vec<N>],
+    ) -> Self {
+        let mut player = Self::new_from_param(structure, activations);
+        for (i, layer) in weights.iter().enumerate() {
+            player.nn.layer_mut(i).copy_from_slice(layer);
+        }
+        player
+    }
+
+    /// Inputs of the network for `board`, seen as red when `red_perspective` is set and
+    /// yellow is to move
+    fn inputs(&self, board: &[[game::Spot; 6]; 7]) -> Vec<N> {
+        let to_move = encoder::side_to_move(board);
+        if self.red_perspective && to_move == game::Spot::YELLOW {
+            self.encoding
+                .encoder()
+                .encode(&encoder::swap_colors(board), game::Spot::RED)
+        } else {
+            self.encoding.encoder().encode(board, to_move)
+        }
+    }
+
+    /// Indices of the weight layers that mutation and crossover may change
+    fn unfrozen_layers(&self) -> Vec<usize> {
+        (0..self.nn.layers())
+            .filter(|i| !self.frozen.contains(i))
+            .collect()
+    }
+
+    fn mutate_layer(
+        &mut self,
+        i: usize,
+        mutation_range: N,
+        mutation_prob: N,
+        rng: &mut dyn RngCore,
+    ) {
+        for x in self.nn.layer_mut(i) {
+            if rng.gen::<N>() < mutation_prob {
+                *x += rng.gen_range(-mutation_range, mutation_range);
+            }
+        }
+    }
+
+    /// Weights (and biases, in the last column) of every layer, `outputs` x `inputs + 1`
+    pub fn layer_matrices(&self) -> Vec<Matrix<N>> {
+        let structure = self.nn.structure();
+        (0..self.nn.layers())
+            .map(|i| {
+                Matrix::from(
+                    self.nn.layer(i).to_vec(),
+                    structure[i + 1],
+                    structure[i] + 1,
+                )
+            })
+            .collect()
+    }
+
+    /// Writes every layer's weights as a grayscale image to `<out_dir>/layer_<i>.pgm`,
+    /// returning the paths written to
+    pub fn export_weights(
+        &self,
+        out_dir: &path::Path,
+    ) -> Result<Vec<path::PathBuf>, Box<dyn Error>> {
+        fs::create_dir_all(out_dir)?;
+        let mut paths = Vec::new();
+        for (i, layer) in self.layer_matrices().iter().enumerate() {
+            let layer_path = out_dir.join(format!("layer_{}.pgm", i));
+            fs::write(&layer_path, layer.to_pgm())?;
+            paths.push(layer_path);
+        }
+        Ok(paths)
+    }
+}
+
+impl MovePlayer for NNPlayer {
     fn get_move(&self, board: [[game::Spot; 6]; 7]) -> [N; 7] {
-        let flattened_board = board
+        to_move_array(self.nn.forward(self.inputs(&board)).T().values)
+            .expect("the output size is checked when the player is created")
+    }
+
+    /// Runs all of `boards` through the network together, with one matrix multiplication
+    /// per layer
+    fn get_moves(&self, boards: &[[[game::Spot; 6]; 7]]) -> Vec<[N; 7]> {
+        let inputs = boards
             .iter()
-            .flatten()
-            .map(|x| x.into_rep())
+            .map(|board| self.inputs(board))
             .collect::<Vec<_>>();
-
         self.nn
-            .forward(flattened_board)
-            .T()
+            .forward_batch(&inputs)
             .values
-            .try_into()
-            .unwrap()
+            .chunks_exact(7)
+            .map(|scores| scores.try_into().unwrap())
+            .collect()
+    }
+
+    fn name(&self) -> String {
+        let layers = self
+            .nn
+            .structure()
+            .iter()
+            .map(|size| size.to_string())
+            .collect::<Vec<_>>();
+        format!("nn({})", layers.join("-"))
+    }
+}
+
+impl Evolvable for NNPlayer {
+    /// Panics if the last layer of `structure` doesn't have 7 outputs
+    fn new_from_param(structure: Vec<usize>, activations: Vec<nn::Activation>) -> Self {
+        Self::new_from_param_with(structure, activations, &mut rand::thread_rng())
+    }
+
+    /// Panics if the last layer of `structure` doesn't have 7 outputs
+    fn new_from_param_with(
+        structure: Vec<usize>,
+        activations: Vec<nn::Activation>,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        if let Err(err) = check_output_size(&structure) {
+            panic!("invalid structure {:?}: {}", structure, err);
+        }
+        Self {
+            nn: Network::new_rand(structure, activations, rng),
+            encoding: Encoding::default(),
+            frozen: Vec::new(),
+            red_perspective: false,
+        }
+    }
+
+    fn structure(&self) -> Option<&[usize]> {
+        Some(self.nn.structure())
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
     }
 
-    fn mutate(&mut self, mutation_range: N, mutation_prob: N) {
-        let mut rng = rand::thread_rng(); //rng::thread_rng();
-        for i in 0..self.nn.weights.len() {
-            self.nn.weights[i].map(&mut |x| {
+    fn freeze_layers(&mut self, layers: &[usize]) {
+        self.frozen = layers.to_vec();
+    }
+
+    fn set_red_perspective(&mut self, red_perspective: bool) {
+        self.red_perspective = red_perspective;
+    }
+
+    fn saturation(&self, boards: &[[[game::Spot; 6]; 7]]) -> Vec<N> {
+        let traces = boards
+            .iter()
+            .map(|board| self.nn.forward_trace(self.inputs(board)).1)
+            .collect::<Vec<_>>();
+        nn::saturation(self.nn.activations(), &traces)
+    }
+
+    fn weight_vector(&self) -> Vec<N> {
+        (0..self.nn.layers())
+            .flat_map(|i| self.nn.layer(i).iter().cloned())
+            .collect()
+    }
+
+    fn mutate(&mut self, mutation_range: N, mutation_prob: N, rng: &mut dyn RngCore) {
+        for i in self.unfrozen_layers() {
+            self.mutate_layer(i, mutation_range, mutation_prob, rng);
+        }
+    }
+
+    fn mutate_one_layer(&mut self, mutation_range: N, mutation_prob: N, rng: &mut dyn RngCore) {
+        let layers = self.unfrozen_layers();
+        if let Some(layer) = layers.choose(rng) {
+            self.mutate_layer(*layer, mutation_range, mutation_prob, rng);
+        }
+    }
+
+    fn mutate_antithetic(
+        &mut self,
+        sibling: &mut Self,
+        mutation_range: N,
+        mutation_prob: N,
+        rng: &mut dyn RngCore,
+    ) {
+        for i in self.unfrozen_layers() {
+            for (x, y) in self.nn.layer_mut(i).iter_mut().zip(sibling.nn.layer_mut(i)) {
                 if rng.gen::<N>() < mutation_prob {
-                    x + rng.gen_range(-mutation_range, mutation_range)
-                } else {
-                    x
+                    let delta = rng.gen_range(-mutation_range, mutation_range);
+                    *x += delta;
+                    *y -= delta;
                 }
-            });
+            }
         }
     }
 
-    fn crossover(&mut self, other: &Self) {
-        let mut rng = rand::thread_rng();
-        for i in 0..self.nn.weights.len() {
+    fn crossover(&mut self, other: &Self, rng: &mut dyn RngCore) {
+        for i in self.unfrozen_layers() {
             if rng.gen::<f32>() < 0.5 {
-                self.nn.weights[i] = other.nn.weights[i].clone();
+                self.nn.layer_mut(i).copy_from_slice(other.nn.layer(i));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod nn_player_tests {
+    use super::*;
+
+    #[test]
+    fn antithetic_perturbations_cancel() {
+        let parent = NNPlayer::new_from_param(
+            vec![42, 10, 7],
+            vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+        );
+        let mut first = parent.clone();
+        let mut second = parent.clone();
+        first.mutate_antithetic(&mut second, 0.5, 0.5, &mut rand::thread_rng());
+
+        let mut changed = 0;
+        for i in 0..parent.nn.layers() {
+            let layers = parent.nn.layer(i).iter().zip(first.nn.layer(i));
+            for ((p, a), b) in layers.zip(second.nn.layer(i)) {
+                assert!(((a - p) + (b - p)).abs() < 1e-6);
+                changed += (a != p) as usize;
+            }
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn mutate_one_layer() {
+        let parent =
+            NNPlayer::new_from_param(vec![42, 10, 10, 7], vec![nn::Activation::Sigmoid; 3]);
+        for _ in 0..10 {
+            let mut child = parent.clone();
+            child.mutate_one_layer(0.5, 1.0, &mut rand::thread_rng());
+            let changed = (0..parent.nn.layers())
+                .filter(|i| parent.nn.layer(*i) != child.nn.layer(*i))
+                .count();
+            assert_eq!(1, changed);
+        }
+    }
+
+    #[test]
+    fn frozen_layers() {
+        let mut parent =
+            NNPlayer::new_from_param(vec![42, 10, 10, 7], vec![nn::Activation::Sigmoid; 3]);
+        parent.freeze_layers(&[0, 2]);
+        let other = NNPlayer::new_from_param(vec![42, 10, 10, 7], vec![nn::Activation::Sigmoid; 3]);
+        let bytes = |player: &NNPlayer, i: usize| {
+            player
+                .nn
+                .layer(i)
+                .iter()
+                .flat_map(|x| x.to_ne_bytes())
+                .collect::<Vec<_>>()
+        };
+
+        let mut rng = rand::thread_rng();
+        let mut child = parent.clone();
+        let mut sibling = parent.clone();
+        child.mutate(0.5, 1.0, &mut rng);
+        child.mutate_one_layer(0.5, 1.0, &mut rng);
+        child.mutate_antithetic(&mut sibling, 0.5, 1.0, &mut rng);
+        for _ in 0..10 {
+            child.crossover(&other, &mut rng);
+        }
+        for i in [0, 2] {
+            assert_eq!(bytes(&parent, i), bytes(&child, i));
+            assert_eq!(bytes(&parent, i), bytes(&sibling, i));
+        }
+        assert_ne!(parent.nn.layer(1), child.nn.layer(1));
+    }
+
+    #[test]
+    fn red_perspective() {
+        let mut player = NNPlayer::new_from_param(
+            vec![42, 10, 7],
+            vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+        );
+        let mut board = game::Board::new();
+        board.insert_top(3, game::Spot::RED);
+        board.insert_top(3, game::Spot::YELLOW);
+        let red_to_move = board.positions;
+        board.insert_top(2, game::Spot::RED);
+        let yellow_to_move = board.positions;
+        let swapped = encoder::swap_colors(&yellow_to_move);
+
+        let plain = (player.get_move(red_to_move), player.get_move(swapped));
+        player.set_red_perspective(true);
+        // Red's moves are unchanged, yellow sees the board as red would
+        assert_eq!(plain.0, player.get_move(red_to_move));
+        assert_eq!(plain.1, player.get_move(yellow_to_move));
+
+        // Only set for evaluation, so saves don't keep it
+        let loaded: NNPlayer =
+            serde_json::from_str(&serde_json::to_string(&player).unwrap()).unwrap();
+        assert!(!loaded.red_perspective);
+        assert!(player.clone().red_perspective);
+    }
+
+    #[test]
+    fn red_perspective_saturation() {
+        // The hidden unit sums the board, so it's dead whenever yellow has more pieces
+        let mut hidden = vec![1.0; 42];
+        hidden.push(0.0);
+        let mut player = NNPlayer::from_weights(
+            vec![42, 1, 7],
+            vec![nn::Activation::RELU; 2],
+            &[hidden, vec![0.0; 14]],
+        );
+        let mut board = game::Board::new();
+        board.insert_top(3, game::Spot::RED);
+        board.insert_top(3, game::Spot::YELLOW);
+        board.insert_top(2, game::Spot::RED);
+
+        assert_eq!(vec![0.0, 1.0], player.saturation(&[board.positions]));
+        player.set_red_perspective(true);
+        assert_eq!(vec![1.0, 1.0], player.saturation(&[board.positions]));
+    }
+
+    #[test]
+    fn export_weights() {
+        let out_dir =
+            std::env::temp_dir().join(format!("fourai_export_weights_{}", std::process::id()));
+        let player = NNPlayer::new_from_param(
+            vec![42, 10, 7],
+            vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+        );
+        let paths = player.export_weights(&out_dir).unwrap();
+        assert_eq!(2, paths.len());
+
+        for (layer_path, layer) in paths.iter().zip(player.layer_matrices()) {
+            let pgm = fs::read_to_string(layer_path).unwrap();
+            let mut lines = pgm.lines();
+            assert_eq!(Some("P2"), lines.next());
+            assert_eq!(
+                Some(format!("{} {}", layer.cols, layer.rows).as_str()),
+                lines.next()
+            );
+            assert_eq!(Some("255"), lines.next());
+            let pixel_rows = lines.collect::<Vec<_>>();
+            assert_eq!(layer.rows, pixel_rows.len());
+            assert!(pixel_rows
+                .iter()
+                .all(|row| row.split(' ').count() == layer.cols));
+        }
+        // Input layer is 10 x (42 + 1 bias), output layer 7 x (10 + 1)
+        let layers = player.layer_matrices();
+        assert_eq!((10, 43), (layers[0].rows, layers[0].cols));
+        assert_eq!((7, 11), (layers[1].rows, layers[1].cols));
+
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn wrong_output_size() {
+        assert_eq!(Err(WrongOutputSize(5)), to_move_array(vec![0.5; 5]));
+        assert_eq!(Ok([0.5; 7]), to_move_array(vec![0.5; 7]));
+
+        // Saved players are checked when they are loaded, instead of on their first move
+        let player = NNPlayer {
+            nn: Network::new_rand(
+                vec![42, 10, 5],
+                vec![nn::Activation::Sigmoid; 2],
+                &mut rand::thread_rng(),
+            ),
+            encoding: Encoding::default(),
+            frozen: Vec::new(),
+            red_perspective: false,
+        };
+        let err =
+            serde_json::from_str::<NNPlayer>(&serde_json::to_string(&player).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("5 outputs"));
+    }
+}
@@ -1,56 +1,641 @@
 use std::cmp::{Ordering, Reverse};
+use std::collections::{hash_map::DefaultHasher, HashMap};
 use std::error::Error;
 use std::fs::{create_dir_all, File};
+use std::hash::Hasher;
+use std::io::Write;
 use std::path;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
+use rand::{seq::SliceRandom, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_cbor;
 
 use super::{
-    agent::{Agent, Player},
-    nn, RandomPlayer, N,
+    agent::{self, Agent, IllegalMove, MovePlayer, Player, TieBreak},
+    encoder::Encoding,
+    nn, MinimaxPlayer, RandomPlayer, SeededRandomPlayer, N,
 };
+use crate::alloc_profile::{self, Allocated};
 use crate::game;
 use crate::helpers;
+use crate::holdout::{self, HoldoutPosition};
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Largest difference between column scores that still counts as the same score
+const CONSTANT_OUTPUT_TOLERANCE: N = 1e-6;
+
+/// Share of saturated units in a layer above which `warn_on_saturation` warns
+const SATURATION_WARNING: N = 0.5;
+/// Number of boards the saturation check runs the top agent on
+const SATURATION_BOARDS: usize = 64;
+/// Number of random boards the top agent moves on for `move_histogram`
+const HISTOGRAM_BOARDS: usize = 1000;
+
+/// Results of both agents of a pairing and the outcomes of its games
+type PairingResult = (SelfPlayRecord, SelfPlayRecord, GameTally);
+
+/// Self-play results by the weight checksums of the two agents
+type FitnessCache = HashMap<(u64, u64), PairingResult>;
+
+/// Fitness every agent earned from each of its pairings, as `(agent, opponent, fitness)`
+type OpponentPoints = Vec<(usize, usize, i32)>;
+
+/// Results of every agent, by index, the outcomes of a share of the self-play games, and the
+/// per-opponent fitness of those games when `opponent_weighted` is set
+type SelfPlayResults = (Vec<SelfPlayRecord>, GameTally, OpponentPoints);
+
+/// Layer sizes of a network and the activations between its layers
+pub type Architecture = (Vec<usize>, Vec<nn::Activation>);
+
+/// Outcome counts of a set of games
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct GameTally {
+    pub red_wins: usize,
+    pub yellow_wins: usize,
+    pub draws: usize,
+    /// Moves played, summed over all games
+    pub moves: usize,
+}
+
+impl GameTally {
+    pub fn add(&mut self, winner: game::Spot) {
+        match winner {
+            game::Spot::RED => self.red_wins += 1,
+            game::Spot::YELLOW => self.yellow_wins += 1,
+            game::Spot::EMPTY => self.draws += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &GameTally) {
+        self.red_wins += other.red_wins;
+        self.yellow_wins += other.yellow_wins;
+        self.draws += other.draws;
+        self.moves += other.moves;
+    }
+
+    pub fn games(&self) -> usize {
+        self.red_wins + self.yellow_wins + self.draws
+    }
+
+    /// Average number of moves it took to decide a game
+    pub fn average_moves(&self) -> N {
+        self.moves as N / self.games().max(1) as N
+    }
+}
+
+/// Self-play results of one agent, summed over all of its games of a generation
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct SelfPlayRecord {
+    pub fitness: i32,
+    pub wins: usize,
+    pub games: usize,
+}
+
+impl SelfPlayRecord {
+    pub fn add_game(&mut self, fitness: i32, won: bool) {
+        self.fitness += fitness;
+        self.wins += won as usize;
+        self.games += 1;
+    }
+
+    pub fn merge(&mut self, other: &SelfPlayRecord) {
+        self.fitness += other.fitness;
+        self.wins += other.wins;
+        self.games += other.games;
+    }
+}
+
+/// How an agent's self-play results are reduced to the fitness it is ranked by
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum FitnessAggregation {
+    /// The fitness summed over all games
+    #[default]
+    Sum,
+    /// The fitness per game, in thousandths
+    Average,
+    /// The share of games won, in thousandths, so that draws count the same as losses
+    WinRate,
+}
+
+impl FitnessAggregation {
+    pub fn from_string(s: &str) -> FitnessAggregation {
+        match s {
+            "sum" => FitnessAggregation::Sum,
+            "average" => FitnessAggregation::Average,
+            "win-rate" => FitnessAggregation::WinRate,
+            _ => panic!("invalid fitness aggregation: {}", s),
+        }
+    }
+
+    /// Fitness of an agent with the self-play results `record`. An agent without games gets 0.
+    pub fn apply(&self, record: &SelfPlayRecord) -> i32 {
+        if record.games == 0 {
+            return 0;
+        }
+        let games = record.games as i64;
+        match self {
+            FitnessAggregation::Sum => record.fitness,
+            FitnessAggregation::Average => (record.fitness as i64 * 1000 / games) as i32,
+            FitnessAggregation::WinRate => (record.wins as i64 * 1000 / games) as i32,
+        }
+    }
+}
+
+/// Results of `Pool::evaluate` besides the agents' fitness
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Evaluation {
+    pub tally: GameTally,
+    /// Mean fitness against the reference agent, when reference games are played
+    pub reference_mean: Option<N>,
+    /// Number of agents penalized for scoring every column the same, when checked
+    pub constant_outputs: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct PoolProperties {
     /// Amount of agents to retain per generations
     /// This means the number that die off is
     /// total_pos - surviving_amount
     pub surviving_amount: usize,
+    /// Number of the fittest survivors copied into the next generation unchanged, ahead of
+    /// the crossed over agents. Neither mutation nor crossover touches them, so their
+    /// pairings can be taken from the fitness cache. `0` mutates every offspring.
+    pub elitism: usize,
 
     /// Range of mutations on weights
     pub mutation_range: N,
     /// Probability that a mutation occurs
     pub mutation_prob: N,
+    /// Mutate the copies of every survivor in pairs with opposite perturbations (antithetic
+    /// sampling), which lowers the variance of comparing mutations against each other.
+    /// Crossed over agents are mutated on their own.
+    pub antithetic: bool,
+    /// Mutate a single randomly picked layer of every offspring instead of all of them.
+    /// Ignored with `antithetic`, which perturbs whole networks.
+    pub layer_mutation: bool,
+    /// Weight layers, counted from the inputs, that neither mutation nor crossover change,
+    /// for example to keep the early layers of a network seeded from a trained one fixed
+    /// while evolving the rest. Empty evolves every layer.
+    pub frozen_layers: Vec<usize>,
 
     /// Number of crossed over agents
     pub crossover_size: usize,
+    /// Fill the population with crossed over agents before the mutated copies. Without it,
+    /// `crossover_size` is ignored and every offspring is a mutated copy of a survivor.
+    pub crossover_enabled: bool,
+    /// Share of every new generation replaced with freshly initialised networks (random
+    /// immigrants), taking the place of the copies of the weakest survivors, to keep exploring
+    /// after the population has converged. `0` breeds every agent from the survivors.
+    pub immigrant_fraction: N,
 
     /// Total population of pool
     /// Most are killed off
     /// Calculated through (surviving_amount * surviving_amount - surviving_amount)* crossover_amount * mutation_amount
     pub population_size: usize,
+    /// Breakpoints `(generation, size)` of the population size: from each breakpoint's
+    /// generation on, the population is bred to its size, until the next breakpoint. Before
+    /// the first one the population has `population_size` agents. Empty keeps the population
+    /// constant.
+    pub population_schedule: Vec<(usize, usize)>,
 
     pub structure: Vec<usize>,
     pub activations: Vec<nn::Activation>,
+    /// Further architectures spread over the population along with `structure`, to compare
+    /// them against each other in one coevolving population. Agent `i` of a new pool gets
+    /// the architecture `i % (extra_architectures.len() + 1)` of `architectures`. Every one
+    /// needs the same inputs and outputs, and only agents with the same layer sizes are
+    /// crossed over. Empty for a pool of one architecture.
+    pub extra_architectures: Vec<Architecture>,
+    /// How boards are fed to the networks. The first layer of `structure` has to match the
+    /// encoding's input size.
+    pub encoding: Encoding,
 
     pub generations: isize,
+    /// Wall-clock time after which training stops, on top of `generations`. The generation
+    /// that runs out of time is saved, whatever `save_interval` says, so the run can be resumed.
+    /// `None` trains for as long as `generations` takes.
+    pub max_runtime: Option<Duration>,
+    /// Seed of the RNG behind the initial networks, breeding and opponent sampling, for runs
+    /// that can be repeated exactly. `None` seeds it from the OS.
+    pub seed: Option<u64>,
 
     pub save_interval: isize,
+    /// Only save the top agent of each saved generation, instead of all survivors
+    pub save_best_only: bool,
+    /// Also write the whole pool to `<file_path>_checkpoint` every saved generation, and resume
+    /// from it verbatim when it exists, instead of rebuilding the population from the survivors
+    pub checkpoint_full: bool,
+    /// Number of most recent generation files to keep on disk. Older ones are deleted after
+    /// every save. `-1` keeps every generation, and `0` is rejected by `try_new`.
+    pub keep_last: isize,
+    /// When there is no generation to resume from, but a `<file_path>_best` file in the saved
+    /// generation format exists, start at generation 0 from clones of its agents instead of
+    /// random networks
+    pub seed_from_best: bool,
+    /// Number of generations after resuming from a saved generation during which offspring
+    /// inherit a share of their parent's fitness instead of starting from 0, so that the
+    /// saved ranking carries over. The share starts at all of the saved fitness in the first
+    /// resumed generation and decays linearly to none. `0` disables it.
+    pub preserve_fitness: usize,
     pub compare_interval: isize,
+    /// Number of game sets against a random opponent that the comparison averages over, since
+    /// a single set is noisy. Every set plays its own seeded random opponent, seeded by the
+    /// generation and the set, so the sets differ but a generation always plays the same.
+    pub compare_games: usize,
+    /// Report the top agent's win-rate from each first move when comparing
+    pub opening_report: bool,
+    /// Split the comparison against the random agent into the games played as red and the
+    /// ones played as yellow
+    pub compare_both_colors: bool,
+    /// When comparing, also print the moves and final board of the top agent's game as red
+    /// against the random agent
+    pub show_best_game: bool,
+    /// When comparing, have the survivors see the board with the colors swapped whenever they
+    /// play yellow, so that their networks always play as red. Self-play is unaffected.
+    pub red_perspective: bool,
+
+    /// Number of generations at the start of training during which games against the random
+    /// agent also contribute to fitness. `0` disables the curriculum.
+    pub curriculum_generations: usize,
+    /// Weight of the random agent games in the first generation. It decays linearly to 0 over
+    /// `curriculum_generations`, after which training is pure self-play.
+    pub curriculum_weight: i32,
+    /// Generation from which games against a one-ply minimax opponent also contribute to
+    /// fitness, weighted by `curriculum_weight`, until the end of training. `0` leaves the
+    /// minimax opponent out of the curriculum.
+    pub curriculum_minimax_from: usize,
+
+    /// Number of games each agent plays against a fixed reference opponent every generation.
+    /// Self-play fitness is only relative to the rest of the population, so these games
+    /// give fitness an absolute component that is comparable across generations. The
+    /// reference results are added on top of the self-play fitness; since self-play sums
+    /// over every other agent, the count needs to be a sizable fraction of the population
+    /// for the reference games to noticeably affect selection. `0` disables them.
+    pub reference_games: usize,
+
+    /// Fitness taken away from both agents for every drawn game, to discourage agents that
+    /// stall for draws instead of trying to win. `0` scores draws neutrally.
+    pub draw_penalty: i32,
+    /// Fitness given on top of the draw score to the agent with the better position, by
+    /// `Board::evaluate`, when a game is drawn, to reward pressing an advantage even when it
+    /// doesn't lead to a win. `0` scores both sides of a draw the same.
+    pub draw_position_bonus: i32,
+    /// How the self-play results of every agent are reduced to its fitness. The curriculum,
+    /// reference and constant output terms are added on top of the reduced value.
+    pub fitness_aggregation: FitnessAggregation,
+    /// Scale the fitness of every self-play game by the strength of the opponent, so that
+    /// beating a strong agent counts more than beating a weak one. Strength is the agent's
+    /// flat fitness of the same generation, scaled to `0..=1` over the population: fitness
+    /// gained is multiplied by `0.5 + strength` and fitness lost by `1.5 - strength`.
+    pub opponent_weighted: bool,
+
+    /// When comparing, also report the share of saturated or dead units in every layer of the
+    /// top agent's network over a set of random boards, and warn for layers where it is high
+    pub warn_on_saturation: bool,
+    /// When comparing, also print how often the top agent picks each column over a set of
+    /// random boards, to spot a network that collapsed onto a single column
+    pub move_histogram: bool,
+
+    /// Fitness taken away from every agent that scores all 7 columns the same on every opening
+    /// position up to two moves deep. Such agents always play the tie-break column and can drift
+    /// through selection against equally degenerate opponents. `0` disables the check.
+    pub constant_output_penalty: i32,
+
+    /// Print the number of red wins, yellow wins and draws every generation
+    pub report_outcomes: bool,
+    /// Print the summary of a generation every this many generations, independently of
+    /// `save_interval` and `compare_interval`. `-1` never prints it.
+    pub stats_interval: isize,
+
+    /// Evaluate self-play in tiles of this many by this many agents instead of one agent per
+    /// task. Each task then reuses a small working set of networks. `0` disables tiling.
+    pub eval_block_size: usize,
+    /// Play all of the round-robin's games together, one move at a time, so that every
+    /// agent scores the boards of all the games it is to move in with one batched forward
+    /// pass. Takes the place of `eval_block_size` and `fitness_cache`, and has no effect with
+    /// `max_opponents`. The results are the same as without it up to float rounding: the
+    /// batched multiplication can round scores differently and flip a close move.
+    pub batched_eval: bool,
+    /// Number of opponents every agent plays in self-play, out of a fresh random order of the
+    /// population each generation, so that a generation takes `O(n * max_opponents)` games
+    /// instead of `O(n^2)`. Only the agent that picked the opponent is scored by their games,
+    /// so every agent plays the same number of games. Ignores `eval_block_size`. `0` plays
+    /// every other agent.
+    pub max_opponents: usize,
+
+    /// Play every pairing twice, once with each agent as red. When `false`, only the game with
+    /// the first agent as red is played. In self-play every agent still plays both colors,
+    /// since each pair of agents is paired up once either way round, but games against the
+    /// random agent, the reference agent and the comparison opponent are then only played as
+    /// red, so their results include red's first-move advantage.
+    pub symmetric_eval: bool,
+    /// Number of times every color assignment of a pairing is played, with the results of
+    /// all of them summed. More than `1` only reduces noise when games aren't reproducible,
+    /// e.g. with `TieBreak::Random`.
+    pub games_per_color: usize,
+
+    /// How agents pick between columns their network scores equally
+    pub tie_break: TieBreak,
+    /// Whether a player that scores a full column highest plays the best legal column
+    /// instead, or loses the game on the spot
+    pub illegal_move: IllegalMove,
+
+    /// Generation at which to write every agent's fitness and weight checksum to
+    /// `<file_path>_snapshot_<generation>.csv`, for comparing runs. `-1` never writes one.
+    pub snapshot_at: isize,
+    /// Number of self-play games of generation 0 to write to `<file_path>_games/`, one
+    /// `game_<n>.txt` replay per game, to see how freshly created networks play. `0` writes
+    /// none.
+    pub dump_games: usize,
+
+    /// Remember the result of each self-play pairing by the weight checksums of both agents,
+    /// and reuse it in the next generation when neither agent changed. Ignored with
+    /// `TieBreak::Random`, where replaying a pairing can give a different result.
+    pub fitness_cache: bool,
+    /// Count the heap allocations of every phase of a generation and print them. Needs a
+    /// build with the `profile-alloc` feature.
+    pub profile_memory: bool,
+    /// Append the top and mean fitness of every generation, and the survivors' fitness
+    /// against the random agent when comparing, to `<file_path>_stats.jsonl` as one JSON
+    /// object per line
+    pub stats_log: bool,
+
+    /// Generation file of a separately trained agent to compare the survivors against, in
+    /// addition to the random agent. Loaded once when training starts.
+    pub eval_opponent_from: Option<path::PathBuf>,
+    /// Generation file of an earlier checkpoint. Every compare interval, each survivor plays
+    /// its top agent, and the number of survivors that beat it is printed.
+    pub benchmark_against: Option<path::PathBuf>,
+    /// File of test positions with their right moves, see `holdout::parse_holdout`. Every
+    /// compare interval, the share of them where the top agent plays a right move is printed.
+    pub holdout: Option<path::PathBuf>,
+
     pub file_path: path::PathBuf,
 }
 
+impl PoolProperties {
+    /// Size of the population at generation `gen`, by `population_schedule`
+    pub fn population_at(&self, gen: usize) -> usize {
+        self.population_schedule
+            .iter()
+            .filter(|(from, _)| *from <= gen)
+            .max_by_key(|(from, _)| *from)
+            .map_or(self.population_size, |(_, size)| *size)
+    }
+
+    /// Number of random immigrants among a new generation of `population_size` agents
+    pub fn immigrants(&self, population_size: usize) -> usize {
+        let immigrants = (population_size as N * self.immigrant_fraction).round() as usize;
+        immigrants.min(population_size)
+    }
+
+    /// Every architecture of the population, `structure` and `activations` first
+    pub fn architectures(&self) -> Vec<Architecture> {
+        let mut architectures = vec![(self.structure.clone(), self.activations.clone())];
+        architectures.extend(self.extra_architectures.iter().cloned());
+        architectures
+    }
+}
+
+/// What happened in one generation, yielded by `Pool::generations`
+#[derive(Clone, Debug)]
+pub struct GenerationReport {
+    pub generation: usize,
+    /// Fitness of every agent after evaluation, highest first
+    pub fitnesses: Vec<i32>,
+    pub evaluation: Evaluation,
+    /// Time the whole generation took, including saving and comparing
+    pub duration: Duration,
+    /// Training ran past `max_runtime` during this generation, so it was saved and no further
+    /// generation is played
+    pub out_of_time: bool,
+    /// Allocations of every phase, with `profile_memory`
+    pub memory: Option<MemoryProfile>,
+    /// Average fitness of the survivors against the random agent, in generations that
+    /// compared
+    pub random_fitness: Option<N>,
+}
+
+/// One line of the `stats_log`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatsRecord {
+    pub generation: usize,
+    pub top_fitness: i32,
+    pub mean_fitness: N,
+    /// Average fitness of the survivors against the random agent, in generations that
+    /// compared
+    pub random_fitness: Option<N>,
+}
+
+/// Heap allocations made by the phases of a generation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryProfile {
+    /// Self-play and the other games that decide the fitness
+    pub evaluation: Allocated,
+    /// Selecting the survivors and breeding their offspring: copies, crossover and mutation
+    pub breeding: Allocated,
+    /// Writing the generation file, pruning old ones and writing the checkpoint
+    pub saving: Allocated,
+}
+
+impl GenerationReport {
+    /// The line of this generation in the `stats_log`
+    pub fn stats_record(&self) -> StatsRecord {
+        StatsRecord {
+            generation: self.generation,
+            top_fitness: self.fitnesses.first().copied().unwrap_or(0),
+            mean_fitness: self.fitnesses.iter().sum::<i32>() as N
+                / self.fitnesses.len().max(1) as N,
+            random_fitness: self.random_fitness,
+        }
+    }
+
+    /// Prints the one line summary `training_loop` shows for every generation
+    pub fn print(&self, report_outcomes: bool, reference_games: usize) {
+        let tally = self.evaluation.tally;
+        if report_outcomes {
+            print!(
+                "{}Red wins: {}, yellow wins: {}, draws: {}. {}",
+                GREEN!(),
+                tally.red_wins,
+                tally.yellow_wins,
+                tally.draws,
+                RESET!()
+            );
+        }
+        print!(
+            "{}Draw rate: {:.1}%. {}",
+            GREEN!(),
+            tally.draws as N / tally.games().max(1) as N * 100.0,
+            RESET!()
+        );
+        if let Some(reference_mean) = self.evaluation.reference_mean {
+            print!(
+                "{}Mean fitness against reference agent: {:.2} over {} games. {}",
+                GREEN!(),
+                reference_mean,
+                reference_games,
+                RESET!()
+            );
+        }
+        if self.evaluation.constant_outputs > 0 {
+            print!(
+                "{}Constant output agents: {}. {}",
+                YELLOW!(),
+                self.evaluation.constant_outputs,
+                RESET!()
+            );
+        }
+        if let Some(top) = self.fitnesses.first() {
+            print!("{}Top fitness: {}. {}", GREEN!(), top, RESET!());
+        }
+        println!(
+            "{}Generation {} done in {:.2}s.{}",
+            CYAN!(),
+            self.generation,
+            self.duration.as_secs_f32(),
+            RESET!()
+        );
+    }
+}
+
+/// Iterator returned by `Pool::generations`
+pub struct Generations<'a, Plr: Player> {
+    pool: &'a mut Pool<Plr>,
+    range: Box<dyn Iterator<Item = usize>>,
+    /// End of `max_runtime`, counted from the creation of the iterator
+    deadline: Option<Instant>,
+    out_of_time: bool,
+}
+
+impl<'a, Plr> Iterator for Generations<'a, Plr>
+where
+    Plr: Player + Clone + Serialize + DeserializeOwned + Sync + Send,
+{
+    type Item = Result<GenerationReport, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.out_of_time {
+            return None;
+        }
+        let gen = self.range.next()?;
+        let report = self.pool.run_generation(gen, self.deadline);
+        if let Ok(report) = &report {
+            self.out_of_time = report.out_of_time;
+        }
+        Some(report)
+    }
+}
+
+/// RNG behind every random choice the pool makes itself. It is saved with full checkpoints, so
+/// that a resumed run draws the same numbers as one that never stopped.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedRng", into = "SavedRng")]
+pub struct PoolRng {
+    seed: [u8; 32],
+    rng: ChaCha8Rng,
+}
+
+/// A `PoolRng` as saved: its seed and how far into the stream of numbers it has drawn
+#[derive(Serialize, Deserialize)]
+struct SavedRng {
+    seed: [u8; 32],
+    /// Upper and lower half of the position in 32-bit words
+    word_pos: (u64, u64),
+}
+
+impl PoolRng {
+    /// A generator seeded from `seed`, or from the OS without one
+    pub fn new(seed: Option<u64>) -> Self {
+        let mut bytes = [0; 32];
+        match seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed).fill_bytes(&mut bytes),
+            None => rand::thread_rng().fill_bytes(&mut bytes),
+        }
+        Self {
+            seed: bytes,
+            rng: ChaCha8Rng::from_seed(bytes),
+        }
+    }
+}
+
+/// Seeded from the OS, for checkpoints written before the RNG was saved with them
+impl Default for PoolRng {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl From<SavedRng> for PoolRng {
+    fn from(saved: SavedRng) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(saved.seed);
+        rng.set_word_pos((saved.word_pos.0 as u128) << 64 | saved.word_pos.1 as u128);
+        Self {
+            seed: saved.seed,
+            rng,
+        }
+    }
+}
+
+impl From<PoolRng> for SavedRng {
+    fn from(rng: PoolRng) -> Self {
+        let word_pos = rng.rng.get_word_pos();
+        Self {
+            seed: rng.seed,
+            word_pos: ((word_pos >> 64) as u64, word_pos as u64),
+        }
+    }
+}
+
+impl RngCore for PoolRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Pool<Plr: Player> {
     agents: Vec<Agent<Plr>>,
     generation: usize,
     properties: PoolProperties,
+    /// Opponent loaded from `eval_opponent_from`. Not saved, since it is loaded again on start.
+    #[serde(skip, default = "Option::default")]
+    eval_opponent: Option<Agent<Plr>>,
+    /// Top agent loaded from `benchmark_against`, loaded again on start like `eval_opponent`
+    #[serde(skip, default = "Option::default")]
+    benchmark: Option<Agent<Plr>>,
+    /// Positions loaded from `holdout`, loaded again on start like `eval_opponent`
+    #[serde(skip, default = "Vec::new")]
+    holdout: Vec<HoldoutPosition>,
+    /// Generation this run resumed at from a saved generation, for `preserve_fitness`
+    #[serde(skip)]
+    resumed_at: Option<usize>,
+    /// Self-play results of the last generation, keyed by the weight checksums of the red and
+    /// yellow agent of the first game. See `PoolProperties::fitness_cache`.
+    #[serde(skip)]
+    fitness_cache: FitnessCache,
+    /// Agents that didn't survive the last selection, kept so that the next generation can
+    /// be copied into their buffers instead of allocating new ones
+    #[serde(skip, default = "Vec::new")]
+    spare: Vec<Agent<Plr>>,
+    #[serde(default)]
+    rng: PoolRng,
 }
 
 impl<'a, Plr> Pool<Plr>
@@ -58,118 +643,417 @@ where
     Plr: Player + Clone + Serialize + DeserializeOwned + Sync + Send,
 {
     pub fn new(properties: PoolProperties) -> Pool<Plr> {
-        let mut agents = Vec::with_capacity(properties.population_size);
-        for _ in 0..properties.population_size {
-            agents.push(Agent::new(Plr::new_from_param(
-                properties.structure.clone(),
-                properties.activations.clone(),
-            )))
+        let architectures = properties.architectures();
+        let size = properties.population_at(0);
+        let mut rng = PoolRng::new(properties.seed);
+        let mut agents = Vec::with_capacity(size);
+        for i in 0..size {
+            let (structure, activations) = architectures[i % architectures.len()].clone();
+            let mut player = Plr::new_from_param_with(structure, activations, &mut rng);
+            player.set_encoding(properties.encoding);
+            agents.push(Agent::new(player))
         }
 
         Pool {
             agents,
             generation: 0,
             properties,
+            eval_opponent: None,
+            benchmark: None,
+            holdout: Vec::new(),
+            resumed_at: None,
+            fitness_cache: HashMap::new(),
+            spare: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Same as `new`, but fails when the population can't hold the agents the properties ask
+    /// for, which would otherwise only panic once training selects the survivors
+    pub fn try_new(properties: PoolProperties) -> Result<Pool<Plr>, Box<dyn Error>> {
+        if properties.surviving_amount == 0 {
+            return Err("at least one agent has to survive every generation".into());
+        }
+        if properties.games_per_color == 0 {
+            return Err("every pairing has to play at least one game per color".into());
+        }
+        if properties.elitism > properties.surviving_amount {
+            return Err(format!(
+                "can't keep {} elites out of {} survivors",
+                properties.elitism, properties.surviving_amount
+            )
+            .into());
+        }
+        if properties.keep_last == 0 {
+            return Err("keeping the last 0 generations would delete every save".into());
+        }
+        if properties.profile_memory && !alloc_profile::available() {
+            return Err("profiling memory needs a build with the profile-alloc feature".into());
+        }
+        for (structure, _) in properties.architectures() {
+            let layers = structure.len().saturating_sub(1);
+            if let Some(layer) = properties
+                .frozen_layers
+                .iter()
+                .find(|layer| **layer >= layers)
+            {
+                return Err(format!(
+                    "can't freeze layer {} of {:?}, which only has {} weight layers",
+                    layer, structure, layers
+                )
+                .into());
+            }
         }
+        if !(0.0..=1.0).contains(&properties.immigrant_fraction) {
+            return Err(format!(
+                "immigrant fraction {} is not between 0 and 1",
+                properties.immigrant_fraction
+            )
+            .into());
+        }
+        let sizes = properties
+            .population_schedule
+            .iter()
+            .map(|(_, size)| *size)
+            .chain(std::iter::once(properties.population_size));
+        for size in sizes {
+            if properties.surviving_amount > size {
+                return Err(format!(
+                    "{} surviving agents don't fit in a population of {}",
+                    properties.surviving_amount, size
+                )
+                .into());
+            }
+            if properties.crossover_enabled && properties.crossover_size > size {
+                return Err(format!(
+                    "{} crossed over agents don't fit in a population of {}",
+                    properties.crossover_size, size
+                )
+                .into());
+            }
+        }
+        Ok(Self::new(properties))
     }
 
+    /// Plays a game from the empty board with `player1` as red, returning the winner and the
+    /// final board
     fn play<P1: Player, P2: Player>(
         &self,
         player1: &Agent<P1>,
         player2: &Agent<P2>,
-    ) -> (game::Spot, usize) {
-        let mut board = game::Board::new();
-        let mut current_color = game::Spot::RED;
-        let winner: game::Spot;
-
-        'outer: loop {
-            let mut temp = if current_color == game::Spot::RED {
-                player1.player.get_move(board.positions)
-            } else {
-                player2.player.get_move(board.positions)
-            };
-
-            'inner: loop {
-                let idx = temp
-                    .iter()
-                    .enumerate()
-                    .max_by(|(_, a), (_, b)| a.partial_cmp(&b).unwrap_or(Ordering::Equal))
-                    .unwrap();
-
-                match board.insert_top(idx.0, current_color) {
-                    (true, Some(win)) => {
-                        winner = win;
-                        break 'outer;
-                    }
-                    (true, None) => {
-                        break 'inner;
-                    }
-                    (_, _) => {
-                        temp[idx.0] = -100000.0;
-                    }
-                };
-            }
-
-            current_color = if current_color == game::Spot::RED {
-                game::Spot::YELLOW
-            } else {
-                game::Spot::RED
-            };
-        }
-
-        (winner, board.moves())
+    ) -> (game::Spot, game::Board) {
+        game::play_game(
+            game::Board::new(),
+            game::Spot::RED,
+            &player1.player,
+            &player2.player,
+            self.properties.tie_break,
+            self.properties.illegal_move,
+        )
     }
 
-    fn get_fitness<P1: Player, P2: Player>(
+    /// Plays a game starting from an existing `board`, with `current_color` to move.
+    /// `player1` always plays red and `player2` always plays yellow.
+    fn play_from<P1: Player, P2: Player>(
         &self,
+        board: game::Board,
+        current_color: game::Spot,
         player1: &Agent<P1>,
         player2: &Agent<P2>,
-    ) -> (i32, i32) {
+    ) -> (game::Spot, usize) {
+        game::play_from(
+            board,
+            current_color,
+            &player1.player,
+            &player2.player,
+            self.properties.tie_break,
+            self.properties.illegal_move,
+        )
+    }
+
+    /// Fitness change of the red and the yellow player of a game won by `winner` that ended
+    /// on `board`
+    fn game_fitness(&self, winner: game::Spot, board: &game::Board) -> (i32, i32) {
         let win_amount = 1;
-        let (winner1, moves1) = self.play(player1, player2);
-        let (x, y) = match winner1 {
+        let draw_amount = -self.properties.draw_penalty;
+        let bonus = self.properties.draw_position_bonus;
+        match winner {
             game::Spot::RED => {
-                // player1 wins
+                // red wins
                 (win_amount, -win_amount)
             }
             game::Spot::YELLOW => {
-                // player2 wins
+                // yellow wins
                 (-win_amount, win_amount)
             }
             game::Spot::EMPTY => {
-                // tie
-                (0, 0)
+                // tie, with the bonus for whoever had the better position
+                match board.evaluate().cmp(&0) {
+                    Ordering::Greater => (draw_amount + bonus, draw_amount),
+                    Ordering::Less => (draw_amount, draw_amount + bonus),
+                    Ordering::Equal => (draw_amount, draw_amount),
+                }
             }
-        };
+        }
+    }
 
-        let (winner2, moves2) = self.play(player2, player1);
-        let (temp2, temp1) = match winner2 {
-            game::Spot::RED => {
-                // player1 wins
-                (win_amount, -win_amount)
+    /// Fitness of `agent` against `opponent` in the game where it plays red and in the one
+    /// where it plays yellow. With `symmetric_eval`, their sum is the agent's fitness from
+    /// `get_fitness`.
+    fn fitness_by_color<P1: Player, P2: Player>(
+        &self,
+        agent: &Agent<P1>,
+        opponent: &Agent<P2>,
+    ) -> (i32, i32) {
+        let (winner, board) = self.play(agent, opponent);
+        let (as_red, _) = self.game_fitness(winner, &board);
+        let (winner, board) = self.play(opponent, agent);
+        let (_, as_yellow) = self.game_fitness(winner, &board);
+        (as_red, as_yellow)
+    }
+
+    fn get_fitness<P1: Player, P2: Player>(
+        &self,
+        player1: &Agent<P1>,
+        player2: &Agent<P2>,
+    ) -> (i32, i32, GameTally) {
+        let (first, second, tally) = self.pairing(player1, player2);
+        (first.fitness, second.fitness, tally)
+    }
+
+    /// Plays `player1` against `player2` with `player1` as red, and again with the colors
+    /// swapped when `symmetric_eval` is on, `games_per_color` times each. Returns the results
+    /// of both players and the outcomes of the games.
+    fn pairing<P1: Player, P2: Player>(
+        &self,
+        player1: &Agent<P1>,
+        player2: &Agent<P2>,
+    ) -> PairingResult {
+        let mut first = SelfPlayRecord::default();
+        let mut second = SelfPlayRecord::default();
+        let mut tally = GameTally::default();
+
+        for _ in 0..self.properties.games_per_color {
+            let (winner, board) = self.play(player1, player2);
+            self.record_game(winner, &board, &mut first, &mut second, &mut tally);
+
+            if self.properties.symmetric_eval {
+                let (winner, board) = self.play(player2, player1);
+                self.record_game(winner, &board, &mut second, &mut first, &mut tally);
             }
-            game::Spot::YELLOW => {
-                // player2 wins
-                (-win_amount, win_amount)
+        }
+
+        (first, second, tally)
+    }
+
+    /// Adds a game won by `winner` that ended on `board` to the results of its `red` and
+    /// `yellow` player and to `tally`
+    fn record_game(
+        &self,
+        winner: game::Spot,
+        board: &game::Board,
+        red: &mut SelfPlayRecord,
+        yellow: &mut SelfPlayRecord,
+        tally: &mut GameTally,
+    ) {
+        let (red_fitness, yellow_fitness) = self.game_fitness(winner, board);
+        red.add_game(red_fitness, winner == game::Spot::RED);
+        yellow.add_game(yellow_fitness, winner == game::Spot::YELLOW);
+        tally.add(winner);
+        tally.moves += board.moves();
+    }
+
+    /// The same as `pairing` for every pair of different agents `(i, j)`, with all of the
+    /// games of the generation played together by `game::play_games_lockstep`, so that each
+    /// agent scores all of its boards of a move in one batch
+    fn lockstep_pairings(&self) -> Vec<(usize, usize, PairingResult)> {
+        let n = self.agents.len();
+        let games_per_color = self.properties.games_per_color;
+        let symmetric = self.properties.symmetric_eval;
+        let pairs = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .collect::<Vec<_>>();
+        // In the order `pairing` plays them
+        let mut games = Vec::new();
+        for (i, j) in pairs.iter() {
+            for _ in 0..games_per_color {
+                games.push((*i, *j));
+                if symmetric {
+                    games.push((*j, *i));
+                }
             }
-            game::Spot::EMPTY => {
-                // tie
-                (0, 0)
+        }
+
+        let players = self
+            .agents
+            .iter()
+            .map(|agent| &agent.player as &(dyn MovePlayer + Sync))
+            .collect::<Vec<_>>();
+        let mut outcomes = game::play_games_lockstep(
+            &players,
+            &games,
+            self.properties.tie_break,
+            self.properties.illegal_move,
+        )
+        .into_iter();
+
+        pairs
+            .into_iter()
+            .map(|(i, j)| {
+                let mut first = SelfPlayRecord::default();
+                let mut second = SelfPlayRecord::default();
+                let mut tally = GameTally::default();
+                for _ in 0..games_per_color {
+                    let (winner, board) = outcomes.next().unwrap();
+                    self.record_game(winner, &board, &mut first, &mut second, &mut tally);
+                    if symmetric {
+                        let (winner, board) = outcomes.next().unwrap();
+                        self.record_game(winner, &board, &mut second, &mut first, &mut tally);
+                    }
+                }
+                (i, j, (first, second, tally))
+            })
+            .collect()
+    }
+
+    /// Plays `agent` against `opponent` from each of the 7 possible first moves, once with the
+    /// agent as red and once as yellow, and returns the agent's win-rate for each opening.
+    pub fn opening_report<P1: Player, P2: Player>(
+        &self,
+        agent: &Agent<P1>,
+        opponent: &Agent<P2>,
+    ) -> [N; 7] {
+        let mut win_rates = [0.0; 7];
+        for (column, win_rate) in win_rates.iter_mut().enumerate() {
+            let mut board = game::Board::new();
+            board.insert_top(column, game::Spot::RED);
+            let (winner_as_red, _) =
+                self.play_from(board.clone(), game::Spot::YELLOW, agent, opponent);
+            let (winner_as_yellow, _) = self.play_from(board, game::Spot::YELLOW, opponent, agent);
+
+            let wins = (winner_as_red == game::Spot::RED) as usize
+                + (winner_as_yellow == game::Spot::YELLOW) as usize;
+            *win_rate = wins as N / 2.0;
+        }
+
+        win_rates
+    }
+
+    /// Fitness of `agent` over `reference_games` games against the fixed `reference` opponent.
+    /// Games alternate colors and cycle through the 7 opening columns, so the set of games is
+    /// the same every generation.
+    fn reference_fitness<P1: Player, P2: Player>(
+        &self,
+        agent: &Agent<P1>,
+        reference: &Agent<P2>,
+    ) -> i32 {
+        (0..self.properties.reference_games)
+            .map(|game| {
+                let mut board = game::Board::new();
+                board.insert_top((game / 2) % 7, game::Spot::RED);
+                if game % 2 == 0 {
+                    match self
+                        .play_from(board, game::Spot::YELLOW, agent, reference)
+                        .0
+                    {
+                        game::Spot::RED => 1,
+                        game::Spot::YELLOW => -1,
+                        game::Spot::EMPTY => 0,
+                    }
+                } else {
+                    match self
+                        .play_from(board, game::Spot::YELLOW, reference, agent)
+                        .0
+                    {
+                        game::Spot::RED => -1,
+                        game::Spot::YELLOW => 1,
+                        game::Spot::EMPTY => 0,
+                    }
+                }
+            })
+            .sum()
+    }
+
+    /// Weight of the random agent games for the current generation in the curriculum schedule
+    fn curriculum_weight(&self) -> i32 {
+        let total = self.properties.curriculum_generations;
+        if self.generation >= total {
+            return 0;
+        }
+
+        (self.properties.curriculum_weight as isize * (total - self.generation) as isize
+            / total as isize) as i32
+    }
+
+    /// Weight of the minimax games for the current generation in the curriculum schedule
+    fn curriculum_minimax_weight(&self) -> i32 {
+        let from = self.properties.curriculum_minimax_from;
+        if from == 0 || self.generation < from {
+            return 0;
+        }
+
+        self.properties.curriculum_weight
+    }
+
+    /// Share of their parent's fitness that the offspring playing generation `gen` inherit,
+    /// by `preserve_fitness`
+    fn inherited_fitness_share(&self, gen: usize) -> N {
+        let total = self.properties.preserve_fitness;
+        match self.resumed_at {
+            Some(resumed_at) if gen >= resumed_at && gen - resumed_at < total => {
+                (total - (gen - resumed_at)) as N / total as N
             }
-        };
+            _ => 0.0,
+        }
+    }
 
-        let move_fitness = 0;
-        (x + temp1 + move_fitness, y + temp2 + move_fitness)
+    /// Adds a copy of `agent` to the population, into the buffers of a spare agent if there
+    /// is one left
+    fn push_copy(&mut self, agent: &Agent<Plr>) {
+        match self.spare.pop() {
+            Some(mut copy) => {
+                copy.clone_from(agent);
+                self.agents.push(copy);
+            }
+            None => self.agents.push(agent.clone()),
+        }
     }
 
-    fn mutate_crossover(&mut self, new_pop: &mut Vec<Agent<Plr>>) {
+    /// Replaces the population with `population_size` offspring of `new_pop` that will play
+    /// generation `gen`: unchanged elites first, then crossed over agents, then mutated copies
+    fn mutate_crossover(
+        &mut self,
+        new_pop: &mut Vec<Agent<Plr>>,
+        population_size: usize,
+        gen: usize,
+    ) {
+        // The offspring are copies of `new_pop`, so they inherit the frozen layers
+        for agent in new_pop.iter_mut() {
+            agent.player.freeze_layers(&self.properties.frozen_layers);
+        }
+        let elites = self
+            .properties
+            .elitism
+            .min(new_pop.len())
+            .min(population_size);
+        for elite in &new_pop[..elites] {
+            self.push_copy(elite);
+        }
+        let crossover_size = if self.properties.crossover_enabled {
+            elites + self.properties.crossover_size
+        } else {
+            0
+        };
         'crossover: for i in 0..new_pop.len() {
             for k in 0..new_pop.len() {
-                if i != k {
-                    if self.agents.len() < self.properties.crossover_size {
-                        let mut new_agent = new_pop[i].clone();
-                        new_agent.player.crossover(&new_pop[k].player);
-                        self.agents.push(new_agent);
+                // Layers of different architectures don't line up
+                if i != k && new_pop[i].player.structure() == new_pop[k].player.structure() {
+                    if self.agents.len() < crossover_size {
+                        self.push_copy(&new_pop[i]);
+                        let new_agent = self.agents.last_mut().unwrap();
+                        new_agent
+                            .player
+                            .crossover(&new_pop[k].player, &mut self.rng);
                     } else {
                         break 'crossover;
                     }
@@ -177,182 +1061,2808 @@ where
             }
         }
 
-        'copy: loop {
+        let crossed = self.agents.len();
+
+        // With `antithetic`, every survivor is copied twice in a row, into a pair that is
+        // mutated in opposite directions
+        let copies = if self.properties.antithetic { 2 } else { 1 };
+        // Nothing to copy from an empty generation
+        'copy: while !new_pop.is_empty() {
             for net in new_pop.iter() {
-                if !(self.agents.len() >= self.properties.population_size) {
-                    break 'copy;
+                for _ in 0..copies {
+                    if self.agents.len() >= population_size {
+                        break 'copy;
+                    }
+                    self.push_copy(net);
+                }
+            }
+        }
+
+        if self.properties.antithetic {
+            let (crossed, copied) = self.agents[elites..].split_at_mut(crossed - elites);
+            for agent in crossed {
+                agent.player.mutate(
+                    self.properties.mutation_range,
+                    self.properties.mutation_prob,
+                    &mut self.rng,
+                );
+            }
+            for pair in copied.chunks_mut(2) {
+                match pair {
+                    [first, second] => first.player.mutate_antithetic(
+                        &mut second.player,
+                        self.properties.mutation_range,
+                        self.properties.mutation_prob,
+                        &mut self.rng,
+                    ),
+                    [last] => last.player.mutate(
+                        self.properties.mutation_range,
+                        self.properties.mutation_prob,
+                        &mut self.rng,
+                    ),
+                    _ => unreachable!(),
+                }
+            }
+        } else {
+            for agent in self.agents.iter_mut().skip(elites) {
+                if self.properties.layer_mutation {
+                    agent.player.mutate_one_layer(
+                        self.properties.mutation_range,
+                        self.properties.mutation_prob,
+                        &mut self.rng,
+                    );
+                } else {
+                    agent.player.mutate(
+                        self.properties.mutation_range,
+                        self.properties.mutation_prob,
+                        &mut self.rng,
+                    );
                 }
-                self.agents.push(net.clone());
             }
         }
 
+        // Every offspring starts from its parent's fitness, copied along with its network
+        let share = self.inherited_fitness_share(gen);
         for agent in self.agents.iter_mut() {
-            agent.player.mutate(
-                self.properties.mutation_range,
-                self.properties.mutation_prob,
-            );
-            agent.fitness = 0;
+            agent.fitness = (agent.fitness as N * share).round() as i32;
+        }
+
+        // Fresh immigrants replace the end of the population, the copies made in the last
+        // round over the survivors
+        let architectures = self.properties.architectures();
+        let start = self.agents.len() - self.properties.immigrants(self.agents.len());
+        let start = start.max(elites);
+        for (i, agent) in self.agents.iter_mut().enumerate().skip(start) {
+            let (structure, activations) = architectures[i % architectures.len()].clone();
+            let mut player = Plr::new_from_param_with(structure, activations, &mut self.rng);
+            player.set_encoding(self.properties.encoding);
+            *agent = Agent::new(player);
         }
     }
 
-    #[inline(always)]
-    pub fn get_range(s: usize, e: isize) -> Box<dyn Iterator<Item = usize>> {
-        if e <= -1 {
-            Box::new((s..).into_iter())
+    /// Writes the survivors of the current generation to `<file_path>_<generation>`.
+    /// With `save_best_only`, only the top agent is written.
+    fn save_generation(&self, new_pop: &[Agent<Plr>]) -> Result<(), Box<dyn Error>> {
+        create_dir_all(
+            self.properties
+                .file_path
+                .parent()
+                .unwrap_or(path::Path::new("")),
+        )?;
+        helpers::write_save(
+            &self.generation_path(),
+            &self.saved_agents(new_pop),
+            helpers::SaveFormat::Cbor,
+        )
+    }
+
+    /// Path the current generation is saved to
+    fn generation_path(&self) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}_{}",
+            self.properties.file_path.to_str().unwrap(),
+            self.generation
+        ))
+    }
+
+    /// The agents of `new_pop` that `save_generation` writes
+    fn saved_agents<'b>(&self, new_pop: &'b [Agent<Plr>]) -> &'b [Agent<Plr>] {
+        if self.properties.save_best_only {
+            &new_pop[0..1]
         } else {
-            Box::new((s..(e as usize)).into_iter())
+            new_pop
         }
     }
 
-    #[inline(always)]
-    pub fn training_loop(&mut self, start: usize) -> Result<(), Box<dyn Error>> {
-        for gen in Self::get_range(start, self.properties.generations) {
-            self.generation = gen;
-
-            // Generation loop
-            let fitness_diffs = Arc::new(Mutex::new(vec![0; self.agents.len()]));
-            (0..self.agents.len()).into_par_iter().for_each(|i| {
-                let mut i_fitness_delta = 0;
-                for j in 0..self.agents.len() {
-                    if i != j {
-                        // Play against each other
-                        let fitnesses = self.get_fitness(&self.agents[i], &self.agents[j]);
-                        i_fitness_delta += fitnesses.0;
-                        let mut obj = fitness_diffs.lock().unwrap();
-                        obj[j] += fitnesses.1;
-                        std::mem::drop(obj);
-                    }
-                }
+    /// Saves the initial population the way training would, reads it back and checks that
+    /// every agent survived the round trip unchanged, then deletes the save. Refuses to run
+    /// when the save would overwrite an existing one. Returns the path that was written.
+    pub fn dry_save(&self) -> Result<path::PathBuf, Box<dyn Error>>
+    where
+        Plr: PartialEq,
+    {
+        let path = self.generation_path();
+        if path.exists() {
+            return Err(
+                format!("{} already exists and would be overwritten", path.display()).into(),
+            );
+        }
+        self.save_generation(&self.agents)?;
+        let loaded = helpers::read_save::<Vec<Agent<Plr>>>(&path);
+        std::fs::remove_file(&path)?;
+        let loaded = loaded?;
+        let saved = self.saved_agents(&self.agents);
+        if loaded.len() != saved.len() {
+            return Err(format!(
+                "saved {} agents, but read back {}",
+                saved.len(),
+                loaded.len()
+            )
+            .into());
+        }
+        if let Some(i) = (0..saved.len()).find(|i| loaded[*i] != saved[*i]) {
+            return Err(format!("agent {} changed after saving and loading it", i).into());
+        }
+        Ok(path)
+    }
+
+    /// Index of the first agent that differs between this population and `other`'s, down to
+    /// the last bit of every weight and fitness. A population that is longer than the other
+    /// differs at the end of the shorter one.
+    pub fn first_difference(&self, other: &Pool<Plr>) -> Option<usize>
+    where
+        Plr: PartialEq,
+    {
+        let common = self.agents.len().min(other.agents.len());
+        (0..common)
+            .find(|i| self.agents[*i] != other.agents[*i])
+            .or((self.agents.len() != other.agents.len()).then_some(common))
+    }
+
+    fn best_path(&self) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}_best",
+            self.properties.file_path.to_str().unwrap()
+        ))
+    }
+
+    fn checkpoint_path(&self) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}_checkpoint",
+            self.properties.file_path.to_str().unwrap()
+        ))
+    }
+
+    /// Writes the entire pool, including its properties and the generation it is about to play,
+    /// to `<file_path>_checkpoint`
+    fn save_checkpoint(&self) -> Result<(), Box<dyn Error>> {
+        helpers::write_save(&self.checkpoint_path(), self, helpers::SaveFormat::Cbor)
+    }
+
+    /// Replaces the population with the offspring of `saved`, the survivors of saved
+    /// generation `gen`, to play generation `gen` again
+    fn resume_from_save(&mut self, gen: usize, saved: &mut Vec<Agent<Plr>>) {
+        self.resumed_at = Some(gen);
+        self.agents.clear();
+        self.mutate_crossover(saved, self.properties.population_at(gen), gen);
+    }
+
+    /// Takes the population, RNG and generation of `checkpoint`, but keeps the properties of
+    /// this pool, so that the flags of the resumed run apply, e.g. a raised `generations`
+    fn resume_from_checkpoint(&mut self, checkpoint: Pool<Plr>) {
+        let properties = self.properties.clone();
+        *self = checkpoint;
+        self.properties = properties;
+    }
+
+    /// Loads a pool written by `save_checkpoint`
+    pub fn load_checkpoint(checkpoint_path: &path::Path) -> Result<Pool<Plr>, Box<dyn Error>> {
+        let file = File::open(checkpoint_path)?;
+        Ok(serde_cbor::from_reader(file)?)
+    }
+
+    /// Loads the opponent for `eval_opponent_from`, checking that its network takes the same
+    /// inputs and gives the same outputs as the pool's networks
+    fn load_eval_opponent(&self, opponent_path: &path::Path) -> Result<Agent<Plr>, Box<dyn Error>> {
+        let opponent: Plr = game::load_agent_file(opponent_path)?;
+        if let Some(structure) = opponent.structure() {
+            let expected = &self.properties.structure;
+            if structure.first() != expected.first() || structure.last() != expected.last() {
+                return Err(format!(
+                    "opponent network has structure {:?}, which is incompatible with {:?}",
+                    structure, expected
+                )
+                .into());
+            }
+        }
+        Ok(Agent::new(opponent))
+    }
+
+    /// Writes the fitness and a checksum of the weights of every agent, in population order,
+    /// to `<file_path>_snapshot_<generation>.csv`. Two runs that agree up to this generation
+    /// write identical files.
+    fn write_snapshot(&self) -> Result<(), Box<dyn Error>> {
+        let path = format!(
+            "{}_snapshot_{}.csv",
+            self.properties.file_path.to_str().unwrap(),
+            self.generation
+        );
+        let mut file = File::create(&path[..])?;
+        writeln!(file, "agent,fitness,checksum")?;
+        for (i, agent) in self.agents.iter().enumerate() {
+            writeln!(
+                file,
+                "{},{},{:016x}",
+                i,
+                agent.fitness,
+                weight_checksum(&agent.player.weight_vector())
+            )?;
+        }
+        Ok(())
+    }
 
-                let mut obj = fitness_diffs.lock().unwrap();
-                obj[i] += i_fitness_delta;
-                std::mem::drop(obj);
+    /// Replays the first `dump_games` self-play games, in the order `self_play` plays them,
+    /// and writes each to `<file_path>_games/game_<n>.txt`
+    fn dump_games(&self) -> Result<(), Box<dyn Error>> {
+        let dir = path::PathBuf::from(format!(
+            "{}_games",
+            self.properties.file_path.to_str().unwrap()
+        ));
+        create_dir_all(&dir)?;
+        let n = self.agents.len();
+        let mut pairings = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| i != *j).map(move |j| (i, j)))
+            .flat_map(|(i, j)| {
+                let colors = if self.properties.symmetric_eval { 2 } else { 1 };
+                vec![(i, j), (j, i)]
+                    .into_iter()
+                    .take(colors)
+                    .cycle()
+                    .take(colors * self.properties.games_per_color)
             });
+        for game in 0..self.properties.dump_games {
+            let (red, yellow) = match pairings.next() {
+                Some(pairing) => pairing,
+                None => break,
+            };
+            let (winner, board) = game::play_game(
+                game::Board::new(),
+                game::Spot::RED,
+                &self.agents[red].player,
+                &self.agents[yellow].player,
+                self.properties.tie_break,
+                self.properties.illegal_move,
+            );
+            let name = |i: usize| format!("agent {} ({})", i, self.agents[i].player.name());
+            game::write_replay(
+                &dir.join(format!("game_{}.txt", game)),
+                &name(red),
+                &name(yellow),
+                &board,
+                winner,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn config_path(&self) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}_config.json",
+            self.properties.file_path.to_str().unwrap()
+        ))
+    }
 
-            for (i, fitness_dif) in fitness_diffs.lock().unwrap().iter().enumerate() {
-                self.agents[i].fitness += fitness_dif;
+    fn stats_path(&self) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}_stats.jsonl",
+            self.properties.file_path.to_str().unwrap()
+        ))
+    }
+
+    /// Appends a line to `<file_path>_history.log` noting that the run resumed at generation
+    /// `gen` and every property that differs from the saved `_config.json`, then updates the
+    /// saved config to the current properties, so the log holds every change over the run
+    fn record_resume(&self, gen: usize) -> Result<(), Box<dyn Error>> {
+        // Through text, so that floats compare the same way they do in the saved file
+        let current = serde_json::from_str(&serde_json::to_string(&self.properties)?)?;
+        let changes = match std::fs::read_to_string(self.config_path()) {
+            Ok(saved) => {
+                let changes = changed_properties(&serde_json::from_str(&saved)?, &current);
+                if changes.is_empty() {
+                    "no changed properties".to_string()
+                } else {
+                    format!("changed {}", changes.join(", "))
+                }
             }
+            Err(_) => "no saved config to compare against".to_string(),
+        };
+        let history_path = format!(
+            "{}_history.log",
+            self.properties.file_path.to_str().unwrap()
+        );
+        let mut history = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(history_path)?;
+        writeln!(history, "generation {}: resumed, {}", gen, changes)?;
+        self.save_config()
+    }
 
-            self.agents.sort_unstable_by_key(|x| Reverse(x.fitness));
-            let mut new_pop = self
-                .agents
-                .drain(0..self.properties.surviving_amount)
-                .collect::<Vec<_>>();
-            self.agents.clear();
+    /// Writes the properties of this run to `<file_path>_config.json`, so that it can be
+    /// reproduced with `--config`
+    fn save_config(&self) -> Result<(), Box<dyn Error>> {
+        create_dir_all(
+            self.properties
+                .file_path
+                .parent()
+                .unwrap_or(path::Path::new("")),
+        )?;
+        helpers::write_atomic(&self.config_path(), |file| {
+            Ok(serde_json::to_writer_pretty(file, &self.properties)?)
+        })
+    }
 
-            if self.properties.save_interval >= 0
-                && self.generation != 0
-                && self.generation % (self.properties.save_interval as usize) == 0
-            {
-                print!(
-                    "{}Writing generation {}... {}",
-                    BLUE!(),
-                    self.generation,
+    /// Weight checksums of every agent when the fitness cache applies, `None` for agents
+    /// whose results can't be cached
+    fn cache_keys(&self) -> Vec<Option<u64>> {
+        let caching =
+            self.properties.fitness_cache && self.properties.tie_break != TieBreak::Random;
+        self.agents
+            .iter()
+            .map(|agent| {
+                let weights = agent.player.weight_vector();
+                // Players without weights can't be told apart
+                if caching && !weights.is_empty() {
+                    Some(weight_checksum(&weights))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// `pairing` for agents `i` and `j`, reusing the last generation's result when both
+    /// are unchanged. Every result with cacheable agents is recorded in `new_cache`.
+    fn pairing_fitness(
+        &self,
+        i: usize,
+        j: usize,
+        keys: &[Option<u64>],
+        new_cache: &Mutex<FitnessCache>,
+    ) -> PairingResult {
+        let key = match (keys[i], keys[j]) {
+            (Some(ki), Some(kj)) => (ki, kj),
+            _ => return self.pairing(&self.agents[i], &self.agents[j]),
+        };
+        let fitnesses = match self.fitness_cache.get(&key) {
+            Some(fitnesses) => *fitnesses,
+            None => self.pairing(&self.agents[i], &self.agents[j]),
+        };
+        new_cache.lock().unwrap().insert(key, fitnesses);
+        fitnesses
+    }
+
+    /// Plays every agent against every other agent, returning each agent's results and the
+    /// outcomes of all games played.
+    #[cfg(test)]
+    fn self_play(&mut self) -> (Vec<SelfPlayRecord>, GameTally) {
+        let (records, tally, _) = self.self_play_by_opponent();
+        (records, tally)
+    }
+
+    /// `self_play`, also returning the fitness every agent earned against each opponent when
+    /// `opponent_weighted` is set.
+    ///
+    /// The agents are only read during the games. Every task accumulates the results of its
+    /// pairings into its own vector, and the vectors are summed once all games are played,
+    /// so the tasks never wait on each other; only the fitness cache is shared.
+    fn self_play_by_opponent(&mut self) -> SelfPlayResults {
+        let n = self.agents.len();
+        let keys = self.cache_keys();
+        let new_cache = Mutex::new(HashMap::new());
+        let max_opponents = self.properties.max_opponents;
+        let order = if max_opponents > 0 && max_opponents < n.saturating_sub(1) {
+            let mut order = (0..n).collect::<Vec<_>>();
+            order.shuffle(&mut self.rng);
+            Some(order)
+        } else {
+            None
+        };
+        let pool = &*self;
+        let by_opponent = self.properties.opponent_weighted;
+        let empty = || {
+            (
+                vec![SelfPlayRecord::default(); n],
+                GameTally::default(),
+                Vec::new(),
+            )
+        };
+        let credit =
+            |results: &mut SelfPlayResults, i: usize, j: usize, record: &SelfPlayRecord| {
+                results.0[i].merge(record);
+                if by_opponent {
+                    results.2.push((i, j, record.fitness));
+                }
+            };
+        let play = |results: &mut SelfPlayResults, i: usize, j: usize| {
+            // Play against each other
+            let (first, second, tally) = pool.pairing_fitness(i, j, &keys, &new_cache);
+            credit(results, i, j, &first);
+            credit(results, j, i, &second);
+            results.1.merge(&tally);
+        };
+
+        let results = if let Some(order) = &order {
+            (0..n)
+                .into_par_iter()
+                .fold(empty, |mut results, position| {
+                    let i = order[position];
+                    for offset in 1..=max_opponents {
+                        let j = order[(position + offset) % n];
+                        let (first, _, tally) = pool.pairing_fitness(i, j, &keys, &new_cache);
+                        credit(&mut results, i, j, &first);
+                        results.1.merge(&tally);
+                        if !pool.properties.symmetric_eval {
+                            // Also play the game as yellow, like the full round-robin does
+                            let (_, as_yellow, tally) =
+                                pool.pairing_fitness(j, i, &keys, &new_cache);
+                            credit(&mut results, i, j, &as_yellow);
+                            results.1.merge(&tally);
+                        }
+                    }
+                    results
+                })
+                .reduce(empty, merge_self_play)
+        } else if self.properties.batched_eval {
+            let mut results = empty();
+            for (i, j, (first, second, tally)) in self.lockstep_pairings() {
+                credit(&mut results, i, j, &first);
+                credit(&mut results, j, i, &second);
+                results.1.merge(&tally);
+            }
+            results
+        } else if self.properties.eval_block_size == 0 {
+            (0..n)
+                .into_par_iter()
+                .fold(empty, |mut results, i| {
+                    for j in (0..n).filter(|j| *j != i) {
+                        play(&mut results, i, j);
+                    }
+                    results
+                })
+                .reduce(empty, merge_self_play)
+        } else {
+            // Split the pairings into tiles of `block` x `block` agents, so that each task only
+            // touches the networks of two small groups of agents, which stay in cache
+            let block = self.properties.eval_block_size;
+            let blocks = n.div_ceil(block);
+            (0..blocks * blocks)
+                .into_par_iter()
+                .fold(empty, |mut results, tile| {
+                    let rows = (tile / blocks) * block..((tile / blocks + 1) * block).min(n);
+                    let cols = (tile % blocks) * block..((tile % blocks + 1) * block).min(n);
+                    for i in rows {
+                        for j in cols.clone().filter(|j| *j != i) {
+                            play(&mut results, i, j);
+                        }
+                    }
+                    results
+                })
+                .reduce(empty, merge_self_play)
+        };
+
+        // Only keep the pairings of this generation, so entries of changed agents are dropped
+        self.fitness_cache = new_cache.into_inner().unwrap();
+        results
+    }
+
+    #[inline(always)]
+    pub fn get_range(s: usize, e: isize) -> Box<dyn Iterator<Item = usize>> {
+        if e <= -1 {
+            Box::new((s..).into_iter())
+        } else {
+            Box::new((s..(e as usize)).into_iter())
+        }
+    }
+
+    /// Iterator that plays generation after generation starting at `start`, until
+    /// `generations` is reached or `max_runtime` runs out, yielding a report after each one.
+    /// The caller can stop whenever it likes; the pool is then left ready for the next
+    /// generation.
+    pub fn generations(&mut self, start: usize) -> Generations<'_, Plr> {
+        let range = Self::get_range(start, self.properties.generations);
+        // A runtime too long to add to the clock never runs out
+        let deadline = self
+            .properties
+            .max_runtime
+            .and_then(|runtime| Instant::now().checked_add(runtime));
+        if self.properties.profile_memory {
+            alloc_profile::enable();
+        }
+        Generations {
+            pool: self,
+            range,
+            deadline,
+            out_of_time: false,
+        }
+    }
+
+    /// Runs every generation from `start`, printing the report of each
+    #[inline(always)]
+    pub fn training_loop(&mut self, start: usize) -> Result<(), Box<dyn Error>> {
+        let report_outcomes = self.properties.report_outcomes;
+        let reference_games = self.properties.reference_games;
+        let stats_interval = self.properties.stats_interval;
+        let stats_path = Some(self.stats_path()).filter(|_| self.properties.stats_log);
+        for report in self.generations(start) {
+            let report = report?;
+            if stats_due(stats_interval, report.generation) {
+                report.print(report_outcomes, reference_games);
+            }
+            if let Some(stats_path) = &stats_path {
+                append_stats(stats_path, &report.stats_record())?;
+            }
+            if let Some(memory) = report.memory {
+                println!(
+                    "{}Allocated {} evaluating, {} breeding and {} saving{}",
+                    GREEN!(),
+                    memory.evaluation,
+                    memory.breeding,
+                    memory.saving,
                     RESET!()
                 );
-                create_dir_all(
-                    self.properties
-                        .file_path
-                        .parent()
-                        .unwrap_or(path::Path::new("")),
-                )?;
-                let path = format!(
-                    "{}_{}",
-                    self.properties.file_path.to_str().unwrap(),
-                    self.generation
-                );
-                let file = File::create(&path[..])?;
-
-                serde_cbor::to_writer(file, &new_pop)?;
+            }
+            if report.out_of_time {
                 println!(
-                    "{}Done writing generation {}{}",
+                    "{}Reached the maximum runtime, stopping after generation {}{}",
                     BLUE!(),
-                    self.generation,
+                    report.generation,
                     RESET!()
                 );
             }
+        }
+        Ok(())
+    }
+
+    /// Plays generation `gen`: evaluates, selects, saves and compares as configured, and
+    /// breeds the next generation. Once evaluation ends past `deadline`, the generation is
+    /// saved and reported as the last one.
+    fn run_generation(
+        &mut self,
+        gen: usize,
+        deadline: Option<Instant>,
+    ) -> Result<GenerationReport, Box<dyn Error>> {
+        let start = Instant::now();
+        self.generation = gen;
+        let mut memory = MemoryProfile::default();
+
+        let evaluation = alloc_profile::measure(&mut memory.evaluation, || self.evaluate());
+        if self.generation == 0 && self.properties.dump_games > 0 {
+            self.dump_games()?;
+        }
+        if self.generation as isize == self.properties.snapshot_at {
+            self.write_snapshot()?;
+        }
+        let mut fitnesses = self
+            .agents
+            .iter()
+            .map(|agent| agent.fitness)
+            .collect::<Vec<_>>();
+        fitnesses.sort_unstable_by_key(|fitness| Reverse(*fitness));
+
+        let survivors = alloc_profile::measure(&mut memory.breeding, || self.select());
+        if survivors.is_empty() {
+            return Err(format!("no agents survived generation {}", gen).into());
+        }
 
-            if self.properties.compare_interval >= 0
+        // Checked after evaluation, which takes up most of the generation, so that the last
+        // generation played is always the one saved
+        let out_of_time = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        let saving = out_of_time
+            || self.properties.save_interval >= 0
                 && self.generation != 0
-                && self.generation % (self.properties.compare_interval as usize) == 0
-            {
-                print!(
-                    "{}Calculating fitness relative to dumb agent...{} ",
-                    BLUE!(),
-                    RESET!()
-                );
-                let mut random_fitness = 0;
-                for agent in new_pop[0..1].iter() {
-                    random_fitness += self.get_fitness(agent, &Agent::new(RandomPlayer::new())).0;
+                && self.generation % (self.properties.save_interval as usize) == 0;
+        if saving {
+            print!(
+                "{}Writing generation {}... {}",
+                BLUE!(),
+                self.generation,
+                RESET!()
+            );
+            alloc_profile::measure(&mut memory.saving, || {
+                self.save_generation(&survivors)?;
+                if self.properties.keep_last >= 0 {
+                    helpers::prune_generations(
+                        &self.properties.file_path,
+                        self.properties.keep_last as usize,
+                    )?;
                 }
-                println!(
-                    "{}Top population has a fitness of {} against dumb agent.{}",
-                    GREEN!(),
-                    random_fitness,
-                    RESET!()
-                );
+                Ok::<_, Box<dyn Error>>(())
+            })?;
+            println!(
+                "{}Done writing generation {}{}",
+                BLUE!(),
+                self.generation,
+                RESET!()
+            );
+        }
+
+        let mut random_fitness = None;
+        if self.properties.compare_interval >= 0
+            && self.generation != 0
+            && self.generation % (self.properties.compare_interval as usize) == 0
+        {
+            random_fitness = Some(self.compare(&survivors));
+        }
+
+        alloc_profile::measure(&mut memory.breeding, || self.reproduce(survivors));
+
+        if self.properties.checkpoint_full && saving {
+            // The checkpoint holds the population of the next generation, so resuming
+            // continues exactly where this run left off
+            self.generation = gen + 1;
+            alloc_profile::measure(&mut memory.saving, || self.save_checkpoint())?;
+        }
+
+        Ok(GenerationReport {
+            generation: gen,
+            fitnesses,
+            evaluation,
+            duration: start.elapsed(),
+            out_of_time,
+            memory: Some(memory).filter(|_| self.properties.profile_memory),
+            random_fitness,
+        })
+    }
+
+    /// Plays all of this generation's games and adds the results to every agent's fitness:
+    /// self-play, plus the curriculum and reference games when they are enabled
+    pub fn evaluate(&mut self) -> Evaluation {
+        let (mut records, tally, points) = self.self_play_by_opponent();
+        let aggregation = self.properties.fitness_aggregation;
+        if self.properties.opponent_weighted {
+            records = weight_by_opponent(&records, &points, aggregation);
+        }
+        for (agent, record) in self.agents.iter_mut().zip(records) {
+            agent.fitness += aggregation.apply(&record);
+        }
+
+        // Curriculum: early generations also play against a weak fixed opponent
+        let curriculum_weight = self.curriculum_weight();
+        if curriculum_weight != 0 {
+            let random_fitnesses = (0..self.agents.len())
+                .into_par_iter()
+                .map(|i| {
+                    self.get_fitness(&self.agents[i], &Agent::new(RandomPlayer::new()))
+                        .0
+                })
+                .collect::<Vec<_>>();
+            for (agent, random_fitness) in self.agents.iter_mut().zip(random_fitnesses) {
+                agent.fitness += random_fitness * curriculum_weight;
+            }
+        }
+        // and late ones against a stronger one that takes every win in one move
+        let minimax_weight = self.curriculum_minimax_weight();
+        if minimax_weight != 0 {
+            let minimax_fitnesses = (0..self.agents.len())
+                .into_par_iter()
+                .map(|i| {
+                    let minimax = Agent::new(MinimaxPlayer::new(Duration::ZERO));
+                    self.get_fitness(&self.agents[i], &minimax).0
+                })
+                .collect::<Vec<_>>();
+            for (agent, minimax_fitness) in self.agents.iter_mut().zip(minimax_fitnesses) {
+                agent.fitness += minimax_fitness * minimax_weight;
             }
+        }
 
-            print!(
-                "{}Top fitness: {}. {}",
+        // Degenerate networks that ignore the board
+        let mut constant_outputs = 0;
+        if self.properties.constant_output_penalty != 0 {
+            let boards = opening_boards();
+            let constant = self
+                .agents
+                .par_iter()
+                .map(|agent| has_constant_output(&agent.player, &boards))
+                .collect::<Vec<_>>();
+            for (agent, constant) in self.agents.iter_mut().zip(constant) {
+                if constant {
+                    agent.fitness -= self.properties.constant_output_penalty;
+                    constant_outputs += 1;
+                }
+            }
+        }
+
+        // Absolute skill anchor: games against a reference opponent that never changes
+        let mut reference_mean = None;
+        if self.properties.reference_games > 0 {
+            let reference_fitnesses = (0..self.agents.len())
+                .into_par_iter()
+                .map(|i| self.reference_fitness(&self.agents[i], &Agent::new(RandomPlayer::new())))
+                .collect::<Vec<_>>();
+            reference_mean =
+                Some(reference_fitnesses.iter().sum::<i32>() as N / reference_fitnesses.len() as N);
+            for (agent, reference_fitness) in self.agents.iter_mut().zip(reference_fitnesses) {
+                agent.fitness += reference_fitness;
+            }
+        }
+
+        Evaluation {
+            tally,
+            reference_mean,
+            constant_outputs,
+        }
+    }
+
+    /// Removes every agent from the pool and returns the `surviving_amount` fittest ones,
+    /// fittest first
+    pub fn select(&mut self) -> Vec<Agent<Plr>> {
+        self.agents.sort_unstable_by_key(|x| Reverse(x.fitness));
+        let survivors = self
+            .agents
+            .drain(0..self.properties.surviving_amount)
+            .collect::<Vec<_>>();
+        self.spare.append(&mut self.agents);
+        survivors
+    }
+
+    /// Fills the pool with the next generation, bred from `survivors`
+    pub fn reproduce(&mut self, mut survivors: Vec<Agent<Plr>>) {
+        let size = self.properties.population_at(self.generation + 1);
+        self.mutate_crossover(&mut survivors, size, self.generation + 1);
+    }
+
+    /// Total fitness of `agents` against the random agent
+    fn fitness_against_random(&self, agents: &[Agent<Plr>]) -> i32 {
+        agents
+            .par_iter()
+            .map(|agent| self.get_fitness(agent, &Agent::new(RandomPlayer::new())).0)
+            .sum()
+    }
+
+    /// Mean fitness of the current population against the random agent
+    pub fn mean_fitness_against_random(&self) -> N {
+        self.fitness_against_random(&self.agents) as N / self.agents.len().max(1) as N
+    }
+
+    /// Total fitness of `agents` against a seeded random opponent per game set, averaged over
+    /// `compare_games` sets, the share of all those games they won and their average length
+    fn random_comparison<P: Player + Sync>(&self, agents: &[Agent<P>]) -> (N, N, N) {
+        let sets = self.properties.compare_games.max(1);
+        let (record, tally) = agents
+            .par_iter()
+            .map(|agent| {
+                let mut record = SelfPlayRecord::default();
+                let mut tally = GameTally::default();
+                for set in 0..sets {
+                    // Every agent plays the same opponents
+                    let seed = (self.generation * sets + set) as u64;
+                    let random = Agent::new(SeededRandomPlayer::new(seed));
+                    let (first, _, games) = self.pairing(agent, &random);
+                    record.merge(&first);
+                    tally.merge(&games);
+                }
+                (record, tally)
+            })
+            .reduce(Default::default, |mut a, b| {
+                a.0.merge(&b.0);
+                a.1.merge(&b.1);
+                a
+            });
+        (
+            record.fitness as N / sets as N,
+            record.wins as N / record.games.max(1) as N,
+            tally.average_moves(),
+        )
+    }
+
+    /// Prints how the survivors do against the random agent and the comparison opponent,
+    /// returning their average fitness against the random agent
+    fn compare(&self, survivors: &[Agent<Plr>]) -> N {
+        let swapped;
+        let survivors = if self.properties.red_perspective {
+            swapped = survivors
+                .iter()
+                .cloned()
+                .map(|mut agent| {
+                    agent.player.set_red_perspective(true);
+                    agent
+                })
+                .collect::<Vec<_>>();
+            &swapped[..]
+        } else {
+            survivors
+        };
+        print!(
+            "{}Calculating fitness relative to dumb agent...{} ",
+            BLUE!(),
+            RESET!()
+        );
+        let (random_fitness, win_rate, average_moves) = self.random_comparison(survivors);
+        println!(
+            "{}Surviving population has a total fitness of {:.1} (average {:.2} over {} agents, {} game sets) against dumb agent, winning {:.0}% of games, avg moves to decision: {:.1}.{}",
+            GREEN!(),
+            random_fitness,
+            random_fitness / survivors.len() as N,
+            survivors.len(),
+            self.properties.compare_games.max(1),
+            win_rate * 100.0,
+            average_moves,
+            RESET!()
+        );
+        if self.properties.compare_both_colors {
+            let random = Agent::new(RandomPlayer::new());
+            let (as_red, as_yellow) = survivors
+                .par_iter()
+                .map(|agent| self.fitness_by_color(agent, &random))
+                .reduce(|| (0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+            let (top_red, top_yellow) = self.fitness_by_color(&survivors[0], &random);
+            println!(
+                "{}Against dumb agent as red (first): {} in total, {} for the top agent. As yellow (second): {} in total, {} for the top agent.{}",
                 GREEN!(),
-                new_pop.first().unwrap().fitness,
+                as_red,
+                top_red,
+                as_yellow,
+                top_yellow,
                 RESET!()
             );
-            self.mutate_crossover(&mut new_pop);
+        }
 
+        if let Some(opponent) = &self.eval_opponent {
+            let mut opponent_fitness = 0;
+            for agent in survivors.iter() {
+                opponent_fitness += self.get_fitness(agent, opponent).0;
+            }
+            let win_rates = self.opening_report(&survivors[0], opponent);
             println!(
-                "{}Generation {} done.{}",
-                CYAN!(),
-                self.generation,
+                "{}Surviving population has a total fitness of {} (average {:.2}) against the comparison opponent ({}). Top agent wins {:.0}% of games against it across all openings.{}",
+                GREEN!(),
+                opponent_fitness,
+                opponent_fitness as N / survivors.len() as N,
+                opponent.player.name(),
+                win_rates.iter().sum::<N>() / win_rates.len() as N * 100.0,
+                RESET!()
+            );
+        }
+
+        if let Some(benchmark) = &self.benchmark {
+            println!(
+                "{}{} of {} surviving agents beat the benchmark agent from {}.{}",
+                GREEN!(),
+                self.agents_beating(survivors, benchmark),
+                survivors.len(),
+                self.properties
+                    .benchmark_against
+                    .as_ref()
+                    .map_or(String::new(), |path| path.display().to_string()),
+                RESET!()
+            );
+        }
+
+        if !self.holdout.is_empty() {
+            let right = holdout::score(
+                &survivors[0].player,
+                &self.holdout,
+                self.properties.tie_break,
+            );
+            println!(
+                "{}Top agent plays a right move in {} of {} holdout positions ({:.0}%).{}",
+                GREEN!(),
+                right,
+                self.holdout.len(),
+                right as N / self.holdout.len() as N * 100.0,
+                RESET!()
+            );
+        }
+
+        if self.properties.warn_on_saturation {
+            let saturation = survivors[0]
+                .player
+                .saturation(&sample_boards(SATURATION_BOARDS));
+            println!(
+                "{}Saturated units of the top agent by layer: {}{}",
+                GREEN!(),
+                saturation
+                    .iter()
+                    .map(|share| format!("{:.0}%", share * 100.0))
+                    .collect::<Vec<_>>()
+                    .join(", "),
                 RESET!()
             );
+            for (layer, share) in saturation.iter().enumerate() {
+                if *share > SATURATION_WARNING {
+                    println!(
+                        "{}Warning: {:.0}% of the units in layer {} are saturated or dead on every sample board{}",
+                        YELLOW!(),
+                        share * 100.0,
+                        layer + 1,
+                        RESET!()
+                    );
+                }
+            }
+        }
+
+        if self.properties.move_histogram {
+            let counts = move_histogram(
+                &survivors[0].player,
+                &sample_games(HISTOGRAM_BOARDS),
+                self.properties.tie_break,
+            );
+            let total = counts.iter().sum::<usize>().max(1);
+            println!(
+                "{}Top agent moves by column over {} random boards: {}{}",
+                GREEN!(),
+                HISTOGRAM_BOARDS,
+                counts
+                    .iter()
+                    .enumerate()
+                    .map(|(column, count)| format!(
+                        "{}: {:.0}%",
+                        column + 1,
+                        *count as N / total as N * 100.0
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                RESET!()
+            );
+        }
+
+        if self.properties.opening_report {
+            let win_rates = self.opening_report(&survivors[0], &Agent::new(RandomPlayer::new()));
+            println!(
+                "{}Top agent win-rate by opening column: {}{}",
+                GREEN!(),
+                win_rates
+                    .iter()
+                    .enumerate()
+                    .map(|(column, rate)| format!("{}: {:.0}%", column + 1, rate * 100.0))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                RESET!()
+            );
+        }
+
+        if self.properties.show_best_game {
+            let (winner, board) = self.best_game(&survivors[0]);
+            let moves = board
+                .history()
+                .iter()
+                .map(|column| (column + 1).to_string())
+                .collect::<Vec<_>>();
+            let result = match winner {
+                game::Spot::RED => "won",
+                game::Spot::YELLOW => "lost",
+                game::Spot::EMPTY => "drew",
+            };
+            println!(
+                "{}Top agent {} as red against dumb agent, moves: {}{}",
+                GREEN!(),
+                result,
+                moves.join(" "),
+                RESET!()
+            );
+            println!("{}", board);
+        }
+
+        random_fitness / survivors.len() as N
+    }
+
+    /// Number of `agents` that end their pairing against `opponent` with a positive fitness
+    fn agents_beating<P1: Player + Sync, P2: Player + Sync>(
+        &self,
+        agents: &[Agent<P1>],
+        opponent: &Agent<P2>,
+    ) -> usize {
+        agents
+            .par_iter()
+            .filter(|agent| self.get_fitness(*agent, opponent).0 > 0)
+            .count()
+    }
+
+    /// Plays `top` as red against the random agent, returning the winner and the final board
+    fn best_game(&self, top: &Agent<Plr>) -> (game::Spot, game::Board) {
+        game::play_game(
+            game::Board::new(),
+            game::Spot::RED,
+            &top.player,
+            &RandomPlayer::new(),
+            self.properties.tie_break,
+            self.properties.illegal_move,
+        )
+    }
+
+    /// Checks that the networks of every architecture take as many inputs as the encoding
+    /// produces
+    fn check_encoding(&self) -> Result<(), Box<dyn Error>> {
+        let input_size = self.properties.encoding.encoder().input_size();
+        for (structure, _) in self.properties.architectures() {
+            match structure.first() {
+                Some(inputs) if *inputs == input_size => {}
+                inputs => {
+                    return Err(format!(
+                        "the {:?} encoding gives {} inputs, but the network structure {:?} starts \
+                         with {:?}",
+                        self.properties.encoding, input_size, structure, inputs
+                    )
+                    .into())
+                }
+            }
         }
         Ok(())
     }
 
     pub fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        self.check_encoding()?;
         println!("{}Looking for previous saves...{}", BLUE!(), RESET!());
-        let start: usize =
-            if let Some(val) = helpers::get_max_generation(&self.properties.file_path)? {
-                let filename = val.file_name();
-                let os_to_str = filename.to_str().unwrap();
-                let gen = os_to_str
-                    .split("_")
-                    .last()
-                    .unwrap()
-                    .parse::<usize>()
-                    .unwrap();
-                print!(
-                    "{}Detected generation {}, starting from there... {}",
-                    BLUE!(),
+        let checkpoint_path = self.checkpoint_path();
+        let checkpoint = if self.properties.checkpoint_full && checkpoint_path.exists() {
+            match Self::load_checkpoint(&checkpoint_path) {
+                Ok(checkpoint) => Some(checkpoint),
+                Err(e) => {
+                    eprintln!(
+                        "{}Ignoring the checkpoint, which failed to load: {}{}",
+                        YELLOW!(),
+                        e,
+                        RESET!()
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let latest = helpers::latest_valid_save::<Vec<Agent<Plr>>>(&self.properties.file_path)?;
+        // A generation saved after the checkpoint was written is more recent
+        let checkpoint = match (checkpoint, &latest) {
+            (Some(checkpoint), Some((gen, _, _))) if *gen > checkpoint.generation => {
+                eprintln!(
+                    "{}Ignoring the checkpoint at generation {}, since generation {} was saved after it{}",
+                    YELLOW!(),
+                    checkpoint.generation,
                     gen,
                     RESET!()
                 );
-                let file = File::open(val.path())?;
-                let mut new_pop: Vec<Agent<Plr>> = serde_cbor::from_reader(file)?;
-                self.agents.clear();
-                self.mutate_crossover(&mut new_pop);
-                println!("{}Loaded generations{}", BLUE!(), RESET!());
-                println!(
-                    "{}Starting with a population of {}{}",
-                    GREEN!(),
-                    self.agents.len(),
-                    RESET!()
-                );
-                gen
-            } else {
-                println!(
-                    "{}Starting with a population of {}{}",
-                    GREEN!(),
-                    self.properties.population_size,
-                    RESET!()
-                );
-                0
-            };
-
-        println!("");
-
-        self.training_loop(start)
+                None
+            }
+            (checkpoint, _) => checkpoint,
+        };
+        let start: usize = if let Some(checkpoint) = checkpoint {
+            self.resume_from_checkpoint(checkpoint);
+            println!(
+                "{}Resuming from the full checkpoint at generation {} with a population of {}{}",
+                BLUE!(),
+                self.generation,
+                self.agents.len(),
+                RESET!()
+            );
+            self.record_resume(self.generation)?;
+            self.generation
+        } else if let Some((gen, _, mut new_pop)) = latest {
+            print!(
+                "{}Detected generation {}, starting from there... {}",
+                BLUE!(),
+                gen,
+                RESET!()
+            );
+            self.resume_from_save(gen, &mut new_pop);
+            self.record_resume(gen)?;
+            println!("{}Loaded generations{}", BLUE!(), RESET!());
+            println!(
+                "{}Starting with a population of {}{}",
+                GREEN!(),
+                self.agents.len(),
+                RESET!()
+            );
+            gen
+        } else if self.properties.seed_from_best && self.best_path().exists() {
+            let mut best: Vec<Agent<Plr>> = helpers::read_save(&self.best_path())?;
+            if best.is_empty() {
+                return Err(format!("{} contains no agents", self.best_path().display()).into());
+            }
+            self.save_config()?;
+            self.agents.clear();
+            self.mutate_crossover(&mut best, self.properties.population_at(0), 0);
+            println!(
+                "{}Seeded a population of {} from {} agents in {}{}",
+                GREEN!(),
+                self.agents.len(),
+                best.len(),
+                self.best_path().display(),
+                RESET!()
+            );
+            0
+        } else {
+            self.save_config()?;
+            println!(
+                "{}Starting with a population of {}{}",
+                GREEN!(),
+                self.properties.population_size,
+                RESET!()
+            );
+            0
+        };
+
+        if let Some(opponent_path) = self.properties.eval_opponent_from.clone() {
+            let opponent = self.load_eval_opponent(&opponent_path)?;
+            println!(
+                "{}Loaded comparison opponent {} from {}{}",
+                BLUE!(),
+                opponent.player.name(),
+                opponent_path.display(),
+                RESET!()
+            );
+            self.eval_opponent = Some(opponent);
+        }
+        if let Some(benchmark_path) = self.properties.benchmark_against.clone() {
+            let benchmark = self.load_eval_opponent(&benchmark_path)?;
+            println!(
+                "{}Loaded benchmark agent {} from {}{}",
+                BLUE!(),
+                benchmark.player.name(),
+                benchmark_path.display(),
+                RESET!()
+            );
+            self.benchmark = Some(benchmark);
+        }
+        if let Some(holdout_path) = &self.properties.holdout {
+            self.holdout = holdout::load_holdout(holdout_path)?;
+            println!(
+                "{}Loaded {} holdout positions from {}{}",
+                BLUE!(),
+                self.holdout.len(),
+                holdout_path.display(),
+                RESET!()
+            );
+        }
+
+        println!("");
+
+        self.training_loop(start)
+    }
+}
+
+/// Whether the summary of generation `gen` is printed with `stats_interval`
+fn stats_due(stats_interval: isize, gen: usize) -> bool {
+    stats_interval > 0 && gen.is_multiple_of(stats_interval as usize)
+}
+
+/// The empty board and every position after one or two moves
+fn opening_boards() -> Vec<[[game::Spot; 6]; 7]> {
+    let mut boards = vec![game::Board::new().positions];
+    for first in 0..7 {
+        let mut board = game::Board::new();
+        board.insert_top(first, game::Spot::RED);
+        boards.push(board.positions);
+        for second in 0..7 {
+            let mut board = board.clone();
+            board.insert_top(second, game::Spot::YELLOW);
+            boards.push(board.positions);
+        }
+    }
+    boards
+}
+
+/// Whether `player` gives every column the same score on all of `boards`
+fn has_constant_output(player: &dyn MovePlayer, boards: &[[[game::Spot; 6]; 7]]) -> bool {
+    boards.iter().all(|board| {
+        let scores = player.get_move(*board);
+        scores
+            .iter()
+            .all(|score| (score - scores[0]).abs() <= CONSTANT_OUTPUT_TOLERANCE)
+    })
+}
+
+/// How often `player` picks each column on `boards`, choosing among the legal columns like it
+/// does in a game
+fn move_histogram(
+    player: &dyn MovePlayer,
+    boards: &[game::Board],
+    tie_break: TieBreak,
+) -> [usize; 7] {
+    let mut counts = [0; 7];
+    for board in boards {
+        let scores = player.get_move(board.positions);
+        if let Some(column) = agent::choose_move(&scores, board, tie_break) {
+            counts[column] += 1;
+        }
+    }
+    counts
+}
+
+/// Positions of `sample_games`
+fn sample_boards(count: usize) -> Vec<[[game::Spot; 6]; 7]> {
+    sample_games(count)
+        .into_iter()
+        .map(|board| board.positions)
+        .collect()
+}
+
+/// Boards reached by playing a random number of random moves from an empty board, before the
+/// game ends
+fn sample_games(count: usize) -> Vec<game::Board> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| {
+            let moves = rng.gen_range(0, 30);
+            game::Board::random_position(moves, &mut rng)
+        })
+        .collect()
+}
+
+/// Appends `record` to the stats log at `stats_path` as a line of JSON
+fn append_stats(stats_path: &path::Path, record: &StatsRecord) -> Result<(), Box<dyn Error>> {
+    let mut stats = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(stats_path)?;
+    writeln!(stats, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Every top-level property of `current` that differs from `saved`, as
+/// `<name>: <saved> -> <current>`, in name order
+fn changed_properties(saved: &serde_json::Value, current: &serde_json::Value) -> Vec<String> {
+    let missing = serde_json::Value::Null;
+    match current.as_object() {
+        Some(properties) => properties
+            .iter()
+            .filter_map(|(name, value)| {
+                let old = saved.get(name).unwrap_or(&missing);
+                (old != value).then(|| format!("{}: {} -> {}", name, old, value))
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Sums the self-play results of two sets of games
+fn merge_self_play(mut a: SelfPlayResults, b: SelfPlayResults) -> SelfPlayResults {
+    for (record, other) in a.0.iter_mut().zip(b.0.iter()) {
+        record.merge(other);
+    }
+    a.1.merge(&b.1);
+    a.2.extend(b.2);
+    a
+}
+
+/// `records` with the fitness of every game scaled by the strength of the opponent, by
+/// `points`. Strength is the opponent's flat fitness under `aggregation`, scaled to `0..=1`
+/// over the population, with every agent at `0.5` when they are all equally fit.
+fn weight_by_opponent(
+    records: &[SelfPlayRecord],
+    points: &[(usize, usize, i32)],
+    aggregation: FitnessAggregation,
+) -> Vec<SelfPlayRecord> {
+    let provisional = records
+        .iter()
+        .map(|record| aggregation.apply(record))
+        .collect::<Vec<_>>();
+    let min = provisional.iter().copied().min().unwrap_or(0);
+    let max = provisional.iter().copied().max().unwrap_or(0);
+    let strength = |agent: usize| {
+        if max == min {
+            0.5
+        } else {
+            (provisional[agent] - min) as N / (max - min) as N
+        }
+    };
+
+    let mut weighted = vec![0.0; records.len()];
+    for &(agent, opponent, fitness) in points {
+        // Wins against strong agents gain more, losses against them cost less
+        let scale = if fitness > 0 {
+            0.5 + strength(opponent)
+        } else {
+            1.5 - strength(opponent)
+        };
+        weighted[agent] += fitness as N * scale;
+    }
+    records
+        .iter()
+        .zip(weighted)
+        .map(|(record, fitness)| SelfPlayRecord {
+            fitness: fitness.round() as i32,
+            ..*record
+        })
+        .collect()
+}
+
+/// Cheap, run-independent hash of a weight vector
+fn weight_checksum(weights: &[N]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for weight in weights {
+        hasher.write_u32(weight.to_bits());
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+pub(crate) mod pool_tests {
+    use super::*;
+    use crate::ai::{agent::Evolvable, NNPlayer};
+
+    /// Small, fast properties that neither save nor compare
+    pub(crate) fn test_props(file_path: path::PathBuf) -> PoolProperties {
+        PoolProperties {
+            surviving_amount: 3,
+            elitism: 0,
+            mutation_range: 0.015,
+            mutation_prob: 0.05,
+            antithetic: false,
+            layer_mutation: false,
+            frozen_layers: Vec::new(),
+            crossover_size: 6,
+            crossover_enabled: true,
+            immigrant_fraction: 0.0,
+            population_size: 8,
+            population_schedule: Vec::new(),
+            structure: vec![42, 8, 7],
+            activations: vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+            extra_architectures: Vec::new(),
+            encoding: Encoding::Scalar,
+            generations: 1,
+            max_runtime: None,
+            seed: None,
+            save_interval: -1,
+            save_best_only: false,
+            checkpoint_full: false,
+            keep_last: -1,
+            seed_from_best: false,
+            preserve_fitness: 0,
+            compare_interval: -1,
+            compare_games: 1,
+            opening_report: false,
+            compare_both_colors: false,
+            show_best_game: false,
+            red_perspective: false,
+            curriculum_generations: 0,
+            curriculum_weight: 0,
+            curriculum_minimax_from: 0,
+            reference_games: 0,
+            draw_penalty: 0,
+            draw_position_bonus: 0,
+            fitness_aggregation: FitnessAggregation::Sum,
+            opponent_weighted: false,
+            warn_on_saturation: false,
+            move_histogram: false,
+            constant_output_penalty: 0,
+            report_outcomes: false,
+            stats_interval: 1,
+            eval_block_size: 0,
+            batched_eval: false,
+            max_opponents: 0,
+            symmetric_eval: true,
+            games_per_color: 1,
+            tie_break: TieBreak::First,
+            illegal_move: IllegalMove::Skip,
+            snapshot_at: -1,
+            dump_games: 0,
+            fitness_cache: false,
+            profile_memory: false,
+            stats_log: false,
+            eval_opponent_from: None,
+            benchmark_against: None,
+            holdout: None,
+            file_path,
+        }
+    }
+
+    fn test_dir(name: &str) -> path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("fourai_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Red fills the first column and yellow the second, so red wins vertically
+    struct FirstColumnPlayer;
+    impl Evolvable for FirstColumnPlayer {
+        fn new_from_param(_: Vec<usize>, _: Vec<nn::Activation>) -> Self {
+            FirstColumnPlayer
+        }
+    }
+    impl MovePlayer for FirstColumnPlayer {
+        fn get_move(&self, board: [[game::Spot; 6]; 7]) -> [N; 7] {
+            let reds = board
+                .iter()
+                .flatten()
+                .filter(|s| **s == game::Spot::RED)
+                .count();
+            let yellows = board
+                .iter()
+                .flatten()
+                .filter(|s| **s == game::Spot::YELLOW)
+                .count();
+            if reds == yellows {
+                [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+            } else {
+                [0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+            }
+        }
+    }
+
+    /// Plays the columns so that the board fills up without anyone winning
+    struct DrawingPlayer;
+    impl Evolvable for DrawingPlayer {
+        fn new_from_param(_: Vec<usize>, _: Vec<nn::Activation>) -> Self {
+            DrawingPlayer
+        }
+    }
+    impl MovePlayer for DrawingPlayer {
+        fn get_move(&self, board: [[game::Spot; 6]; 7]) -> [N; 7] {
+            // A full game that ends without anyone connecting four
+            const DRAW: [usize; 42] = [
+                0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 4, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4,
+                4, 4, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 5,
+            ];
+            let moves = board
+                .iter()
+                .flatten()
+                .filter(|s| **s != game::Spot::EMPTY)
+                .count();
+            let mut scores = [0.0; 7];
+            scores[*DRAW.get(moves).unwrap_or(&0)] = 1.0;
+            scores
+        }
+    }
+
+    #[test]
+    fn draw_penalty() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        let neutral: Pool<NNPlayer> = Pool::new(props.clone());
+        props.draw_penalty = 2;
+        let penalized: Pool<NNPlayer> = Pool::new(props);
+
+        let drawer = Agent::new(DrawingPlayer);
+        let (neutral_fitness, _, tally) = neutral.get_fitness(&drawer, &drawer);
+        assert_eq!(2, tally.draws);
+        let (penalized_fitness, _, _) = penalized.get_fitness(&drawer, &drawer);
+        assert!(penalized_fitness < neutral_fitness);
+        assert_eq!(-4, penalized_fitness);
+
+        // Decisive games are unaffected
+        let winner = Agent::new(FirstColumnPlayer);
+        assert_eq!(
+            neutral.get_fitness(&winner, &winner),
+            penalized.get_fitness(&winner, &winner)
+        );
+    }
+
+    #[test]
+    fn game_tally() {
+        let mut tally = GameTally::default();
+        tally.add(game::Spot::RED);
+        tally.add(game::Spot::RED);
+        tally.add(game::Spot::EMPTY);
+        let mut other = GameTally::default();
+        other.add(game::Spot::YELLOW);
+        tally.merge(&other);
+        assert_eq!(
+            GameTally {
+                red_wins: 2,
+                yellow_wins: 1,
+                draws: 1,
+                moves: 0
+            },
+            tally
+        );
+        assert_eq!(4, tally.games());
+        assert_eq!(0.0, tally.average_moves());
+
+        // A seven move win and a full board draw
+        let mut lengths = GameTally {
+            red_wins: 1,
+            yellow_wins: 0,
+            draws: 1,
+            moves: 7 + 42,
+        };
+        assert_eq!(24.5, lengths.average_moves());
+        lengths.merge(&GameTally {
+            red_wins: 0,
+            yellow_wins: 1,
+            draws: 0,
+            moves: 8,
+        });
+        assert_eq!(19.0, lengths.average_moves());
+
+        // Red wins both games of a pairing when it always takes the first column
+        let pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let winner = Agent::new(FirstColumnPlayer);
+        let (_, _, tally) = pool.get_fitness(&winner, &winner);
+        assert_eq!(2, tally.red_wins);
+        // Four in the first column takes seven moves
+        assert_eq!(14, tally.moves);
+        assert_eq!(7.0, tally.average_moves());
+        assert_eq!(2, tally.games());
+    }
+
+    #[test]
+    fn symmetric_eval() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        let symmetric: Pool<NNPlayer> = Pool::new(props.clone());
+        props.symmetric_eval = false;
+        let single: Pool<NNPlayer> = Pool::new(props);
+
+        // Red always wins, so swapping colors evens the pairing out
+        let winner = Agent::new(FirstColumnPlayer);
+        let (red, yellow, tally) = symmetric.get_fitness(&winner, &winner);
+        assert_eq!((0, 0, 2), (red, yellow, tally.games()));
+        let (red, yellow, tally) = single.get_fitness(&winner, &winner);
+        assert_eq!((1, -1, 1), (red, yellow, tally.games()));
+        assert_eq!(1, tally.red_wins);
+
+        // Self-play still pairs up both orders, so it stays zero sum
+        let mut single = single;
+        let (diffs, tally) = single.self_play();
+        assert_eq!(8 * 7, tally.games());
+        assert_eq!(0, diffs.iter().map(|record| record.fitness).sum::<i32>());
+    }
+
+    #[test]
+    fn red_perspective() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.illegal_move = IllegalMove::Lose;
+        let pool: Pool<NNPlayer> = Pool::new(props);
+        // Scores column 0 above the others while it holds more red than yellow pieces
+        let mut weights = vec![0.0; 7 * 43];
+        weights[0..6].fill(1.0);
+        let plain = Agent::new(NNPlayer::from_weights(
+            vec![42, 7],
+            vec![nn::Activation::Sigmoid],
+            &[weights],
+        ));
+        let mut swapped = plain.clone();
+        swapped.player.set_red_perspective(true);
+
+        // As yellow, the plain network keeps blocking column 0 until red has to play into
+        // the full column and forfeits. Swapped, it sees red's pieces as its own, plays
+        // column 1 and loses to four in column 0.
+        let opponent = Agent::new(FirstColumnPlayer);
+        let (plain_red, plain_yellow) = pool.fitness_by_color(&plain, &opponent);
+        let (swapped_red, swapped_yellow) = pool.fitness_by_color(&swapped, &opponent);
+        assert_eq!((1, -1), (plain_yellow, swapped_yellow));
+        // Only the games as yellow change
+        assert_eq!(plain_red, swapped_red);
+    }
+
+    #[test]
+    fn games_per_color() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.games_per_color = 2;
+        let symmetric: Pool<NNPlayer> = Pool::new(props.clone());
+        props.symmetric_eval = false;
+        let single: Pool<NNPlayer> = Pool::new(props.clone());
+
+        // Red always wins, in all four games
+        let winner = Agent::new(FirstColumnPlayer);
+        let (first, second, tally) = symmetric.pairing(&winner, &winner);
+        assert_eq!(4, tally.games());
+        assert_eq!(4, tally.red_wins);
+        assert_eq!((4, 4), (first.games, second.games));
+        assert_eq!((2, 2), (first.wins, second.wins));
+        assert_eq!((0, 0), (first.fitness, second.fitness));
+
+        // Both games as red count
+        let (red, yellow, tally) = single.get_fitness(&winner, &winner);
+        assert_eq!((2, -2, 2), (red, yellow, tally.games()));
+
+        props.games_per_color = 0;
+        assert!(Pool::<NNPlayer>::try_new(props).is_err());
+    }
+
+    #[test]
+    fn fitness_by_color() {
+        let pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let random = Agent::new(RandomPlayer::new());
+        for agent in pool.agents.iter() {
+            let (as_red, as_yellow) = pool.fitness_by_color(agent, &random);
+            assert_eq!(pool.get_fitness(agent, &random).0, as_red + as_yellow);
+        }
+
+        // Red always wins with the first column, whoever plays it
+        let winner = Agent::new(FirstColumnPlayer);
+        assert_eq!((1, -1), pool.fitness_by_color(&winner, &winner));
+    }
+
+    #[test]
+    fn blocked_self_play() {
+        let mut rows: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let (expected_diffs, expected_tally) = rows.self_play();
+
+        // Block sizes that do and don't divide the population, and one larger than it
+        for block in &[1, 3, 8, 20] {
+            let mut blocked = rows.clone();
+            blocked.properties.eval_block_size = *block;
+            let (diffs, tally) = blocked.self_play();
+            assert_eq!(expected_diffs, diffs);
+            assert_eq!(expected_tally, tally);
+        }
+    }
+
+    #[test]
+    fn batched_self_play() {
+        // Exact as long as the BLAS sums every row in the same order alone and in a batch
+        let mut props = test_props(path::PathBuf::from("unused"));
+        for (games_per_color, symmetric_eval) in [(1, true), (2, true), (1, false)] {
+            props.games_per_color = games_per_color;
+            props.symmetric_eval = symmetric_eval;
+            let mut serial: Pool<NNPlayer> = Pool::new(props.clone());
+            let mut batched = serial.clone();
+            batched.properties.batched_eval = true;
+            assert_eq!(serial.self_play(), batched.self_play());
+        }
+
+        // Per opponent points are the same too
+        props.opponent_weighted = true;
+        let mut serial: Pool<NNPlayer> = Pool::new(props);
+        let mut batched = serial.clone();
+        batched.properties.batched_eval = true;
+        let mut expected = serial.self_play_by_opponent();
+        let mut points = batched.self_play_by_opponent();
+        expected.2.sort_unstable();
+        points.2.sort_unstable();
+        assert_eq!(expected, points);
+    }
+
+    #[test]
+    fn checkpoint_full() {
+        let dir = test_dir("checkpoint_full");
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 2;
+        props.save_interval = 1;
+        props.checkpoint_full = true;
+
+        let mut trained: Pool<NNPlayer> = Pool::new(props.clone());
+        trained.training_loop(0).unwrap();
+        assert_eq!(2, trained.generation);
+
+        let loaded: Pool<NNPlayer> = Pool::load_checkpoint(&dir.join("gen_checkpoint")).unwrap();
+        let expected = serde_cbor::to_vec(&trained).unwrap();
+        assert_eq!(expected, serde_cbor::to_vec(&loaded).unwrap());
+
+        // Resuming picks the checkpoint up as-is. All generations are done, so nothing is played.
+        let mut resumed: Pool<NNPlayer> = Pool::new(props.clone());
+        resumed.start().unwrap();
+        assert_eq!(expected, serde_cbor::to_vec(&resumed).unwrap());
+
+        // Properties given on resuming replace the checkpoint's, so a run can be extended
+        props.generations = 3;
+        let mut extended: Pool<NNPlayer> = Pool::new(props.clone());
+        extended.start().unwrap();
+        assert_eq!(3, extended.generation);
+        assert_eq!(3, extended.properties.generations);
+
+        // A checkpoint older than the latest saved generation is ignored
+        Pool::<NNPlayer>::new(props.clone())
+            .save_checkpoint()
+            .unwrap();
+        let mut newer: Pool<NNPlayer> = Pool::new(props);
+        newer.start().unwrap();
+        assert_eq!(3, newer.generation);
+        assert_eq!(Some(2), newer.resumed_at);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn eval_opponent_from() {
+        let dir = test_dir("eval_opponent_from");
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool: Pool<NNPlayer> = Pool::new(test_props(dir.join("gen")));
+
+        let compatible = dir.join("compatible");
+        let opponent = Agent::new(NNPlayer::new_from_param(
+            vec![42, 16, 16, 7],
+            vec![
+                nn::Activation::Sigmoid,
+                nn::Activation::Sigmoid,
+                nn::Activation::Sigmoid,
+            ],
+        ));
+        serde_cbor::to_writer(File::create(&compatible).unwrap(), &vec![opponent]).unwrap();
+        assert!(pool.load_eval_opponent(&compatible).is_ok());
+
+        let incompatible = dir.join("incompatible");
+        let opponent = Agent::new(NNPlayer::new_from_param(
+            vec![40, 8, 7],
+            vec![nn::Activation::Sigmoid, nn::Activation::Sigmoid],
+        ));
+        serde_cbor::to_writer(File::create(&incompatible).unwrap(), &vec![opponent]).unwrap();
+        assert!(pool.load_eval_opponent(&incompatible).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn preserve_fitness() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.mutation_prob = 0.0;
+        props.preserve_fitness = 2;
+        let saved = |props: &PoolProperties| {
+            let mut saved: Vec<Agent<NNPlayer>> = Pool::<NNPlayer>::new(props.clone()).select();
+            saved[0].fitness = 1000;
+            saved
+        };
+
+        let mut preserved: Pool<NNPlayer> = Pool::new(props.clone());
+        preserved.resume_from_save(7, &mut saved(&props));
+        assert!(preserved.agents.iter().any(|agent| agent.fitness == 1000));
+        preserved.evaluate();
+        let top = preserved.select().remove(0);
+        assert!(top.fitness > 900);
+
+        props.preserve_fitness = 0;
+        let mut reset: Pool<NNPlayer> = Pool::new(props.clone());
+        reset.resume_from_save(7, &mut saved(&props));
+        assert!(reset.agents.iter().all(|agent| agent.fitness == 0));
+        reset.evaluate();
+        assert!(reset.select()[0].fitness < 100);
+
+        // Half of the saved fitness in the second resumed generation, none after that
+        assert_eq!(1.0, preserved.inherited_fitness_share(7));
+        assert_eq!(0.5, preserved.inherited_fitness_share(8));
+        assert_eq!(0.0, preserved.inherited_fitness_share(9));
+        assert_eq!(0.0, preserved.inherited_fitness_share(6));
+    }
+
+    #[test]
+    fn dry_save() {
+        let dir = test_dir("dry_save");
+        let mut props = test_props(dir.join("gen"));
+        let pool: Pool<NNPlayer> = Pool::new(props.clone());
+        let path = pool.dry_save().unwrap();
+        assert_eq!(dir.join("gen_0"), path);
+        assert!(!path.exists());
+
+        props.save_best_only = true;
+        let best_only: Pool<NNPlayer> = Pool::new(props);
+        assert!(best_only.dry_save().is_ok());
+
+        // An existing save is left alone
+        std::fs::write(&path, "previous run").unwrap();
+        assert!(pool.dry_save().is_err());
+        assert_eq!("previous run", std::fs::read_to_string(&path).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn agents_beating() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        let symmetric: Pool<NNPlayer> = Pool::new(props.clone());
+        props.symmetric_eval = false;
+        let single: Pool<NNPlayer> = Pool::new(props);
+
+        // Red always wins, so an even pairing doesn't count as beating the opponent
+        let agents = [Agent::new(FirstColumnPlayer), Agent::new(FirstColumnPlayer)];
+        let opponent = Agent::new(FirstColumnPlayer);
+        assert_eq!(0, symmetric.agents_beating(&agents, &opponent));
+        assert_eq!(2, single.agents_beating(&agents, &opponent));
+        assert_eq!(0, single.agents_beating(&agents[..0], &opponent));
+    }
+
+    #[test]
+    fn stats_log() {
+        let dir = test_dir("stats_log");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 4;
+        props.compare_interval = 2;
+        props.stats_log = true;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        pool.training_loop(0).unwrap();
+
+        let stats = std::fs::read_to_string(dir.join("gen_stats.jsonl")).unwrap();
+        let records = stats
+            .lines()
+            .map(|line| serde_json::from_str::<StatsRecord>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            records
+                .iter()
+                .map(|record| record.generation)
+                .collect::<Vec<_>>()
+        );
+        for record in records.iter() {
+            assert!(record.mean_fitness <= record.top_fitness as N);
+            // Generation 0 is never compared
+            assert_eq!(record.generation == 2, record.random_fitness.is_some());
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keep_last() {
+        let dir = test_dir("keep_last");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 6;
+        props.save_interval = 1;
+        props.keep_last = 2;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        pool.start().unwrap();
+
+        let mut files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|file| file.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(vec!["gen_4", "gen_5", "gen_config.json"], files);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut props = test_props(dir.join("gen"));
+        props.keep_last = 0;
+        assert!(Pool::<NNPlayer>::try_new(props).is_err());
+    }
+
+    #[test]
+    fn encoding_input_size() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.encoding = Encoding::TwoPlane;
+        let pool: Pool<NNPlayer> = Pool::new(props.clone());
+        assert!(pool.check_encoding().is_err());
+
+        props.structure = vec![84, 8, 7];
+        let pool: Pool<NNPlayer> = Pool::new(props);
+        assert!(pool.check_encoding().is_ok());
+        let (fitness, _, _) = pool.get_fitness(&pool.agents[0], &pool.agents[1]);
+        assert!(fitness.abs() <= 2);
+    }
+
+    #[test]
+    fn evaluate_assigns_fitness() {
+        let mut pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let evaluation = pool.evaluate();
+        // Every pair plays twice, and self-play is zero sum when draws aren't penalized
+        assert_eq!(8 * 7 * 2, evaluation.tally.games());
+        assert_eq!(
+            0,
+            pool.agents.iter().map(|agent| agent.fitness).sum::<i32>()
+        );
+        assert_eq!(None, evaluation.reference_mean);
+    }
+
+    #[test]
+    fn curriculum_schedule() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.curriculum_generations = 4;
+        props.curriculum_weight = 8;
+        props.curriculum_minimax_from = 6;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        let mut weights = vec![];
+        for generation in 0..8 {
+            pool.generation = generation;
+            weights.push((pool.curriculum_weight(), pool.curriculum_minimax_weight()));
+        }
+        assert_eq!(
+            vec![
+                (8, 0),
+                (6, 0),
+                (4, 0),
+                (2, 0),
+                (0, 0),
+                (0, 0),
+                (0, 8),
+                (0, 8)
+            ],
+            weights
+        );
+
+        // Self-play is zero sum, so what is left comes from the weighted minimax games
+        let evaluation = pool.evaluate();
+        assert_eq!(8 * 7 * 2, evaluation.tally.games());
+        let total = pool.agents.iter().map(|agent| agent.fitness).sum::<i32>();
+        assert_eq!(0, total % 8);
+    }
+
+    #[test]
+    fn select_keeps_fittest() {
+        let mut pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        for (i, agent) in pool.agents.iter_mut().enumerate() {
+            agent.fitness = [3, -1, 7, 0, 5, 2, -4, 1][i];
+        }
+        let survivors = pool.select();
+        assert_eq!(
+            vec![7, 5, 3],
+            survivors
+                .iter()
+                .map(|agent| agent.fitness)
+                .collect::<Vec<_>>()
+        );
+        assert!(pool.agents.is_empty());
+    }
+
+    #[test]
+    fn reproduce_resets_fitness() {
+        let mut pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        pool.evaluate();
+        let survivors = pool.select();
+        pool.reproduce(survivors);
+        assert_eq!(pool.properties.population_size, pool.agents.len());
+        assert!(pool.agents.iter().all(|agent| agent.fitness == 0));
+
+        // Without survivors there is nothing to breed from, and the pool stays empty
+        pool.agents.clear();
+        pool.reproduce(Vec::new());
+        assert!(pool.agents.is_empty());
+    }
+
+    #[test]
+    fn save_best_only() {
+        let dir = test_dir("save_best_only");
+        let mut props = test_props(dir.join("gen"));
+        props.save_best_only = true;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        pool.generation = 5;
+        pool.save_generation(&pool.agents[0..3]).unwrap();
+
+        let file = File::open(dir.join("gen_5")).unwrap();
+        let saved: Vec<Agent<NNPlayer>> = serde_cbor::from_reader(file).unwrap();
+        assert_eq!(1, saved.len());
+
+        let player: NNPlayer = game::load_ai(&dir.join("gen")).unwrap();
+        let (winner, board) = pool.play(&Agent::new(player), &pool.agents[1]);
+        assert!(board.moves() >= 7);
+        assert!(winner != game::Spot::EMPTY || board.moves() == 42);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn population_snapshot() {
+        let dir = test_dir("population_snapshot");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 2;
+        props.snapshot_at = 1;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        // Two identical agents have the same checksum
+        pool.agents[1] = pool.agents[0].clone();
+        pool.generation = 1;
+        pool.evaluate();
+        pool.write_snapshot().unwrap();
+
+        let snapshot = std::fs::read_to_string(dir.join("gen_snapshot_1.csv")).unwrap();
+        let rows = snapshot.lines().collect::<Vec<_>>();
+        assert_eq!("agent,fitness,checksum", rows[0]);
+        assert_eq!(pool.agents.len() + 1, rows.len());
+        let columns = |row: &str| row.split(',').map(String::from).collect::<Vec<_>>();
+        for (i, agent) in pool.agents.iter().enumerate() {
+            let row = columns(rows[i + 1]);
+            assert_eq!(i.to_string(), row[0]);
+            assert_eq!(agent.fitness.to_string(), row[1]);
+        }
+        assert_eq!(columns(rows[1])[2], columns(rows[2])[2]);
+        assert_ne!(columns(rows[1])[2], columns(rows[3])[2]);
+
+        // Only the chosen generation is written by the training loop
+        std::fs::remove_file(dir.join("gen_snapshot_1.csv")).unwrap();
+        pool.training_loop(0).unwrap();
+        assert!(!dir.join("gen_snapshot_0.csv").exists());
+        assert!(dir.join("gen_snapshot_1.csv").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fitness_cache() {
+        let mut fresh: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        // Two identical agents share their cache entries
+        fresh.agents[1] = fresh.agents[0].clone();
+        let mut cached = fresh.clone();
+        cached.properties.fitness_cache = true;
+
+        let expected = fresh.self_play();
+        assert_eq!(expected, cached.self_play());
+        // Of the 8 * 7 pairings, the 12 between a copy and another agent and one of the two
+        // between the copies repeat a pair of checksums
+        assert_eq!(8 * 7 - 13, cached.fitness_cache.len());
+        // Unchanged pairings are taken from the cache instead of being replayed
+        assert_eq!(expected, cached.self_play());
+        let keys = cached.cache_keys();
+        let (first, second) = (keys[2].unwrap(), keys[3].unwrap());
+        let replaced = SelfPlayRecord {
+            fitness: 100,
+            ..Default::default()
+        };
+        let (old, _, _) = cached
+            .fitness_cache
+            .insert((first, second), (replaced, replaced, GameTally::default()))
+            .unwrap();
+        assert_eq!(
+            expected.0[2].fitness + 100 - old.fitness,
+            cached.self_play().0[2].fitness
+        );
+
+        // Changed agents are replayed, and their old entries dropped
+        for pool in [&mut fresh, &mut cached] {
+            pool.agents[2].player = pool.agents[5].player.clone();
+            pool.agents[2]
+                .player
+                .mutate(0.5, 1.0, &mut rand::thread_rng());
+        }
+        // The mutation is random, so copy it over
+        cached.agents[2].player = fresh.agents[2].player.clone();
+        assert_eq!(fresh.self_play(), cached.self_play());
+        assert!(!cached.fitness_cache.contains_key(&(first, second)));
+        assert_eq!(8 * 7 - 13, cached.fitness_cache.len());
+
+        // Results aren't reproducible with random tie breaks
+        cached.properties.tie_break = TieBreak::Random;
+        cached.self_play();
+        assert!(cached.fitness_cache.is_empty());
+    }
+
+    #[test]
+    fn elitism_hits_fitness_cache() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.elitism = 2;
+        props.fitness_cache = true;
+        let mut pool: Pool<NNPlayer> = Pool::try_new(props.clone()).unwrap();
+        pool.evaluate();
+        let survivors = pool.select();
+        let elites = survivors[..2]
+            .iter()
+            .map(|agent| agent.player.clone())
+            .collect::<Vec<_>>();
+        pool.reproduce(survivors);
+        assert_eq!(props.population_size, pool.agents.len());
+        // The fittest survivors come first, unchanged
+        for (elite, agent) in elites.iter().zip(&pool.agents) {
+            assert_eq!(elite, &agent.player);
+        }
+
+        // So the pairing of the two elites is taken from the last generation's results
+        let keys = pool.cache_keys();
+        assert!(pool
+            .fitness_cache
+            .contains_key(&(keys[0].unwrap(), keys[1].unwrap())));
+        let mut fresh = pool.clone();
+        fresh.properties.fitness_cache = false;
+        assert_eq!(fresh.self_play(), pool.self_play());
+
+        props.elitism = props.surviving_amount + 1;
+        assert!(Pool::<NNPlayer>::try_new(props).is_err());
+    }
+
+    #[test]
+    fn sample_boards() {
+        let boards = super::sample_boards(50);
+        assert_eq!(50, boards.len());
+        for positions in boards {
+            let count = |color| positions.iter().flatten().filter(|s| **s == color).count();
+            let (red, yellow) = (count(game::Spot::RED), count(game::Spot::YELLOW));
+            assert!(red == yellow || red == yellow + 1);
+            // Pieces are stacked from the bottom of each column
+            for spots in positions.iter() {
+                let height = spots.iter().filter(|s| **s != game::Spot::EMPTY).count();
+                assert!(spots[..6 - height].iter().all(|s| *s == game::Spot::EMPTY));
+            }
+        }
+    }
+
+    #[test]
+    fn generations() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.generations = 5;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+
+        let reports = pool
+            .generations(0)
+            .take(3)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![0, 1, 2],
+            reports
+                .iter()
+                .map(|report| report.generation)
+                .collect::<Vec<_>>()
+        );
+        for report in reports.iter() {
+            assert_eq!(8, report.fitnesses.len());
+            assert!(report.fitnesses.windows(2).all(|pair| pair[0] >= pair[1]));
+            assert_eq!(8 * 7 * 2, report.evaluation.tally.games());
+        }
+
+        // Stopping early leaves the pool ready to go on, and the iterator ends at `generations`
+        assert_eq!(8, pool.agents.len());
+        assert_eq!(2, pool.generations(3).count());
+        assert_eq!(4, pool.generation);
+    }
+
+    #[test]
+    fn move_histogram() {
+        let props = test_props(path::PathBuf::from("unused"));
+        let boards = sample_games(200);
+
+        // Scoring every column the same always falls back on the tie-break column, unless
+        // it is full
+        let constant = NNPlayer::constant(props.structure.clone(), props.activations.clone());
+        let counts = super::move_histogram(&constant, &boards, TieBreak::First);
+        assert_eq!(200, counts.iter().sum::<usize>());
+        assert!(counts[0] > 150);
+    }
+
+    #[test]
+    fn constant_output_penalty() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        let mut unpenalized: Pool<NNPlayer> = Pool::new(props.clone());
+        let constant = NNPlayer::constant(props.structure.clone(), props.activations.clone());
+        assert!(has_constant_output(&constant, &opening_boards()));
+        assert!(!has_constant_output(
+            &unpenalized.agents[1].player,
+            &opening_boards()
+        ));
+        unpenalized.agents[0].player = constant;
+
+        props.constant_output_penalty = 5;
+        let mut penalized = unpenalized.clone();
+        penalized.properties = props;
+        assert_eq!(0, unpenalized.evaluate().constant_outputs);
+        assert_eq!(1, penalized.evaluate().constant_outputs);
+        assert_eq!(
+            unpenalized.agents[0].fitness - 5,
+            penalized.agents[0].fitness
+        );
+        for (a, b) in unpenalized.agents.iter().zip(&penalized.agents).skip(1) {
+            assert_eq!(a.fitness, b.fitness);
+        }
+    }
+
+    #[test]
+    fn seed_from_best() {
+        let dir = test_dir("seed_from_best");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 0;
+        let best = Agent::new(NNPlayer::constant(
+            props.structure.clone(),
+            props.activations.clone(),
+        ));
+        serde_cbor::to_writer(File::create(dir.join("gen_best")).unwrap(), &vec![best]).unwrap();
+        let mutation_range = props.mutation_range;
+        let mutated_from_zero = |pool: &Pool<NNPlayer>| {
+            pool.agents.iter().all(|agent| {
+                agent
+                    .player
+                    .weight_vector()
+                    .iter()
+                    .all(|weight| weight.abs() <= mutation_range)
+            })
+        };
+
+        // Only used when asked for
+        let mut random: Pool<NNPlayer> = Pool::new(props.clone());
+        random.start().unwrap();
+        assert!(!mutated_from_zero(&random));
+
+        props.seed_from_best = true;
+        let mut seeded: Pool<NNPlayer> = Pool::new(props.clone());
+        seeded.start().unwrap();
+        assert_eq!(props.population_size, seeded.agents.len());
+        assert!(mutated_from_zero(&seeded));
+        assert_eq!(0, seeded.generation);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dump_games() {
+        let dir = test_dir("dump_games");
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 2;
+        props.dump_games = 5;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        pool.training_loop(0).unwrap();
+
+        let mut files = std::fs::read_dir(dir.join("gen_games"))
+            .unwrap()
+            .map(|file| file.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            vec![
+                "game_0.txt",
+                "game_1.txt",
+                "game_2.txt",
+                "game_3.txt",
+                "game_4.txt"
+            ],
+            files
+        );
+        // Agents 0 and 1 play both colors first
+        let replay = std::fs::read_to_string(dir.join("gen_games/game_1.txt")).unwrap();
+        let lines = replay.lines().collect::<Vec<_>>();
+        assert_eq!("red agent 1 (nn(42-8-7))", lines[0]);
+        assert_eq!("yellow agent 0 (nn(42-8-7))", lines[1]);
+        let moves = lines[2].strip_prefix("moves ").unwrap().split(' ');
+        assert!(moves.count() >= 7);
+        assert!(lines[3].starts_with("winner "));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stats_interval() {
+        assert!((0..5).all(|gen| stats_due(1, gen)));
+        let due = (0..12).filter(|gen| stats_due(5, *gen)).collect::<Vec<_>>();
+        assert_eq!(vec![0, 5, 10], due);
+        assert!(!(0..12).any(|gen| stats_due(-1, gen)));
+        assert!(!(0..12).any(|gen| stats_due(0, gen)));
+    }
+
+    #[test]
+    fn fitness_aggregation() {
+        fn record(wins: usize, losses: usize, draws: usize) -> SelfPlayRecord {
+            SelfPlayRecord {
+                fitness: wins as i32 - losses as i32,
+                wins,
+                games: wins + losses + draws,
+            }
+        }
+        // Draws are scored neutrally
+        let records = [
+            record(6, 2, 4),
+            record(5, 0, 1),
+            record(7, 5, 0),
+            record(10, 4, 0),
+        ];
+        let ranking = |aggregation: FitnessAggregation| {
+            let mut order = (0..records.len()).collect::<Vec<_>>();
+            order.sort_by_key(|i| -aggregation.apply(&records[*i]));
+            order
+        };
+        assert_eq!(vec![3, 1, 0, 2], ranking(FitnessAggregation::Sum));
+        assert_eq!(vec![1, 3, 0, 2], ranking(FitnessAggregation::Average));
+        assert_eq!(vec![1, 3, 2, 0], ranking(FitnessAggregation::WinRate));
+        assert_eq!(833, FitnessAggregation::WinRate.apply(&records[1]));
+        assert_eq!(0, FitnessAggregation::Average.apply(&record(0, 0, 0)));
+
+        // Self-play counts the wins of every agent
+        let mut pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let (records, tally) = pool.self_play();
+        let wins = records.iter().map(|record| record.wins).sum::<usize>();
+        assert_eq!(tally.red_wins + tally.yellow_wins, wins);
+        let games = records.iter().map(|record| record.games).sum::<usize>();
+        assert_eq!(2 * tally.games(), games);
+    }
+
+    #[test]
+    fn try_new_checks_sizes() {
+        let props = test_props(path::PathBuf::from("unused"));
+        assert!(Pool::<NNPlayer>::try_new(props.clone()).is_ok());
+
+        let mut surviving = props.clone();
+        surviving.surviving_amount = 9;
+        assert!(Pool::<NNPlayer>::try_new(surviving).is_err());
+        let mut crossover = props.clone();
+        crossover.crossover_size = 9;
+        assert!(Pool::<NNPlayer>::try_new(crossover).is_err());
+
+        // Every agent surviving is fine
+        let mut all = props;
+        all.surviving_amount = all.population_size;
+        assert!(Pool::<NNPlayer>::try_new(all).is_ok());
+    }
+
+    #[test]
+    fn disable_crossover() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.crossover_enabled = false;
+        props.mutation_prob = 0.0;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        let parents = pool.select();
+        let parent_weights = parents
+            .iter()
+            .map(|agent| agent.player.weight_vector())
+            .collect::<Vec<_>>();
+        pool.reproduce(parents);
+
+        // Without mutation, every offspring is an exact copy of a parent
+        assert_eq!(pool.properties.population_size, pool.agents.len());
+        for agent in pool.agents.iter() {
+            assert!(parent_weights.contains(&agent.player.weight_vector()));
+        }
+    }
+
+    #[test]
+    fn self_play_totals() {
+        // Sum up every pairing one at a time, without any parallelism
+        let mut pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let n = pool.agents.len();
+        let mut expected = vec![SelfPlayRecord::default(); n];
+        let mut expected_tally = GameTally::default();
+        for i in 0..n {
+            for j in (0..n).filter(|j| *j != i) {
+                let (first, second, tally) = pool.pairing(&pool.agents[i], &pool.agents[j]);
+                expected[i].merge(&first);
+                expected[j].merge(&second);
+                expected_tally.merge(&tally);
+            }
+        }
+
+        assert_eq!((expected.clone(), expected_tally), pool.self_play());
+        pool.properties.eval_block_size = 3;
+        assert_eq!((expected, expected_tally), pool.self_play());
+    }
+
+    #[test]
+    fn mixed_architectures() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.extra_architectures = vec![(
+            vec![42, 5, 3, 7],
+            vec![
+                nn::Activation::RELU,
+                nn::Activation::ELU,
+                nn::Activation::Sigmoid,
+            ],
+        )];
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        for (i, agent) in pool.agents.iter().enumerate() {
+            let expected: &[usize] = if i % 2 == 0 {
+                &[42, 8, 7]
+            } else {
+                &[42, 5, 3, 7]
+            };
+            assert_eq!(Some(expected), agent.player.structure());
+        }
+        assert!(pool.check_encoding().is_ok());
+
+        // Both architectures play each other through `get_move`
+        let (records, tally) = pool.self_play();
+        assert_eq!(8 * 7 * 2, tally.games());
+        assert!(records.iter().all(|record| record.games == 7 * 2 * 2));
+
+        // Crossover only pairs agents of the same architecture
+        pool.evaluate();
+        let survivors = pool.select();
+        pool.reproduce(survivors);
+        assert_eq!(8, pool.agents.len());
+        assert!(pool.agents.iter().all(|agent| {
+            let structure = agent.player.structure().unwrap();
+            structure == [42, 8, 7] || structure == [42, 5, 3, 7]
+        }));
+
+        pool.properties.extra_architectures[0].0[0] = 84;
+        assert!(pool.check_encoding().is_err());
+    }
+
+    #[test]
+    fn max_opponents() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.max_opponents = 3;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        let (records, tally) = pool.self_play();
+        assert_eq!(8 * 3 * 2, tally.games());
+        assert!(records.iter().all(|record| record.games == 3 * 2));
+
+        // Asymmetric games still have every agent play both colors
+        pool.properties.symmetric_eval = false;
+        let (records, tally) = pool.self_play();
+        assert_eq!(8 * 3 * 2, tally.games());
+        assert!(records.iter().all(|record| record.games == 3 * 2));
+
+        // A cap of the whole population plays the full round-robin
+        pool.properties.symmetric_eval = true;
+        pool.properties.max_opponents = 7;
+        assert_eq!(8 * 7 * 2, pool.self_play().1.games());
+    }
+
+    #[test]
+    fn zero_survivors() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.surviving_amount = 0;
+        props.generations = 1;
+        let error = Pool::<NNPlayer>::try_new(props.clone()).err().unwrap();
+        assert!(error.to_string().contains("at least one agent"));
+
+        // Pools built without the check fail the generation instead of panicking
+        let mut unchecked: Pool<NNPlayer> = Pool::new(props.clone());
+        assert!(unchecked.training_loop(0).is_err());
+        props.population_size = 0;
+        let mut empty: Pool<NNPlayer> = Pool::new(props);
+        assert!(empty.training_loop(0).is_err());
+    }
+
+    #[test]
+    fn best_game() {
+        let pool: Pool<NNPlayer> = Pool::new(test_props(path::PathBuf::from("unused")));
+        let (winner, board) = pool.best_game(&pool.agents[0]);
+
+        // Replaying the recorded moves ends the game the same way
+        let mut replay = game::Board::new();
+        let mut color = game::Spot::RED;
+        let mut replayed_winner = game::Spot::EMPTY;
+        for (i, column) in board.history().iter().enumerate() {
+            let won = replay.play_move(*column, color).unwrap();
+            if let Some(won) = won {
+                assert_eq!(board.history().len(), i + 1);
+                replayed_winner = won;
+            }
+            color = match color {
+                game::Spot::RED => game::Spot::YELLOW,
+                _ => game::Spot::RED,
+            };
+        }
+        assert_eq!(winner, replayed_winner);
+        assert_eq!(board.positions, replay.positions);
+    }
+
+    #[test]
+    fn draw_position_bonus() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        let flat: Pool<NNPlayer> = Pool::new(props.clone());
+        props.draw_position_bonus = 1;
+        let bonus: Pool<NNPlayer> = Pool::new(props);
+
+        let board = |first: game::Spot, second: game::Spot| {
+            let mut board = game::Board::new();
+            for (column, color) in [(0, first), (4, second), (1, first), (4, second), (2, first)] {
+                board.insert_top(column, color);
+            }
+            board
+        };
+        // Three in a row on the bottom for the dominant side, nothing for the other
+        let red_ahead = board(game::Spot::RED, game::Spot::YELLOW);
+        let yellow_ahead = board(game::Spot::YELLOW, game::Spot::RED);
+        let neutral = game::Board::new();
+
+        assert_eq!((1, 0), bonus.game_fitness(game::Spot::EMPTY, &red_ahead));
+        assert_eq!((0, 1), bonus.game_fitness(game::Spot::EMPTY, &yellow_ahead));
+        assert_eq!((0, 0), bonus.game_fitness(game::Spot::EMPTY, &neutral));
+        // Flat 0-0 draws by default, and decisive games are unaffected
+        assert_eq!((0, 0), flat.game_fitness(game::Spot::EMPTY, &red_ahead));
+        assert_eq!((1, -1), bonus.game_fitness(game::Spot::RED, &red_ahead));
+    }
+
+    #[test]
+    fn opponent_weighted() {
+        // Flat fitnesses make agent 1 the strongest and agent 3 the weakest
+        let records = [0, 10, 0, -10]
+            .iter()
+            .map(|fitness| SelfPlayRecord {
+                fitness: *fitness,
+                wins: 0,
+                games: 1,
+            })
+            .collect::<Vec<_>>();
+        let points = [(0, 1, 10), (2, 3, 10), (3, 1, -10), (1, 3, -10)];
+        let weighted = weight_by_opponent(&records, &points, FitnessAggregation::Sum);
+        let fitnesses = weighted.iter().map(|r| r.fitness).collect::<Vec<_>>();
+        // Beating the strong agent gains more than beating the weak one, and losing to it
+        // costs less
+        assert_eq!(vec![15, -15, 5, -5], fitnesses);
+        assert_eq!(records[1].games, weighted[1].games);
+
+        // An even population keeps every game at its flat value
+        let even = vec![SelfPlayRecord::default(); 2];
+        let weighted = weight_by_opponent(&even, &[(0, 1, 1), (1, 0, -1)], FitnessAggregation::Sum);
+        assert_eq!(1, weighted[0].fitness);
+        assert_eq!(-1, weighted[1].fitness);
+    }
+
+    #[test]
+    fn population_schedule() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.population_schedule = vec![(4, 6), (2, 12)];
+        props.generations = 6;
+        let mut pool: Pool<NNPlayer> = Pool::try_new(props.clone()).unwrap();
+        let sizes = pool
+            .generations(0)
+            .map(|report| report.unwrap().fitnesses.len())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![8, 8, 12, 12, 6, 6], sizes);
+
+        // Every size has to hold the survivors
+        props.population_schedule = vec![(2, props.surviving_amount - 1)];
+        assert!(Pool::<NNPlayer>::try_new(props).is_err());
+    }
+
+    #[test]
+    fn compare_games() {
+        fn variance(samples: &[N]) -> N {
+            let mean = samples.iter().sum::<N>() / samples.len() as N;
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<N>() / samples.len() as N
+        }
+
+        let mut props = test_props(path::PathBuf::from("unused"));
+        let mut single: Pool<NNPlayer> = Pool::new(props.clone());
+        let agents = [single.agents[0].clone()];
+        props.compare_games = 16;
+        let mut averaged: Pool<NNPlayer> = Pool::new(props);
+
+        // The same network faces different random opponents every generation
+        let mut singles = vec![];
+        let mut averages = vec![];
+        for generation in 0..40 {
+            single.generation = generation;
+            averaged.generation = generation;
+            singles.push(single.random_comparison(&agents).0);
+            averages.push(averaged.random_comparison(&agents).0);
+        }
+        assert!(variance(&singles) > 0.0);
+        assert!(variance(&averages) < variance(&singles) / 2.0);
+        // and the same ones when a generation is compared again
+        assert_eq!(averages[3], {
+            averaged.generation = 3;
+            averaged.random_comparison(&agents).0
+        });
+
+        let (_, win_rate, average_moves) = averaged.random_comparison(&agents);
+        assert!((0.0..=1.0).contains(&win_rate));
+        assert!((7.0..=42.0).contains(&average_moves));
+    }
+
+    #[test]
+    fn resume_history() {
+        let dir = test_dir("resume_history");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 2;
+        props.save_interval = 1;
+        let mut first: Pool<NNPlayer> = Pool::new(props.clone());
+        first.start().unwrap();
+        let history_path = dir.join("gen_history.log");
+        assert!(!history_path.exists());
+
+        props.mutation_range = 0.5;
+        let mut resumed: Pool<NNPlayer> = Pool::new(props.clone());
+        resumed.start().unwrap();
+        let mut again: Pool<NNPlayer> = Pool::new(props);
+        again.start().unwrap();
+
+        let history = std::fs::read_to_string(history_path).unwrap();
+        assert_eq!(
+            vec![
+                "generation 1: resumed, changed mutation_range: 0.015 -> 0.5",
+                "generation 1: resumed, no changed properties",
+            ],
+            history.lines().collect::<Vec<_>>()
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resume_history_from_checkpoint() {
+        let dir = test_dir("resume_history_from_checkpoint");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 2;
+        props.save_interval = 1;
+        props.checkpoint_full = true;
+        let mut first: Pool<NNPlayer> = Pool::new(props.clone());
+        first.start().unwrap();
+
+        props.mutation_range = 0.5;
+        let mut resumed: Pool<NNPlayer> = Pool::new(props);
+        resumed.start().unwrap();
+
+        let history = std::fs::read_to_string(dir.join("gen_history.log")).unwrap();
+        assert_eq!(
+            vec!["generation 2: resumed, changed mutation_range: 0.015 -> 0.5"],
+            history.lines().collect::<Vec<_>>()
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn antithetic_pairs() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.antithetic = true;
+        props.crossover_size = 2;
+        props.population_size = 9;
+        props.mutation_prob = 0.5;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        let survivors = pool.select();
+        let parents = survivors
+            .iter()
+            .map(|agent| agent.player.weight_vector())
+            .collect::<Vec<_>>();
+        pool.reproduce(survivors);
+        assert_eq!(9, pool.agents.len());
+
+        // After the crossed over agents, each survivor's two copies are mutated in opposite
+        // directions, so they average out to the survivor, and the last copy is alone
+        let weights = pool
+            .agents
+            .iter()
+            .map(|agent| agent.player.weight_vector())
+            .collect::<Vec<_>>();
+        for (i, pair) in weights[2..8].chunks(2).enumerate() {
+            assert_ne!(pair[0], pair[1]);
+            let parent = &parents[i % parents.len()];
+            for ((a, b), p) in pair[0].iter().zip(&pair[1]).zip(parent) {
+                assert!(((a - p) + (b - p)).abs() < 1e-5);
+            }
+        }
+        // The crossed over agents aren't overwritten by a copy of their neighbour
+        assert_ne!(weights[0], weights[1]);
+        assert_ne!(weights[1], weights[2]);
+    }
+
+    #[test]
+    fn reproduce_reuses_dead_agents() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.mutation_prob = 0.0;
+        props.crossover_enabled = false;
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        let outputs = |agents: &[Agent<NNPlayer>]| {
+            agents
+                .iter()
+                .map(|agent| agent.player.get_move(game::Board::new().positions))
+                .collect::<Vec<_>>()
+        };
+
+        for fitnesses in [[3, -1, 7, 0, 5, 2, -4, 1], [0, 4, -2, 9, 1, 6, 3, -5]] {
+            for (agent, fitness) in pool.agents.iter_mut().zip(fitnesses) {
+                agent.fitness = fitness;
+            }
+            let survivors = pool.select();
+            assert_eq!(5, pool.spare.len());
+            let expected = outputs(&survivors);
+            pool.reproduce(survivors);
+
+            // Unmutated copies of the survivors, in order, whichever buffers they landed in
+            assert_eq!(8, pool.agents.len());
+            assert!(pool.spare.is_empty());
+            for (i, output) in outputs(&pool.agents).iter().enumerate() {
+                assert_eq!(&expected[i % expected.len()], output);
+            }
+        }
+    }
+
+    #[test]
+    fn immigrant_fraction() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.mutation_prob = 0.0;
+        props.crossover_enabled = false;
+        props.immigrant_fraction = 0.25;
+        let mut pool: Pool<NNPlayer> = Pool::try_new(props.clone()).unwrap();
+        let output = |agent: &Agent<NNPlayer>| agent.player.get_move(game::Board::new().positions);
+
+        for _ in 0..3 {
+            for (i, agent) in pool.agents.iter_mut().enumerate() {
+                agent.fitness = i as i32;
+            }
+            let survivors = pool.select();
+            let inherited = survivors.iter().map(output).collect::<Vec<_>>();
+            pool.reproduce(survivors);
+
+            // The last quarter are new networks, the rest unmutated copies of the survivors
+            assert_eq!(8, pool.agents.len());
+            let fresh = pool
+                .agents
+                .iter()
+                .map(|agent| !inherited.contains(&output(agent)))
+                .collect::<Vec<_>>();
+            assert_eq!(vec![false; 6], fresh[..6]);
+            assert_eq!(vec![true; 2], fresh[6..]);
+        }
+
+        props.immigrant_fraction = 1.5;
+        assert!(Pool::<NNPlayer>::try_new(props).is_err());
+    }
+
+    #[test]
+    fn frozen_layers() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.mutation_prob = 1.0;
+        props.frozen_layers = vec![0];
+        let mut pool: Pool<NNPlayer> = Pool::try_new(props.clone()).unwrap();
+        // 8 outputs of 42 weights and a bias
+        let first_layer = |agent: &Agent<NNPlayer>| agent.player.weight_vector()[..8 * 43].to_vec();
+
+        let survivors = pool.select();
+        let inherited = survivors.iter().map(first_layer).collect::<Vec<_>>();
+        pool.reproduce(survivors);
+        assert!(pool
+            .agents
+            .iter()
+            .all(|agent| inherited.contains(&first_layer(agent))));
+
+        props.frozen_layers = vec![2];
+        assert!(Pool::<NNPlayer>::try_new(props).is_err());
+    }
+
+    #[test]
+    fn max_runtime() {
+        let dir = std::env::temp_dir().join(format!("fourai_max_runtime_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut props = test_props(dir.join("gen"));
+        props.generations = 100_000;
+        props.save_interval = -1;
+        props.max_runtime = Some(Duration::from_millis(200));
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+
+        let start = Instant::now();
+        let reports = pool
+            .generations(0)
+            .map(|report| report.unwrap())
+            .collect::<Vec<_>>();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(5));
+
+        // Only the generation that ran out of time is saved, despite the save interval
+        let last = reports.last().unwrap();
+        assert!(last.out_of_time);
+        assert!(reports[..reports.len() - 1]
+            .iter()
+            .all(|report| !report.out_of_time));
+        let (gen, _, saved) = helpers::latest_valid_save::<Vec<Agent<NNPlayer>>>(&dir.join("gen"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(last.generation, gen);
+        assert_eq!(3, saved.len());
+
+        std::fs::remove_dir_all(dir).unwrap();
+
+        // A runtime past the end of the clock never runs out
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.generations = 2;
+        props.max_runtime = Some(Duration::from_secs(200_000_000_000_000 * 24 * 60 * 60));
+        let mut pool: Pool<NNPlayer> = Pool::new(props);
+        assert!(pool
+            .generations(0)
+            .all(|report| !report.unwrap().out_of_time));
+    }
+
+    #[test]
+    fn checkpoint_rng() {
+        let dir = test_dir("checkpoint_rng");
+        let mut props = test_props(dir.join("gen"));
+        props.seed = Some(7);
+        props.save_interval = 1;
+        props.checkpoint_full = true;
+        props.generations = 3;
+        let weights = |pool: &Pool<NNPlayer>| {
+            pool.agents
+                .iter()
+                .map(|agent| agent.player.weight_vector())
+                .collect::<Vec<_>>()
+        };
+
+        // The same seed gives the same networks
+        let mut uninterrupted: Pool<NNPlayer> = Pool::new(props.clone());
+        assert_eq!(weights(&uninterrupted), weights(&Pool::new(props.clone())));
+
+        // Stop once generation 1 is checkpointed, then train generation 2 from the checkpoint
+        props.generations = 2;
+        Pool::<NNPlayer>::new(props).training_loop(0).unwrap();
+        let checkpoint = dir.join("gen_checkpoint");
+        let mut resumed: Pool<NNPlayer> = Pool::load_checkpoint(&checkpoint).unwrap();
+        assert_eq!(2, resumed.generation);
+        let mut reseeded = resumed.clone();
+        reseeded.rng = PoolRng::new(Some(7));
+        for pool in [&mut resumed, &mut reseeded] {
+            pool.properties.generations = 3;
+            pool.training_loop(2).unwrap();
+        }
+
+        uninterrupted.training_loop(0).unwrap();
+        assert_eq!(weights(&uninterrupted), weights(&resumed));
+        assert_ne!(weights(&uninterrupted), weights(&reseeded));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn profile_memory() {
+        let mut props = test_props(path::PathBuf::from("unused"));
+        props.profile_memory = true;
+        if !alloc_profile::available() {
+            assert!(Pool::<NNPlayer>::try_new(props).is_err());
+            return;
+        }
+        let mut pool: Pool<NNPlayer> = Pool::try_new(props).unwrap();
+        let memory = pool.generations(0).next().unwrap().unwrap().memory.unwrap();
+        assert!(memory.evaluation.allocations > 0);
+        // Crossover copies at least one network of 8 x 43 weights
+        assert!(memory.breeding.bytes >= 8 * 43 * std::mem::size_of::<N>() as u64);
+        assert_eq!(0, memory.saving.allocations);
     }
 }
@@ -1,4 +1,8 @@
-use super::{nn, Player, N};
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{nn, Evolvable, MovePlayer, N};
 use crate::game;
 
 #[derive(Clone, Debug)]
@@ -10,12 +14,51 @@ impl RandomPlayer {
     }
 }
 
-impl Player for RandomPlayer {
+impl Evolvable for RandomPlayer {
     fn new_from_param(_structure: Vec<usize>, _activations: Vec<nn::Activation>) -> Self {
         Self {}
     }
+}
 
+impl MovePlayer for RandomPlayer {
     fn get_move(&self, _board: [[game::Spot; 6]; 7]) -> [N; 7] {
         [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
     }
+
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+}
+
+/// Scores the columns at random, drawing from an RNG seeded with `seed`. Unlike
+/// `RandomPlayer`, which always prefers the same column, games against it vary, while a
+/// given seed always plays the same games.
+#[derive(Clone, Debug)]
+pub struct SeededRandomPlayer {
+    rng: RefCell<StdRng>,
+}
+
+impl SeededRandomPlayer {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Evolvable for SeededRandomPlayer {
+    fn new_from_param(_structure: Vec<usize>, _activations: Vec<nn::Activation>) -> Self {
+        Self::new(0)
+    }
+}
+
+impl MovePlayer for SeededRandomPlayer {
+    fn get_move(&self, _board: [[game::Spot; 6]; 7]) -> [N; 7] {
+        let mut rng = self.rng.borrow_mut();
+        [(); 7].map(|_| rng.gen())
+    }
+
+    fn name(&self) -> String {
+        "seeded random".to_string()
+    }
 }
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::ops::{AddAssign, Sub};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Whether allocations are being counted, set once by `enable`
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Heap allocations made since the program started counting
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allocated {
+    /// Bytes requested, counting the new size of every reallocation
+    pub bytes: u64,
+    pub allocations: u64,
+}
+
+impl Sub for Allocated {
+    type Output = Allocated;
+
+    fn sub(self, earlier: Allocated) -> Allocated {
+        Allocated {
+            bytes: self.bytes - earlier.bytes,
+            allocations: self.allocations - earlier.allocations,
+        }
+    }
+}
+
+impl AddAssign for Allocated {
+    fn add_assign(&mut self, other: Allocated) {
+        self.bytes += other.bytes;
+        self.allocations += other.allocations;
+    }
+}
+
+impl fmt::Display for Allocated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bytes >= 1_000_000 {
+            write!(f, "{:.1} MB", self.bytes as f64 / 1e6)?;
+        } else {
+            write!(f, "{:.1} kB", self.bytes as f64 / 1e3)?;
+        }
+        write!(f, " in {} allocations", self.allocations)
+    }
+}
+
+/// Whether this build counts allocations at all, which takes the `profile-alloc` feature
+pub fn available() -> bool {
+    cfg!(feature = "profile-alloc")
+}
+
+/// Starts counting allocations. Has no effect unless `available`.
+pub fn enable() {
+    COUNTING.store(true, Ordering::Relaxed);
+}
+
+/// Allocations counted so far
+pub fn allocated() -> Allocated {
+    Allocated {
+        bytes: BYTES.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Runs `f`, adding the allocations made meanwhile to `total`. Allocations of other threads
+/// in that time are counted too.
+pub fn measure<T>(total: &mut Allocated, f: impl FnOnce() -> T) -> T {
+    let start = allocated();
+    let value = f();
+    *total += allocated() - start;
+    value
+}
+
+#[cfg_attr(not(feature = "profile-alloc"), allow(dead_code))]
+fn count(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        BYTES.fetch_add(size as u64, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The system allocator, counting every allocation once `enable` is called. Only installed
+/// with the `profile-alloc` feature, so that normal builds don't pay for the check.
+#[cfg(feature = "profile-alloc")]
+struct CountingAllocator;
+
+#[cfg(feature = "profile-alloc")]
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        count(layout.size());
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        count(layout.size());
+        std::alloc::System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        count(new_size);
+        std::alloc::System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(feature = "profile-alloc")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[cfg(all(test, feature = "profile-alloc"))]
+mod alloc_profile_tests {
+    use super::*;
+
+    #[test]
+    fn counts_allocations() {
+        enable();
+        let before = allocated();
+        let buffer = vec![0u8; 1 << 20];
+        let counted = allocated() - before;
+        drop(buffer);
+        // Other tests may allocate at the same time
+        assert!(counted.bytes >= 1 << 20);
+        assert!(counted.allocations >= 1);
+    }
+}
@@ -0,0 +1,196 @@
+use std::error::Error;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Size of a tar header and of the blocks file contents are padded to
+const BLOCK: usize = 512;
+
+/// Longest path a plain ustar header holds
+const MAX_NAME: usize = 100;
+
+/// Every file under `dir`, as paths relative to it, sorted so that archives of the same
+/// directory are identical
+fn files_under(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Writes `value` as a NUL terminated octal number filling `field`
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}\0", value, width = field.len() - 1);
+    field.copy_from_slice(digits.as_bytes());
+}
+
+fn read_octal(field: &[u8]) -> Result<u64, Box<dyn Error>> {
+    let digits = std::str::from_utf8(field)?.trim_matches(|c| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    Ok(u64::from_str_radix(digits, 8)?)
+}
+
+/// Header of a regular file named `name` holding `size` bytes
+fn header(name: &str, size: u64) -> Result<[u8; BLOCK], Box<dyn Error>> {
+    if name.len() > MAX_NAME {
+        return Err(format!("path too long to archive: {}", name).into());
+    }
+    let mut header = [0; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], 0);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is taken with its own field set to spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum = header.iter().map(|byte| *byte as u64).sum::<u64>();
+    write_octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+    Ok(header)
+}
+
+/// Writes every file under `dir` to a tar archive at `archive`, returning the number of files
+/// packed. The paths in the archive are relative to `dir`.
+pub fn pack(dir: &Path, archive: &Path) -> Result<usize, Box<dyn Error>> {
+    let files = files_under(dir)?;
+    let mut out = fs::File::create(archive)?;
+    for relative in files.iter() {
+        let name = relative
+            .to_str()
+            .ok_or_else(|| format!("path is not valid UTF-8: {}", relative.display()))?
+            .replace('\\', "/");
+        let contents = fs::read(dir.join(relative))?;
+        out.write_all(&header(&name, contents.len() as u64)?)?;
+        out.write_all(&contents)?;
+        let padding = (BLOCK - contents.len() % BLOCK) % BLOCK;
+        out.write_all(&vec![0; padding])?;
+    }
+    // Two empty blocks end the archive
+    out.write_all(&[0; 2 * BLOCK])?;
+    Ok(files.len())
+}
+
+/// `name` from an archive as a path below the folder it is unpacked to, refusing absolute
+/// paths and `..`, which could write anywhere
+fn entry_path(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let path = PathBuf::from(name);
+    if path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(path)
+    } else {
+        Err(format!("refusing to unpack {} outside of the target folder", name).into())
+    }
+}
+
+/// Restores the files of the tar archive at `archive` into `dir`, creating it if needed, and
+/// returns the number of files unpacked
+pub fn unpack(archive: &Path, dir: &Path) -> Result<usize, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    fs::File::open(archive)?.read_to_end(&mut bytes)?;
+    fs::create_dir_all(dir)?;
+
+    let mut unpacked = 0;
+    let mut offset = 0;
+    while offset + BLOCK <= bytes.len() {
+        let header = &bytes[offset..offset + BLOCK];
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+        let name_end = header[..MAX_NAME]
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(MAX_NAME);
+        let name = std::str::from_utf8(&header[..name_end])?;
+        let size = read_octal(&header[124..136])? as usize;
+        let start = offset + BLOCK;
+        if start + size > bytes.len() {
+            return Err(format!("archive ends in the middle of {}", name).into());
+        }
+
+        let path = dir.join(entry_path(name)?);
+        match header[156] {
+            b'0' | 0 => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, &bytes[start..start + size])?;
+                unpacked += 1;
+            }
+            b'5' => fs::create_dir_all(&path)?,
+            kind => {
+                return Err(
+                    format!("unsupported entry type {:?} for {}", kind as char, name).into(),
+                )
+            }
+        }
+        offset = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+    Ok(unpacked)
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fourai_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn pack_unpack_round_trip() {
+        let dir = test_dir("archive_round_trip");
+        let run = dir.join("run");
+        fs::create_dir_all(run.join("gen_games")).unwrap();
+        fs::write(run.join("gen_config.json"), "{\"generations\": 10}").unwrap();
+        fs::write(run.join("gen_10"), vec![7; 1500]).unwrap();
+        fs::write(run.join("empty"), "").unwrap();
+        fs::write(run.join("gen_games").join("game_0.txt"), "1 2 1 2").unwrap();
+
+        let archive = dir.join("run.tar");
+        assert_eq!(4, pack(&run, &archive).unwrap());
+        assert_eq!(0, fs::metadata(&archive).unwrap().len() as usize % BLOCK);
+
+        let restored = dir.join("restored");
+        assert_eq!(4, unpack(&archive, &restored).unwrap());
+        let files = files_under(&run).unwrap();
+        assert_eq!(files, files_under(&restored).unwrap());
+        for file in files {
+            assert_eq!(
+                fs::read(run.join(&file)).unwrap(),
+                fs::read(restored.join(&file)).unwrap()
+            );
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unpack_refuses_escaping_paths() {
+        assert!(entry_path("gen_games/game_0.txt").is_ok());
+        assert!(entry_path("../outside").is_err());
+        assert!(entry_path("/etc/outside").is_err());
+        assert!(header(&"x".repeat(MAX_NAME + 1), 0).is_err());
+    }
+}
@@ -1,18 +1,24 @@
-use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path;
-use std::process;
 
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::ai::agent::{Agent, Player};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::ai::agent::{
+    choose_move, choose_move_with, prefers_full_column, Agent, IllegalMove, MovePlayer, Player,
+    TieBreak,
+};
+use crate::ai::encoder::{self, Encoding};
 use crate::ai::N;
 use crate::helpers;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Spot {
     EMPTY,
     RED,
@@ -40,6 +46,15 @@ impl fmt::Display for Spot {
 }
 
 impl Spot {
+    /// Like the `Display` output, but shaded differently, to mark the last piece played
+    fn display_marked(&self) -> String {
+        match self {
+            Spot::EMPTY => self.to_string(),
+            Spot::RED => concat!(RED!(), "▓▓", RESET!()).to_string(),
+            Spot::YELLOW => concat!(YELLOW!(), "▓▓", RESET!()).to_string(),
+        }
+    }
+
     fn display(&self) -> &'static str {
         match self {
             Spot::RED => concat!(BOLD!(), RED!(), "RED", RESET!()),
@@ -55,13 +70,182 @@ impl Spot {
             Spot::EMPTY => 0.0,
         }
     }
+
+    /// Single character representation used by the text formats: `.`, `R` or `Y`
+    pub fn as_char(&self) -> char {
+        match self {
+            Spot::EMPTY => '.',
+            Spot::RED => 'R',
+            Spot::YELLOW => 'Y',
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidSpotChar(pub char);
+
+impl fmt::Display for InvalidSpotChar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid spot character: {:?}", self.0)
+    }
+}
+
+impl Error for InvalidSpotChar {}
+
+impl TryFrom<char> for Spot {
+    type Error = InvalidSpotChar;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '.' => Ok(Spot::EMPTY),
+            'R' => Ok(Spot::RED),
+            'Y' => Ok(Spot::YELLOW),
+            _ => Err(InvalidSpotChar(c)),
+        }
+    }
+}
+
+/// Why a grid passed to `Board::from_position` can't be a board
+#[derive(Debug, PartialEq)]
+pub enum InvalidPosition {
+    /// The grid doesn't have exactly 7 columns
+    Columns(usize),
+    /// A column has more than 6 spots
+    ColumnHeight { column: usize, height: usize },
+    /// A piece has an empty spot below it, counting rows from the bottom
+    FloatingPiece { column: usize, row: usize },
+    /// An encoded spot passed to `Board::from_bytes` isn't empty, red or yellow, counting rows
+    /// from the bottom
+    InvalidSpotCode { column: usize, row: usize },
+}
+
+impl fmt::Display for InvalidPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPosition::Columns(columns) => {
+                write!(f, "expected 7 columns, found {}", columns)
+            }
+            InvalidPosition::ColumnHeight { column, height } => write!(
+                f,
+                "column {} has {} spots, at most 6 fit",
+                column + 1,
+                height
+            ),
+            InvalidPosition::FloatingPiece { column, row } => write!(
+                f,
+                "piece in column {}, row {} from the bottom, has nothing below it",
+                column + 1,
+                row + 1
+            ),
+            InvalidPosition::InvalidSpotCode { column, row } => write!(
+                f,
+                "spot in column {}, row {} from the bottom, has an invalid code",
+                column + 1,
+                row + 1
+            ),
+        }
+    }
+}
+
+impl Error for InvalidPosition {}
+
+/// Why `Board::play_move` couldn't play a move
+#[derive(Debug, PartialEq)]
+pub enum MoveError {
+    /// The column has no empty spot left
+    ColumnFull,
+    /// There is no column with that index
+    OutOfRange,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::ColumnFull => write!(f, "that column is full"),
+            MoveError::OutOfRange => write!(f, "there are only 7 columns"),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+/// Why `Board::check_invariants` rejected a board, counting rows from the bottom
+#[derive(Debug, PartialEq)]
+pub enum BoardInvariantError {
+    /// A piece has an empty spot below it
+    FloatingPiece { column: usize, row: usize },
+    /// The next empty spot recorded for a column isn't the one above its top piece
+    HighestPiece {
+        column: usize,
+        expected: isize,
+        found: isize,
+    },
+    /// The move count doesn't match the number of pieces on the board
+    MoveCount { pieces: usize, moves: usize },
+}
+
+impl fmt::Display for BoardInvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardInvariantError::FloatingPiece { column, row } => write!(
+                f,
+                "piece in column {}, row {} from the bottom, has nothing below it",
+                column + 1,
+                row + 1
+            ),
+            BoardInvariantError::HighestPiece {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column {} records row {} as its next empty spot instead of row {}",
+                column + 1,
+                found,
+                expected
+            ),
+            BoardInvariantError::MoveCount { pieces, moves } => {
+                write!(f, "board has {} pieces but counts {} moves", pieces, moves)
+            }
+        }
+    }
+}
+
+impl Error for BoardInvariantError {}
+
+impl TryFrom<Vec<Vec<Spot>>> for Board {
+    type Error = InvalidPosition;
+
+    /// Same as `Board::from_position`
+    fn try_from(grid: Vec<Vec<Spot>>) -> Result<Self, Self::Error> {
+        Board::from_position(grid)
+    }
+}
+
+impl TryFrom<&[u8; 11]> for Board {
+    type Error = InvalidPosition;
+
+    /// Same as `Board::from_bytes`
+    fn try_from(bytes: &[u8; 11]) -> Result<Self, Self::Error> {
+        Board::from_bytes(bytes)
+    }
 }
 
+impl From<&Board> for [u8; 11] {
+    /// Same as `Board::to_bytes`
+    fn from(board: &Board) -> Self {
+        board.to_bytes()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Board {
     pub positions: [[Spot; 6]; 7],
     highest_pieces: [isize; 7],
     dimensions: (usize, usize),
     moves: usize,
+    /// Columns played so far, in order
+    history: Vec<usize>,
 }
 
 impl fmt::Display for Board {
@@ -86,9 +270,14 @@ impl fmt::Display for Board {
                 .collect::<String>()
         )?;
 
-        for col in &self.positions {
+        let last_move = self.last_move();
+        for (column, col) in self.positions.iter().enumerate() {
             for (i, value) in col.iter().enumerate() {
-                rows[i] += &value.to_string()[..];
+                if last_move == Some((column, i)) {
+                    rows[i] += &value.display_marked()[..];
+                } else {
+                    rows[i] += &value.to_string()[..];
+                }
                 rows[i] += " ┃ ";
             }
         }
@@ -108,6 +297,51 @@ impl fmt::Display for Board {
     }
 }
 
+/// How boards are drawn in the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum BoardStyle {
+    /// Colored blocks in a box-drawing frame, the `Display` output
+    #[default]
+    Unicode,
+    /// Plain `R`, `Y` and `.` in a `+-|` frame, for terminals without color or box drawing
+    Ascii,
+    /// A colored circle emoji per spot, which also shows up in chat and markdown
+    Emoji,
+}
+
+impl BoardStyle {
+    pub fn from_string(s: &str) -> BoardStyle {
+        match s {
+            "unicode" => BoardStyle::Unicode,
+            "ascii" => BoardStyle::Ascii,
+            "emoji" => BoardStyle::Emoji,
+            _ => panic!("invalid board style: {}", s),
+        }
+    }
+}
+
+/// Where the interactive games show the board: in the terminal in `style`, and as an SVG
+/// image at `svg_path`, kept up to date after every move, if given
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BoardView<'a> {
+    pub style: BoardStyle,
+    pub svg_path: Option<&'a path::Path>,
+}
+
+impl fmt::Debug for Board {
+    /// Compact text form, one row per line from the top, e.g. `...R...`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.dimensions.0 {
+            let line = (0..self.dimensions.1)
+                .map(|column| self.positions[column][row].as_char())
+                .collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Board {
     pub fn new() -> Self {
         let rows = [Spot::EMPTY; 6];
@@ -120,13 +354,417 @@ impl Board {
             highest_pieces,
             dimensions,
             moves: 0,
+            history: Vec::new(),
         }
     }
 
+    /// Builds a board from a grid of 7 columns, left to right, each listing its spots from
+    /// the bottom up. Columns may be shorter than 6, the rest of the column is empty. The
+    /// order the pieces were played in isn't known, so the board starts without any history
+    /// (`last_move` is `None` and `undo_move` does nothing).
+    pub fn from_position(grid: Vec<Vec<Spot>>) -> Result<Self, InvalidPosition> {
+        let mut board = Board::new();
+        if grid.len() != board.dimensions.1 {
+            return Err(InvalidPosition::Columns(grid.len()));
+        }
+
+        for (column, spots) in grid.iter().enumerate() {
+            if spots.len() > board.dimensions.0 {
+                return Err(InvalidPosition::ColumnHeight {
+                    column,
+                    height: spots.len(),
+                });
+            }
+            let height = spots
+                .iter()
+                .take_while(|spot| **spot != Spot::EMPTY)
+                .count();
+            if let Some(row) = spots[height..].iter().position(|spot| *spot != Spot::EMPTY) {
+                return Err(InvalidPosition::FloatingPiece {
+                    column,
+                    row: height + row,
+                });
+            }
+
+            for (row, spot) in spots[..height].iter().enumerate() {
+                board.change_position(column, board.dimensions.0 - 1 - row, *spot);
+            }
+            board.highest_pieces[column] = (board.dimensions.0 - height) as isize - 1;
+            board.moves += height;
+        }
+
+        debug_assert_eq!(Ok(()), board.check_invariants());
+        Ok(board)
+    }
+
+    /// Builds a board from its compact text form, as written by `Debug`: one line of 7
+    /// characters (`.`, `R` or `Y`) per row, from the top row down. Leading and trailing
+    /// whitespace of each line is ignored.
+    pub fn from_grid(text: &str) -> Result<Self, Box<dyn Error>> {
+        let (rows, columns) = Board::new().dimensions;
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        if lines.len() != rows {
+            return Err(format!("expected {} rows, got {}", rows, lines.len()).into());
+        }
+
+        let mut grid = vec![Vec::with_capacity(rows); columns];
+        // From the bottom up, which is the order `from_position` takes
+        for line in lines.iter().rev() {
+            let spots = line
+                .chars()
+                .map(Spot::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            if spots.len() != columns {
+                return Err(format!("expected {} spots in row {:?}", columns, line).into());
+            }
+            for (column, spot) in spots.into_iter().enumerate() {
+                grid[column].push(spot);
+            }
+        }
+        Ok(Board::from_position(grid)?)
+    }
+
+    /// Checks that the bookkeeping of the board agrees with its pieces: no piece floats above
+    /// an empty spot, every column's next empty spot is right above its top piece, and the
+    /// move count equals the number of pieces
+    pub fn check_invariants(&self) -> Result<(), BoardInvariantError> {
+        let rows = self.dimensions.0;
+        let mut pieces = 0;
+        for (column, spots) in self.positions.iter().enumerate() {
+            // `positions` lists every column from the top
+            let height = spots
+                .iter()
+                .rev()
+                .take_while(|spot| **spot != Spot::EMPTY)
+                .count();
+            if let Some(row) = spots[..rows - height]
+                .iter()
+                .rposition(|spot| *spot != Spot::EMPTY)
+            {
+                return Err(BoardInvariantError::FloatingPiece {
+                    column,
+                    row: rows - 1 - row,
+                });
+            }
+
+            let expected = (rows - height) as isize - 1;
+            if self.highest_pieces[column] != expected {
+                return Err(BoardInvariantError::HighestPiece {
+                    column,
+                    expected,
+                    found: self.highest_pieces[column],
+                });
+            }
+            pieces += height;
+        }
+
+        if pieces != self.moves {
+            return Err(BoardInvariantError::MoveCount {
+                pieces,
+                moves: self.moves,
+            });
+        }
+        Ok(())
+    }
+
+    /// Compact encoding of the pieces on the board, 2 bits per spot (`0` empty, `1` red, `2`
+    /// yellow), in the order of `positions`: column by column, each from the top
+    pub fn to_bytes(&self) -> [u8; 11] {
+        let mut bytes = [0; 11];
+        for (i, spot) in self.positions.iter().flatten().enumerate() {
+            let code = match spot {
+                Spot::EMPTY => 0,
+                Spot::RED => 1,
+                Spot::YELLOW => 2,
+            };
+            bytes[i / 4] |= code << (i % 4 * 2);
+        }
+        bytes
+    }
+
+    /// Reverses `to_bytes`. Like `from_position`, the board starts without any history.
+    pub fn from_bytes(bytes: &[u8; 11]) -> Result<Self, InvalidPosition> {
+        let rows = Board::new().dimensions.0;
+        let mut grid = Vec::with_capacity(7);
+        for column in 0..7 {
+            let mut spots = Vec::with_capacity(rows);
+            for row in 0..rows {
+                // Bottom up, the order `from_position` takes
+                let i = column * rows + rows - 1 - row;
+                spots.push(match bytes[i / 4] >> (i % 4 * 2) & 0b11 {
+                    0 => Spot::EMPTY,
+                    1 => Spot::RED,
+                    2 => Spot::YELLOW,
+                    _ => return Err(InvalidPosition::InvalidSpotCode { column, row }),
+                });
+            }
+            grid.push(spots);
+        }
+        Board::from_position(grid)
+    }
+
     pub fn moves(&self) -> usize {
         self.moves
     }
 
+    /// The board drawn in `style`, with column numbers above it. The unicode and ascii
+    /// styles mark the last piece played.
+    pub fn render(&self, style: BoardStyle) -> String {
+        let last_move = self.last_move();
+        match style {
+            BoardStyle::Unicode => self.to_string(),
+            BoardStyle::Ascii => {
+                let border = format!("{}+\n", "+---".repeat(self.positions.len()));
+                let mut out = (1..=self.positions.len())
+                    .map(|column| format!("  {} ", column))
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string();
+                out += "\n";
+                out += &border;
+                for row in 0..self.dimensions.0 {
+                    for (column, spots) in self.positions.iter().enumerate() {
+                        let spot = spots[row].as_char();
+                        let spot = if last_move == Some((column, row)) {
+                            spot.to_ascii_lowercase()
+                        } else {
+                            spot
+                        };
+                        out += &format!("| {} ", spot);
+                    }
+                    out += "|\n";
+                }
+                out + &border
+            }
+            BoardStyle::Emoji => {
+                // Every emoji is two terminal columns wide, like a digit and a space
+                let mut out = (1..=self.positions.len())
+                    .map(|column| column.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                out += "\n";
+                for row in 0..self.dimensions.0 {
+                    for spots in self.positions.iter() {
+                        out += match spots[row] {
+                            Spot::EMPTY => "⚪",
+                            Spot::RED => "🔴",
+                            Spot::YELLOW => "🟡",
+                        };
+                    }
+                    out += "\n";
+                }
+                out
+            }
+        }
+    }
+
+    /// Number of pieces that can still be played before the board is full
+    pub fn remaining_moves(&self) -> usize {
+        self.dimensions.0 * self.dimensions.1 - self.moves
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.remaining_moves() == 0
+    }
+
+    /// Number of `color` pieces on the board
+    pub fn piece_count(&self, color: Spot) -> usize {
+        self.positions
+            .iter()
+            .flatten()
+            .filter(|spot| **spot == color)
+            .count()
+    }
+
+    /// Whose turn it is, by the pieces on the board, as `encoder::side_to_move` tells it
+    pub fn to_move(&self) -> Spot {
+        encoder::side_to_move(&self.positions)
+    }
+
+    /// Renders the board as a standalone SVG image: a blue board with a red, yellow or empty
+    /// (white) circle for every spot
+    pub fn to_svg(&self) -> String {
+        const CELL: usize = 100;
+        const RADIUS: usize = 40;
+        let (rows, columns) = self.dimensions;
+        let (width, height) = (columns * CELL, rows * CELL);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            width, height
+        );
+        svg += &format!(
+            "  <rect width=\"{}\" height=\"{}\" rx=\"{}\" fill=\"#1e4fc2\"/>\n",
+            width,
+            height,
+            CELL / 5
+        );
+        for column in 0..columns {
+            for row in 0..rows {
+                let fill = match self.positions[column][row] {
+                    Spot::EMPTY => "white",
+                    Spot::RED => "#d6201f",
+                    Spot::YELLOW => "#f5c518",
+                };
+                svg += &format!(
+                    "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n",
+                    column * CELL + CELL / 2,
+                    row * CELL + CELL / 2,
+                    RADIUS,
+                    fill
+                );
+            }
+        }
+        svg += "</svg>\n";
+        svg
+    }
+
+    /// Whether a piece can still be played in `column`
+    pub fn is_legal(&self, column: usize) -> bool {
+        self.highest_pieces[column] != -1
+    }
+
+    /// Heuristic score of the position for red: the number of lines of four spots in which
+    /// red has three pieces, minus the number in which yellow has three. Counts both open
+    /// and blocked lines, so it also tells apart full boards by who came closer to winning.
+    pub fn evaluate(&self) -> i32 {
+        let mut score = 0;
+        for line in lines() {
+            let (mut red, mut yellow) = (0, 0);
+            for (column, row) in line {
+                match self.positions[column][row] {
+                    Spot::RED => red += 1,
+                    Spot::YELLOW => yellow += 1,
+                    Spot::EMPTY => {}
+                }
+            }
+            if red == 3 {
+                score += 1;
+            } else if yellow == 3 {
+                score -= 1;
+            }
+        }
+        score
+    }
+
+    /// Empty spots, as `(column, row)`, that would complete a line of four for `color`,
+    /// whether or not a piece can be dropped there yet
+    fn threats(&self, color: Spot) -> Vec<(usize, usize)> {
+        let mut threats = lines()
+            .filter_map(|line| {
+                let mut empty = line
+                    .iter()
+                    .filter(|(column, row)| self.positions[*column][*row] != color);
+                match (empty.next(), empty.next()) {
+                    (Some(&(column, row)), None) if self.positions[column][row] == Spot::EMPTY => {
+                        Some((column, row))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        threats.sort_unstable();
+        threats.dedup();
+        threats
+    }
+
+    /// Number of new threats, empty spots that would complete a line of four for `color`,
+    /// that dropping a `color` piece in `column` creates. Two or more make a fork that the
+    /// opponent can't block with one move. `0` for full or nonexistent columns.
+    pub fn threats_created_by_move(&self, column: usize, color: Spot) -> usize {
+        if column >= self.dimensions.1 || !self.is_legal(column) {
+            return 0;
+        }
+        let before = self.threats(color);
+        let mut board = self.clone();
+        board.insert_top(column, color);
+        board
+            .threats(color)
+            .iter()
+            .filter(|threat| !before.contains(threat))
+            .count()
+    }
+
+    /// Columns in which `color` would win immediately
+    pub fn winning_moves(&self, color: Spot) -> Vec<usize> {
+        (0..self.dimensions.1)
+            .filter(|column| {
+                let mut board = self.clone();
+                board.insert_top(*column, color) == (true, Some(color))
+            })
+            .collect()
+    }
+
+    /// Whether dropping a `color` piece in `column` leaves `color` with two or more columns
+    /// to win in immediately, which the opponent can't both block. A move that wins right
+    /// away, or can't be played, doesn't count as a fork.
+    pub fn creates_fork(&self, column: usize, color: Spot) -> bool {
+        if column >= self.dimensions.1 || !self.is_legal(column) {
+            return false;
+        }
+        let mut board = self.clone();
+        match board.insert_top(column, color) {
+            (true, None) => board.winning_moves(color).len() >= 2,
+            _ => false,
+        }
+    }
+
+    /// Columns played on this board so far, in order
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    /// Column and row of the most recently played piece, if any move has been played
+    pub fn last_move(&self) -> Option<(usize, usize)> {
+        let column = *self.history.last()?;
+        Some((column, (self.highest_pieces[column] + 1) as usize))
+    }
+
+    /// Takes back the most recent move, returning the column it was played in
+    pub fn undo_move(&mut self) -> Option<usize> {
+        let column = self.history.pop()?;
+        self.highest_pieces[column] += 1;
+        self.change_position(column, self.highest_pieces[column] as usize, Spot::EMPTY);
+        self.moves -= 1;
+        Some(column)
+    }
+
+    /// A board reached by playing `moves` random legal moves from an empty board, starting
+    /// with red. Moves that would win or fill the board are skipped in favour of another
+    /// column, so the game is never over; when every legal move would end it, fewer than
+    /// `moves` are played.
+    pub fn random_position(moves: usize, rng: &mut impl Rng) -> Board {
+        let mut board = Board::new();
+        let mut color = Spot::RED;
+        for _ in 0..moves {
+            let mut columns = (0..board.dimensions.1)
+                .filter(|column| board.is_legal(*column))
+                .collect::<Vec<_>>();
+            columns.shuffle(rng);
+            let next = columns.into_iter().find_map(|column| {
+                let mut next = board.clone();
+                match next.play_move(column, color) {
+                    Ok(None) if !next.is_full() => Some(next),
+                    _ => None,
+                }
+            });
+            match next {
+                Some(next) => board = next,
+                None => break,
+            }
+            color = match color {
+                Spot::RED => Spot::YELLOW,
+                _ => Spot::RED,
+            };
+        }
+        debug_assert_eq!(Ok(()), board.check_invariants());
+        board
+    }
+
     fn change_position(&mut self, x: usize, y: usize, spot: Spot) {
         self.positions[x][y] = spot;
     }
@@ -241,33 +879,374 @@ impl Board {
         None
     }
 
-    pub fn insert_top(&mut self, column: usize, spot: Spot) -> (bool, Option<Spot>) {
+    /// Drops `spot` into `column`, returning the winner if the move completed four in a row.
+    /// A move that fills the board without a win returns `Ok(None)`, check `is_full` for the
+    /// draw.
+    pub fn play_move(&mut self, column: usize, spot: Spot) -> Result<Option<Spot>, MoveError> {
+        if column >= self.dimensions.1 {
+            return Err(MoveError::OutOfRange);
+        }
         let highest = self.highest_pieces[column];
-        if highest != -1 {
-            self.change_position(column, highest as usize, spot);
-            self.highest_pieces[column] -= 1;
-            self.moves += 1;
-            (true, self.check_win(column, highest as usize))
-        } else if self.moves >= self.dimensions.0 * self.dimensions.1 {
-            (true, Some(Spot::EMPTY))
+        if highest == -1 {
+            return Err(MoveError::ColumnFull);
+        }
+        self.change_position(column, highest as usize, spot);
+        self.highest_pieces[column] -= 1;
+        self.moves += 1;
+        self.history.push(column);
+        Ok(self.check_win(column, highest as usize))
+    }
+
+    /// Like `play_move`, as a tuple of whether the move was played and the winner. Playing
+    /// on a full board reports a draw (`Spot::EMPTY`) instead of an illegal move.
+    pub fn insert_top(&mut self, column: usize, spot: Spot) -> (bool, Option<Spot>) {
+        match self.play_move(column, spot) {
+            Ok(winner) => (true, winner),
+            Err(_) if self.is_full() => (true, Some(Spot::EMPTY)),
+            Err(_) => (false, None),
+        }
+    }
+
+    /// A new board for `variant`. Only the standard rules are playable so far, since the
+    /// board is stored as a fixed 7x6 grid.
+    pub fn for_variant(variant: Variant) -> Result<Self, UnsupportedVariant> {
+        let board = Board::new();
+        if board.dimensions == variant.dimensions() && variant.win_length() == 4 {
+            Ok(board)
+        } else {
+            Err(UnsupportedVariant(variant))
+        }
+    }
+}
+
+/// Every line of four spots on the board, as `(column, row)` pairs
+fn lines() -> impl Iterator<Item = [(usize, usize); 4]> {
+    const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+    (0..7isize).flat_map(|column| {
+        (0..6isize).flat_map(move |row| {
+            DIRECTIONS.iter().filter_map(move |(dc, dr)| {
+                let end = (column + 3 * dc, row + 3 * dr);
+                if !(0..7).contains(&end.0) || !(0..6).contains(&end.1) {
+                    return None;
+                }
+                Some([0, 1, 2, 3].map(|k| ((column + k * dc) as usize, (row + k * dr) as usize)))
+            })
+        })
+    })
+}
+
+/// Board presets, selected with `--variant`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    /// 7 columns, 6 rows
+    Standard,
+    /// 6 columns, 5 rows
+    Small,
+    /// 8 columns, 7 rows
+    Large,
+}
+
+impl Variant {
+    pub fn from_string(s: &str) -> Variant {
+        match s {
+            "standard" => Variant::Standard,
+            "small" => Variant::Small,
+            "large" => Variant::Large,
+            _ => panic!("invalid variant: {}", s),
+        }
+    }
+
+    /// Rows and columns of the board, in the order `Board` stores them
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self {
+            Variant::Standard => (6, 7),
+            Variant::Small => (5, 6),
+            Variant::Large => (7, 8),
+        }
+    }
+
+    /// Number of pieces in a row needed to win
+    pub fn win_length(&self) -> usize {
+        4
+    }
+
+    /// Sizes the first and last layer of a network need for this board: one input per spot
+    /// (or two, depending on `encoding`) and one output per column
+    pub fn structure_endpoints(&self, encoding: Encoding) -> (usize, usize) {
+        let (rows, columns) = self.dimensions();
+        (rows * columns * encoding.inputs_per_spot(), columns)
+    }
+
+    /// Whether `structure` begins and ends with the sizes this board needs
+    pub fn matches_structure(&self, structure: &[usize], encoding: Encoding) -> bool {
+        let (inputs, outputs) = self.structure_endpoints(encoding);
+        structure.first() == Some(&inputs) && structure.last() == Some(&outputs)
+    }
+
+    /// `structure` with its first and last layer replaced by the sizes this board needs,
+    /// keeping the hidden layers
+    pub fn fit_structure(&self, structure: &[usize], encoding: Encoding) -> Vec<usize> {
+        let (inputs, outputs) = self.structure_endpoints(encoding);
+        let hidden = structure
+            .get(1..structure.len().saturating_sub(1))
+            .unwrap_or(&[]);
+        let mut fitted = vec![inputs];
+        fitted.extend_from_slice(hidden);
+        fitted.push(outputs);
+        fitted
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnsupportedVariant(pub Variant);
+
+impl fmt::Display for UnsupportedVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (rows, columns) = self.0.dimensions();
+        write!(
+            f,
+            "the {:?} variant ({}x{}) isn't supported yet, only the standard 7x6 board is",
+            self.0, columns, rows
+        )
+    }
+}
+
+impl Error for UnsupportedVariant {}
+
+/// Plays a game starting from an existing `board`, with `current_color` to move.
+/// `player1` always plays red and `player2` always plays yellow. Returns the winner
+/// (`Spot::EMPTY` for a draw) and the number of moves on the final board.
+pub fn play_from(
+    board: Board,
+    current_color: Spot,
+    player1: &dyn MovePlayer,
+    player2: &dyn MovePlayer,
+    tie_break: TieBreak,
+    illegal_move: IllegalMove,
+) -> (Spot, usize) {
+    let (winner, board) = play_game(
+        board,
+        current_color,
+        player1,
+        player2,
+        tie_break,
+        illegal_move,
+    );
+    (winner, board.moves())
+}
+
+/// Same as `play_from`, but returns the final board, whose `history` holds the moves played
+pub fn play_game(
+    board: Board,
+    current_color: Spot,
+    player1: &dyn MovePlayer,
+    player2: &dyn MovePlayer,
+    tie_break: TieBreak,
+    illegal_move: IllegalMove,
+) -> (Spot, Board) {
+    play_game_with(
+        board,
+        current_color,
+        player1,
+        player2,
+        tie_break,
+        illegal_move,
+        |_, _| {},
+    )
+}
+
+/// Same as `play_game`, calling `on_move` with the board and the column after every move
+pub(crate) fn play_game_with(
+    mut board: Board,
+    mut current_color: Spot,
+    player1: &dyn MovePlayer,
+    player2: &dyn MovePlayer,
+    tie_break: TieBreak,
+    illegal_move: IllegalMove,
+    mut on_move: impl FnMut(&Board, usize),
+) -> (Spot, Board) {
+    let winner = loop {
+        let scores = if current_color == Spot::RED {
+            player1.get_move(board.positions)
+        } else {
+            player2.get_move(board.positions)
+        };
+
+        let (column, winner) =
+            play_scored_move(&mut board, current_color, &scores, tie_break, illegal_move);
+        if let Some(column) = column {
+            on_move(&board, column);
+        }
+        if let Some(winner) = winner {
+            break winner;
+        }
+
+        current_color = if current_color == Spot::RED {
+            Spot::YELLOW
         } else {
-            (false, None)
+            Spot::RED
+        };
+    };
+
+    (winner, board)
+}
+
+/// Plays the move `color` picks with `scores` on `board`. Returns the column played, if any,
+/// and the winner once the game is over (`Spot::EMPTY` for a draw).
+fn play_scored_move(
+    board: &mut Board,
+    color: Spot,
+    scores: &[N; 7],
+    tie_break: TieBreak,
+    illegal_move: IllegalMove,
+) -> (Option<usize>, Option<Spot>) {
+    if illegal_move == IllegalMove::Lose && !board.is_full() && prefers_full_column(scores, board) {
+        // The player forfeits
+        let winner = if color == Spot::RED {
+            Spot::YELLOW
+        } else {
+            Spot::RED
+        };
+        return (None, Some(winner));
+    }
+
+    match choose_move(scores, board, tie_break) {
+        Some(column) => {
+            let (_, win) = board.insert_top(column, color);
+            (Some(column), win)
         }
+        // The board is full
+        None => (None, Some(Spot::EMPTY)),
     }
 }
 
-pub fn start_two_player() {
+/// Plays every game of `games`, each a `(red, yellow)` pair of indices into `players`, from
+/// the empty board. The games advance together one move at a time, and each player scores
+/// the boards of all the games it is to move in with a single `get_moves` call, in parallel
+/// across players. Returns the winner and final board of every game, in the order of
+/// `games`, the same as `play_game` would for each of them as long as the players score a
+/// board the same alone and in a batch. Networks only do up to float rounding, so a close
+/// move can come out differently.
+pub fn play_games_lockstep(
+    players: &[&(dyn MovePlayer + Sync)],
+    games: &[(usize, usize)],
+    tie_break: TieBreak,
+    illegal_move: IllegalMove,
+) -> Vec<(Spot, Board)> {
+    let mut boards = vec![Board::new(); games.len()];
+    let mut winners = vec![None; games.len()];
+    let mut color = Spot::RED;
+
+    loop {
+        // The games every player is to move in
+        let mut to_move = vec![Vec::new(); players.len()];
+        for (game, (red, yellow)) in games.iter().enumerate() {
+            if winners[game].is_some() {
+                continue;
+            }
+            if boards[game].is_full() {
+                winners[game] = Some(Spot::EMPTY);
+                continue;
+            }
+            let player = if color == Spot::RED { *red } else { *yellow };
+            to_move[player].push(game);
+        }
+        if to_move.iter().all(Vec::is_empty) {
+            break;
+        }
+
+        let scores = to_move
+            .par_iter()
+            .enumerate()
+            .map(|(player, games)| {
+                let positions = games
+                    .iter()
+                    .map(|game| boards[*game].positions)
+                    .collect::<Vec<_>>();
+                players[player].get_moves(&positions)
+            })
+            .collect::<Vec<_>>();
+        for (games, scores) in to_move.iter().zip(scores) {
+            for (game, scores) in games.iter().zip(scores) {
+                let (_, winner) =
+                    play_scored_move(&mut boards[*game], color, &scores, tie_break, illegal_move);
+                winners[*game] = winner;
+            }
+        }
+
+        color = if color == Spot::RED {
+            Spot::YELLOW
+        } else {
+            Spot::RED
+        };
+    }
+
+    winners
+        .into_iter()
+        .map(|winner| winner.expect("every game ends"))
+        .zip(boards)
+        .collect()
+}
+
+/// Writes a finished game to `path` as text: the names of both players, the columns played
+/// (1-7) in order and the winner, e.g.
+///
+/// ```text
+/// red nn(42-8-7)
+/// yellow random
+/// moves 4 7 4 7 4 7 4
+/// winner RED
+/// ```
+pub fn write_replay(
+    path: &path::Path,
+    red: &str,
+    yellow: &str,
+    board: &Board,
+    winner: Spot,
+) -> io::Result<()> {
+    let moves = board
+        .history()
+        .iter()
+        .map(|column| (column + 1).to_string())
+        .collect::<Vec<_>>();
+    let winner = match winner {
+        Spot::RED => "RED",
+        Spot::YELLOW => "YELLOW",
+        Spot::EMPTY => "draw",
+    };
+    std::fs::write(
+        path,
+        format!(
+            "red {}\nyellow {}\nmoves {}\nwinner {}\n",
+            red,
+            yellow,
+            moves.join(" "),
+            winner
+        ),
+    )
+}
+
+/// Writes `board` to `svg_path` as an SVG image, if a path is given
+fn write_svg(board: &Board, svg_path: Option<&path::Path>) -> io::Result<()> {
+    match svg_path {
+        Some(svg_path) => std::fs::write(svg_path, board.to_svg()),
+        None => Ok(()),
+    }
+}
+
+pub fn start_two_player(view: BoardView<'_>) -> Result<(), Box<dyn Error>> {
     let mut board = Board::new();
     let mut current_player = Spot::RED;
     let mut fail = "";
 
-    loop {
+    let winner = loop {
         println!(
             "\x1b[2J\x1b[H{}{}It's {}'s turn!",
-            board,
+            board.render(view.style),
             fail,
             current_player.display()
         );
+        write_svg(&board, view.svg_path)?;
         eprint!("Enter your move (between 1-7): ");
         let mut column = String::new();
         let stdin = io::stdin();
@@ -278,29 +1257,11 @@ pub fn start_two_player() {
                 column.pop();
             }
         }
-        match column.parse::<usize>() {
-            Ok(val) if val >= 1 && val <= 7 => {
-                fail = "";
-                match board.insert_top(val - 1, current_player) {
-                    (false, _) => {
-                        fail = concat!(BOLD!(), "That column in full. Try again! ", RESET!());
-                        continue;
-                    }
-                    (true, Some(_)) => {
-                        // Winner
-                        break;
-                    }
-                    (true, None) => {
-                        // Continue playing
-                    }
-                };
-            }
-            _ => {
-                fail = concat!(
-                    BOLD!(),
-                    "Invalid input! Please enter an number between 1-7. ",
-                    RESET!()
-                );
+        match play_input(&mut board, &column, current_player) {
+            Ok(Some(winner)) => break winner,
+            Ok(None) => fail = "",
+            Err(message) => {
+                fail = message;
                 continue;
             }
         }
@@ -310,15 +1271,132 @@ pub fn start_two_player() {
         } else {
             Spot::RED
         };
+    };
+
+    println!(
+        "\x1b[2J\x1b[H{}{}",
+        board.render(view.style),
+        outcome(winner)
+    );
+    write_svg(&board, view.svg_path)?;
+
+    Ok(())
+}
+
+/// Plays the column the human typed in `input` (1-7) for `color`. Returns the winner, or
+/// `Spot::EMPTY` for a draw, if the move ended the game, and the message to show if the move
+/// couldn't be played.
+fn play_input(board: &mut Board, input: &str, color: Spot) -> Result<Option<Spot>, &'static str> {
+    let played = match input.parse::<usize>() {
+        Ok(val) if val >= 1 => board.play_move(val - 1, color),
+        _ => Err(MoveError::OutOfRange),
+    };
+    match played {
+        Ok(Some(winner)) => Ok(Some(winner)),
+        Ok(None) if board.is_full() => Ok(Some(Spot::EMPTY)),
+        Ok(None) => Ok(None),
+        Err(MoveError::ColumnFull) => Err(concat!(
+            BOLD!(),
+            "That column is full. Try again! ",
+            RESET!()
+        )),
+        Err(MoveError::OutOfRange) => Err(concat!(
+            BOLD!(),
+            "Invalid input! Please enter an number between 1-7. ",
+            RESET!()
+        )),
+    }
+}
+
+/// Announces the winner of a finished game, `Spot::EMPTY` being a draw
+fn outcome(winner: Spot) -> String {
+    match winner {
+        Spot::EMPTY => "It's a draw!".to_string(),
+        _ => format!("{} Wins!", winner.display()),
+    }
+}
+
+/// Loads the top agent of the latest saved generation at `ai_path`
+pub fn load_ai<Plr: Player + DeserializeOwned>(
+    ai_path: &path::Path,
+) -> Result<Plr, Box<dyn Error>> {
+    load_latest_ai(ai_path).map(|(player, _, _)| player)
+}
+
+/// Same as `load_ai`, but also returns the file the agent was loaded from and its generation.
+/// Generations that fail to load are skipped in favor of older ones.
+pub fn load_latest_ai<Plr: Player + DeserializeOwned>(
+    ai_path: &path::Path,
+) -> Result<(Plr, path::PathBuf, usize), Box<dyn Error>> {
+    let (generation, file, agents) = helpers::latest_valid_save::<Vec<Agent<Plr>>>(ai_path)?
+        .ok_or("no saved generation that can be loaded exists")?;
+    let player = agents
+        .into_iter()
+        .next()
+        .ok_or("saved generation contains no agents")?
+        .player;
+    Ok((player, file, generation))
+}
+
+/// Loads the top agent of a single saved generation file
+pub fn load_agent_file<Plr: Player + DeserializeOwned>(
+    file_path: &path::Path,
+) -> Result<Plr, Box<dyn Error>> {
+    helpers::read_save::<Vec<Agent<Plr>>>(file_path)?
+        .into_iter()
+        .next()
+        .map(|agent| agent.player)
+        .ok_or_else(|| "saved generation contains no agents".into())
+}
+
+pub fn play_against_ai<Plr: Player + DeserializeOwned>(
+    ai_path: &path::Path,
+    ai_first: bool,
+    view: BoardView<'_>,
+    tie_break: TieBreak,
+    red_perspective: bool,
+    seed: u64,
+    quiet: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (mut nn, file, generation): (Plr, _, _) = load_latest_ai(ai_path)?;
+    nn.set_red_perspective(red_perspective);
+    if !quiet {
+        // Catches loading the wrong run or an early generation by mistake
+        println!(
+            "{}Loaded {} (generation {}): {}{}",
+            BLUE!(),
+            file.display(),
+            generation,
+            nn.name(),
+            RESET!()
+        );
     }
-
-    println!("\x1b[2J\x1b[H{}{} Wins!", board, current_player.display());
+    play_against(
+        &nn,
+        ai_first,
+        view,
+        tie_break,
+        seed,
+        false,
+        &mut io::stdin().lock(),
+    )?;
+    Ok(())
 }
 
-pub fn play_against_ai<Plr: Player + DeserializeOwned>(
-    ai_path: &path::Path,
+/// Plays an interactive game against `ai`, reading the human's moves from `input`. All of
+/// the AI's random choices are drawn from an RNG seeded with `seed`, so the same seed and
+/// moves replay the same game. With `show_search_stats`, the nodes searched and time taken
+/// by the AI's last move are shown, for AIs that search. Returns the final board.
+pub fn play_against(
+    ai: &dyn MovePlayer,
     ai_first: bool,
-) -> Result<(), Box<dyn Error>> {
+    view: BoardView<'_>,
+    tie_break: TieBreak,
+    seed: u64,
+    show_search_stats: bool,
+    input: &mut impl BufRead,
+) -> Result<Board, Box<dyn Error>> {
+    let mut rng = StdRng::seed_from_u64(seed);
     let mut board = Board::new();
     let (mut current_player, ai_turn) = if !ai_first {
         // Ai is yellow
@@ -328,34 +1406,26 @@ pub fn play_against_ai<Plr: Player + DeserializeOwned>(
         (Spot::YELLOW, Spot::RED)
     };
     let mut fail = "";
+    let mut search = String::new();
 
-    let nn: Plr = match helpers::get_max_generation(ai_path)? {
-        Some(dir) => {
-            let path = dir.path();
-            let file = File::open(path)?;
-            serde_cbor::from_reader::<Vec<Agent<Plr>>, _>(file)?
-                .remove(0)
-                .player
-        }
-        None => {
-            println!("Error, no file exists.");
-            process::exit(1);
-        }
-    };
-
-    'outer: loop {
+    let winner = loop {
         println!(
-            "\x1b[2J\x1b[H{}{}It's {}'s turn!",
-            board,
+            "\x1b[2J\x1b[HPlaying against {}, replay seed: {}\n{}{}{}It's {}'s turn!",
+            ai.name(),
+            seed,
+            board.render(view.style),
+            search,
             fail,
             current_player.display()
         );
+        write_svg(&board, view.svg_path)?;
         eprint!("Enter your move (between 1-7): ");
 
         if current_player != ai_turn {
             let mut column = String::new();
-            let stdin = io::stdin();
-            stdin.lock().read_line(&mut column).unwrap();
+            if input.read_line(&mut column)? == 0 {
+                return Err("input ended before the game did".into());
+            }
             if column.ends_with('\n') {
                 column.pop();
                 if column.ends_with('\r') {
@@ -363,57 +1433,32 @@ pub fn play_against_ai<Plr: Player + DeserializeOwned>(
                 }
             }
 
-            match board.insert_top(
-                match column.parse::<usize>() {
-                    Ok(val) if val >= 1 && val <= 7 => {
-                        fail = "";
-                        val - 1
-                    }
-                    _ => {
-                        fail = concat!(
-                            BOLD!(),
-                            "Invalid input! Please enter an number between 1-7. ",
-                            RESET!()
-                        );
-                        continue;
-                    }
-                },
-                current_player,
-            ) {
-                (false, _) => {
-                    fail = concat!(BOLD!(), "That column is full. Try again! ", RESET!());
+            match play_input(&mut board, &column, current_player) {
+                Ok(Some(winner)) => break winner,
+                Ok(None) => fail = "",
+                Err(message) => {
+                    fail = message;
                     continue;
                 }
-                (true, Some(_)) => {
-                    // Winner
-                    break 'outer;
-                }
-                (true, None) => {
-                    // Continue playing
-                }
-            };
+            }
         } else {
-            let moves = nn.get_move(board.positions);
-            let mut nn_moves = moves.iter().enumerate().collect::<Vec<_>>();
-            'inner: loop {
-                let idx = nn_moves
-                    .iter()
-                    .enumerate()
-                    .max_by(|(_, a), (_, b)| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
-                    .unwrap_or((0, &(0, &1.0)));
-
-                match board.insert_top((idx.1).0, current_player) {
-                    (true, Some(_)) => {
-                        break 'outer;
-                    }
-                    (true, None) => {
-                        break 'inner;
-                    }
-                    (_, _) => {
-                        let idx = idx.0;
-                        nn_moves.remove(idx);
-                    }
-                };
+            let moves = ai.get_move(board.positions);
+            if let Some(stats) = ai.search_stats().filter(|_| show_search_stats) {
+                search = format!(
+                    "Searched {} nodes in {}ms\n",
+                    stats.nodes,
+                    stats.time.as_millis()
+                );
+            }
+            match choose_move_with(&moves, &board, tie_break, &mut rng) {
+                Some(column) => match board.play_move(column, current_player) {
+                    Ok(Some(winner)) => break winner,
+                    Ok(None) if board.is_full() => break Spot::EMPTY,
+                    Ok(None) => {}
+                    Err(e) => unreachable!("the AI picked an illegal move: {}", e),
+                },
+                // The board is full
+                None => break Spot::EMPTY,
             }
         }
 
@@ -422,9 +1467,122 @@ pub fn play_against_ai<Plr: Player + DeserializeOwned>(
         } else {
             Spot::RED
         };
-    }
+    };
+
+    println!(
+        "\x1b[2J\x1b[HPlaying against {}, replay seed: {}\n{}{}{}",
+        ai.name(),
+        seed,
+        board.render(view.style),
+        search,
+        outcome(winner)
+    );
+    write_svg(&board, view.svg_path)?;
+
+    Ok(board)
+}
 
-    println!("\x1b[2J\x1b[H{}{} Wins!", board, current_player.display());
+const REPL_HELP: &str = "Commands:
+  move <col>   Play in column <col> (1-7) for the side to move
+  show         Print the board
+  scores       Print the network's score for each column
+  undo         Take back the last move
+  reset        Clear the board
+  save <path>  Save the board to <path>
+  load <path>  Load a board from <path>
+  svg <path>   Write the board to <path> as an SVG image
+  help         Print this message
+  quit         Exit";
+
+/// Interactive loop for playing arbitrary positions and inspecting the network's scores
+pub fn start_repl<Plr: Player + DeserializeOwned>(
+    ai_path: &path::Path,
+    style: BoardStyle,
+) -> Result<(), Box<dyn Error>> {
+    let nn: Plr = load_ai(ai_path)?;
+    let mut board = Board::new();
+    let mut winner: Option<Spot> = None;
+
+    println!("{}", REPL_HELP);
+    let stdin = io::stdin();
+    loop {
+        eprint!("> ");
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let mut args = line.split_whitespace();
+        let current_player = match board.moves() % 2 {
+            0 => Spot::RED,
+            _ => Spot::YELLOW,
+        };
+
+        match (args.next(), args.next()) {
+            (Some("move"), Some(column)) => {
+                if winner.is_some() {
+                    println!("The game is over. Use `undo` or `reset`.");
+                    continue;
+                }
+                let played = match column.parse::<usize>() {
+                    Ok(val) if val >= 1 => board.play_move(val - 1, current_player),
+                    _ => Err(MoveError::OutOfRange),
+                };
+                match played {
+                    Err(MoveError::ColumnFull) => println!("That column is full."),
+                    Err(MoveError::OutOfRange) => {
+                        println!("Invalid column! Please enter an number between 1-7.")
+                    }
+                    Ok(None) if !board.is_full() => print!("{}", board.render(style)),
+                    Ok(win) => {
+                        let win = win.unwrap_or(Spot::EMPTY);
+                        winner = Some(win);
+                        print!("{}", board.render(style));
+                        println!("{}", outcome(win));
+                    }
+                }
+            }
+            (Some("show"), None) => {
+                print!("{}", board.render(style));
+                println!("It's {}'s turn!", current_player.display());
+            }
+            (Some("scores"), None) => {
+                let scores = nn.get_move(board.positions);
+                for (column, score) in scores.iter().enumerate() {
+                    println!("{}: {}", column + 1, score);
+                }
+            }
+            (Some("undo"), None) => match board.undo_move() {
+                Some(column) => {
+                    winner = None;
+                    println!("Took back the move in column {}.", column + 1);
+                }
+                None => println!("There are no moves to undo."),
+            },
+            (Some("reset"), None) => {
+                board = Board::new();
+                winner = None;
+            }
+            (Some("save"), Some(path)) => {
+                let file = File::create(path)?;
+                serde_cbor::to_writer(file, &board)?;
+                println!("Saved board to {}.", path);
+            }
+            (Some("load"), Some(path)) => {
+                let file = File::open(path)?;
+                board = serde_cbor::from_reader(file)?;
+                winner = None;
+                print!("{}", board.render(style));
+            }
+            (Some("svg"), Some(path)) => {
+                write_svg(&board, Some(path::Path::new(path)))?;
+                println!("Wrote board to {}.", path);
+            }
+            (Some("help"), None) => println!("{}", REPL_HELP),
+            (Some("quit"), None) | (Some("exit"), None) => break,
+            (None, _) => {}
+            _ => println!("Unknown command. Type `help` for a list of commands."),
+        }
+    }
 
     Ok(())
 }
@@ -433,6 +1591,208 @@ pub fn play_against_ai<Plr: Player + DeserializeOwned>(
 mod game_tests {
     use super::*;
 
+    /// Plays its columns in order, one per turn
+    struct ScriptedPlayer(Vec<usize>);
+
+    impl MovePlayer for ScriptedPlayer {
+        fn get_move(&self, board: [[Spot; 6]; 7]) -> [N; 7] {
+            let pieces = board
+                .iter()
+                .flatten()
+                .filter(|s| **s != Spot::EMPTY)
+                .count();
+            let mut scores = [0.0; 7];
+            scores[self.0[pieces / 2]] = 1.0;
+            scores
+        }
+    }
+
+    #[test]
+    fn illegal_move() {
+        // Both always want the first column, which is full after six moves
+        let red = ScriptedPlayer(vec![0; 21]);
+        let yellow = ScriptedPlayer(vec![0; 21]);
+        let play = |illegal_move| {
+            play_game(
+                Board::new(),
+                Spot::RED,
+                &red,
+                &yellow,
+                TieBreak::First,
+                illegal_move,
+            )
+        };
+
+        let (winner, board) = play(IllegalMove::Lose);
+        assert_eq!(Spot::YELLOW, winner);
+        assert_eq!(6, board.moves());
+
+        // Skipping fills the columns from the left until red completes the bottom row
+        let (winner, board) = play(IllegalMove::Skip);
+        assert_eq!(Spot::RED, winner);
+        assert_eq!(19, board.moves());
+
+        // A full column tied with a legal one isn't preferred
+        let mut scores = [0.0; 7];
+        scores[0] = 1.0;
+        scores[4] = 1.0;
+        assert!(!prefers_full_column(&scores, &board));
+        scores[4] = 0.5;
+        assert!(prefers_full_column(&scores, &board));
+    }
+
+    #[test]
+    fn play_games_lockstep() {
+        let players = [
+            ScriptedPlayer(vec![0; 21]),
+            ScriptedPlayer(vec![1; 21]),
+            ScriptedPlayer((0..21).map(|i| i * 3 % 7).collect()),
+        ];
+        let dyn_players = players
+            .iter()
+            .map(|player| player as &(dyn MovePlayer + Sync))
+            .collect::<Vec<_>>();
+        let games = [(0, 1), (1, 0), (0, 0), (2, 1), (1, 2), (2, 2), (0, 2)];
+
+        for illegal_move in [IllegalMove::Skip, IllegalMove::Lose] {
+            let outcomes =
+                super::play_games_lockstep(&dyn_players, &games, TieBreak::First, illegal_move);
+            assert_eq!(games.len(), outcomes.len());
+            for ((red, yellow), (winner, board)) in games.iter().zip(outcomes) {
+                let (expected_winner, expected_board) = play_game(
+                    Board::new(),
+                    Spot::RED,
+                    &players[*red],
+                    &players[*yellow],
+                    TieBreak::First,
+                    illegal_move,
+                );
+                assert_eq!(expected_winner, winner);
+                assert_eq!(expected_board.history(), board.history());
+            }
+        }
+    }
+
+    #[test]
+    fn spot_char_round_trip() {
+        for spot in &[Spot::EMPTY, Spot::RED, Spot::YELLOW] {
+            assert_eq!(Ok(*spot), Spot::try_from(spot.as_char()));
+        }
+        assert_eq!('.', Spot::EMPTY.as_char());
+        assert_eq!('R', Spot::RED.as_char());
+        assert_eq!('Y', Spot::YELLOW.as_char());
+    }
+
+    #[test]
+    fn random_position() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for moves in 0..42 {
+            let board = Board::random_position(moves, &mut rng);
+            assert!(board.moves() <= moves);
+            assert_eq!(board.moves(), board.history().len());
+            if moves <= 6 {
+                // Nobody can connect four yet, so every move is played
+                assert_eq!(moves, board.moves());
+            }
+
+            let (red, yellow) = (
+                board.piece_count(Spot::RED),
+                board.piece_count(Spot::YELLOW),
+            );
+            assert_eq!(board.moves(), red + yellow);
+            assert!(red == yellow || red == yellow + 1);
+            for (column, spots) in board.positions.iter().enumerate() {
+                let height = spots.iter().filter(|s| **s != Spot::EMPTY).count();
+                assert!(spots[..6 - height].iter().all(|s| *s == Spot::EMPTY));
+                assert_eq!(5 - height as isize, board.highest_pieces[column]);
+            }
+            assert!(!board.is_full());
+
+            // Replaying the moves never ends the game
+            let mut replay = Board::new();
+            for (i, column) in board.history().iter().enumerate() {
+                let color = if i % 2 == 0 { Spot::RED } else { Spot::YELLOW };
+                assert_eq!(Ok(None), replay.play_move(*column, color));
+            }
+            assert_eq!(board.positions, replay.positions);
+        }
+    }
+
+    #[test]
+    fn check_invariants() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let board = Board::random_position(20, &mut rng);
+        assert_eq!(Ok(()), board.check_invariants());
+        assert_eq!(Ok(()), Board::new().check_invariants());
+
+        // A piece at the top of an empty column
+        let mut floating = Board::new();
+        floating.positions[2][0] = Spot::RED;
+        floating.moves = 1;
+        assert_eq!(
+            Err(BoardInvariantError::FloatingPiece { column: 2, row: 5 }),
+            floating.check_invariants()
+        );
+
+        let mut highest = Board::new();
+        highest.insert_top(4, Spot::RED);
+        highest.highest_pieces[4] = 5;
+        assert_eq!(
+            Err(BoardInvariantError::HighestPiece {
+                column: 4,
+                expected: 4,
+                found: 5
+            }),
+            highest.check_invariants()
+        );
+
+        let mut moves = Board::new();
+        moves.insert_top(0, Spot::RED);
+        moves.insert_top(0, Spot::YELLOW);
+        moves.moves = 3;
+        assert_eq!(
+            Err(BoardInvariantError::MoveCount {
+                pieces: 2,
+                moves: 3
+            }),
+            moves.check_invariants()
+        );
+    }
+
+    #[test]
+    fn board_debug() {
+        let mut board = Board::new();
+        board.insert_top(3, Spot::RED);
+        board.insert_top(3, Spot::YELLOW);
+        board.insert_top(0, Spot::RED);
+        assert_eq!(
+            ".......\n.......\n.......\n.......\n...Y...\nR..R...\n",
+            format!("{:?}", board)
+        );
+    }
+
+    #[test]
+    fn board_svg() {
+        let mut board = Board::new();
+        board.insert_top(3, Spot::RED);
+        board.insert_top(3, Spot::YELLOW);
+        board.insert_top(0, Spot::RED);
+        let svg = board.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(42, svg.matches("<circle").count());
+        assert_eq!(2, svg.matches("fill=\"#d6201f\"").count());
+        assert_eq!(1, svg.matches("fill=\"#f5c518\"").count());
+        assert_eq!(39, svg.matches("fill=\"white\"").count());
+        // The bottom left piece is drawn in the bottom row
+        assert!(svg.contains("<circle cx=\"50\" cy=\"550\" r=\"40\" fill=\"#d6201f\"/>"));
+    }
+
+    #[test]
+    fn spot_char_invalid() {
+        assert_eq!(Err(InvalidSpotChar('x')), Spot::try_from('x'));
+        assert_eq!(Err(InvalidSpotChar('r')), Spot::try_from('r'));
+    }
+
     #[test]
     fn forward_diagonal_1() {
         let mut board = Board::new();
@@ -643,6 +2003,192 @@ mod game_tests {
         assert_eq!((true, Some(Spot::RED)), board.insert_top(3, Spot::RED));
     }
 
+    #[test]
+    fn undo_move() {
+        let mut board = Board::new();
+        board.insert_top(3, Spot::RED);
+        board.insert_top(3, Spot::YELLOW);
+        assert_eq!(Some(3), board.undo_move());
+        assert_eq!(1, board.moves());
+        assert_eq!(Spot::EMPTY, board.positions[3][4]);
+        assert_eq!(Spot::RED, board.positions[3][5]);
+        assert_eq!((true, None), board.insert_top(3, Spot::RED));
+        assert_eq!(Spot::RED, board.positions[3][4]);
+        assert_eq!(Some(3), board.undo_move());
+        assert_eq!(Some(3), board.undo_move());
+        assert_eq!(None, board.undo_move());
+    }
+
+    #[test]
+    fn last_move() {
+        let mut board = Board::new();
+        assert_eq!(None, board.last_move());
+        board.insert_top(3, Spot::RED);
+        assert_eq!(Some((3, 5)), board.last_move());
+        board.insert_top(3, Spot::YELLOW);
+        assert_eq!(Some((3, 4)), board.last_move());
+        board.insert_top(0, Spot::RED);
+        assert_eq!(Some((0, 5)), board.last_move());
+        assert_eq!(1, board.to_string().matches("▓▓").count());
+
+        board.undo_move();
+        assert_eq!(Some((3, 4)), board.last_move());
+        board.undo_move();
+        board.undo_move();
+        assert_eq!(None, board.last_move());
+        assert!(!board.to_string().contains("▓▓"));
+
+        // Playing in a full column doesn't move it
+        for _ in 0..6 {
+            board.insert_top(6, Spot::RED);
+        }
+        assert_eq!((false, None), board.insert_top(6, Spot::YELLOW));
+        assert_eq!(Some((6, 0)), board.last_move());
+    }
+
+    #[test]
+    fn from_position() {
+        let (r, y, e) = (Spot::RED, Spot::YELLOW, Spot::EMPTY);
+        let board = Board::from_position(vec![
+            vec![r, y],
+            vec![],
+            vec![],
+            vec![y, r, e],
+            vec![],
+            vec![],
+            vec![r; 6],
+        ])
+        .unwrap();
+        let mut played = Board::new();
+        for (column, spot) in &[(0, r), (0, y), (3, y), (3, r)] {
+            played.insert_top(*column, *spot);
+        }
+        for _ in 0..6 {
+            played.insert_top(6, r);
+        }
+        assert_eq!(played.positions, board.positions);
+        assert_eq!(played.moves(), board.moves());
+        assert_eq!(None, board.last_move());
+        assert!(!board.is_legal(6));
+
+        // Pieces land where they would have been played
+        let mut board = board;
+        assert_eq!((true, None), board.insert_top(0, r));
+        assert_eq!(r, board.positions[0][3]);
+        assert_eq!(Some((0, 3)), board.last_move());
+    }
+
+    #[test]
+    fn from_grid() {
+        let mut played = Board::new();
+        for (column, spot) in &[(3, Spot::RED), (3, Spot::YELLOW), (0, Spot::RED)] {
+            played.insert_top(*column, *spot);
+        }
+        let board = Board::from_grid(&format!("{:?}", played)).unwrap();
+        assert_eq!(played.positions, board.positions);
+        assert_eq!(3, board.moves());
+
+        let board = Board::from_grid(
+            "  .......
+               .......
+               .......
+               .......
+               ...Y...
+               R..R...",
+        )
+        .unwrap();
+        assert_eq!(played.positions, board.positions);
+
+        assert!(Board::from_grid(".......\n.......").is_err());
+        let short_row = ".......\n".repeat(5) + "......\n";
+        assert!(Board::from_grid(&short_row).is_err());
+        let floating = ".......\n".repeat(4) + "...R...\n.......\n";
+        assert!(Board::from_grid(&floating).is_err());
+        let wrong_char = ".......\n".repeat(5) + "...X...\n";
+        assert!(Board::from_grid(&wrong_char).is_err());
+    }
+
+    #[test]
+    fn from_position_invalid() {
+        let (r, e) = (Spot::RED, Spot::EMPTY);
+        assert_eq!(
+            Err(InvalidPosition::Columns(6)),
+            Board::try_from(vec![vec![]; 6]).map(|_| ())
+        );
+        let mut too_high = vec![vec![]; 7];
+        too_high[2] = vec![r; 7];
+        assert_eq!(
+            Err(InvalidPosition::ColumnHeight {
+                column: 2,
+                height: 7
+            }),
+            Board::from_position(too_high).map(|_| ())
+        );
+        let mut floating = vec![vec![]; 7];
+        floating[4] = vec![r, e, r];
+        assert_eq!(
+            Err(InvalidPosition::FloatingPiece { column: 4, row: 2 }),
+            Board::from_position(floating).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn winning_moves() {
+        let (r, y) = (Spot::RED, Spot::YELLOW);
+        let board = Board::from_position(vec![
+            vec![r, r, r],
+            vec![r, r],
+            vec![y],
+            vec![y],
+            vec![y],
+            vec![],
+            vec![],
+        ])
+        .unwrap();
+        // Red wins on top of column 0, and yellow in column 5 along the bottom row, since
+        // column 1 is taken by red
+        assert_eq!(vec![0], board.winning_moves(Spot::RED));
+        assert_eq!(vec![5], board.winning_moves(Spot::YELLOW));
+        assert!(Board::new().winning_moves(Spot::RED).is_empty());
+    }
+
+    #[test]
+    fn remaining_moves_empty() {
+        let board = Board::new();
+        assert_eq!(42, board.remaining_moves());
+        assert!(!board.is_full());
+    }
+
+    #[test]
+    fn remaining_moves_full() {
+        let mut board = Board::new();
+        // Alternating columns, laid out so that no one wins
+        let a = [
+            Spot::RED,
+            Spot::YELLOW,
+            Spot::RED,
+            Spot::YELLOW,
+            Spot::RED,
+            Spot::YELLOW,
+        ];
+        let b = [
+            Spot::YELLOW,
+            Spot::RED,
+            Spot::YELLOW,
+            Spot::RED,
+            Spot::YELLOW,
+            Spot::RED,
+        ];
+        for (column, pieces) in [a, a, b, b, a, a, b].iter().enumerate() {
+            for &spot in pieces {
+                assert_eq!((true, None), board.insert_top(column, spot));
+            }
+        }
+        assert_eq!(0, board.remaining_moves());
+        assert!(board.is_full());
+        assert_eq!((true, Some(Spot::EMPTY)), board.insert_top(0, Spot::RED));
+    }
+
     #[test]
     fn overflow_test() {
         let mut board = Board::new();
@@ -654,4 +2200,352 @@ mod game_tests {
         assert_eq!((true, None), board.insert_top(0, Spot::RED));
         assert_eq!((false, None), board.insert_top(0, Spot::YELLOW));
     }
+
+    #[test]
+    fn variant_boards() {
+        assert_eq!((6, 7), Variant::Standard.dimensions());
+        assert_eq!((5, 6), Variant::Small.dimensions());
+        assert_eq!((7, 8), Variant::Large.dimensions());
+
+        let board = Board::for_variant(Variant::Standard).unwrap();
+        assert_eq!(Variant::Standard.dimensions(), board.dimensions);
+        assert_eq!(
+            (board.positions[0].len(), board.positions.len()),
+            board.dimensions
+        );
+        for variant in [Variant::Small, Variant::Large] {
+            assert_eq!(
+                Err(UnsupportedVariant(variant)),
+                Board::for_variant(variant).map(|_| ())
+            );
+        }
+    }
+
+    #[test]
+    fn variant_structures() {
+        let default = [42, 128, 256, 7];
+        for (variant, name) in [
+            (Variant::Standard, "standard"),
+            (Variant::Small, "small"),
+            (Variant::Large, "large"),
+        ] {
+            assert_eq!(variant, Variant::from_string(name));
+            let (rows, columns) = variant.dimensions();
+            for encoding in [Encoding::Scalar, Encoding::TwoPlane] {
+                let structure = variant.fit_structure(&default, encoding);
+                assert_eq!(
+                    vec![
+                        rows * columns * encoding.inputs_per_spot(),
+                        128,
+                        256,
+                        columns
+                    ],
+                    structure
+                );
+                assert!(variant.matches_structure(&structure, encoding));
+            }
+        }
+        // The default structure already fits the standard board
+        assert!(Variant::Standard.matches_structure(&default, Encoding::Scalar));
+        assert!(!Variant::Standard.matches_structure(&default, Encoding::TwoPlane));
+        assert!(!Variant::Small.matches_structure(&default, Encoding::Scalar));
+        for encoding in [Encoding::Scalar, Encoding::TwoPlane] {
+            assert_eq!(
+                encoding.encoder().input_size(),
+                Variant::Standard.structure_endpoints(encoding).0
+            );
+        }
+    }
+
+    #[test]
+    fn replay_seed() {
+        use crate::ai::{nn::Activation, NNPlayer};
+
+        // Every column scores the same, so each AI move is a random tie-break
+        let ai = NNPlayer::constant(vec![42, 7], vec![Activation::Sigmoid]);
+        let moves = (0..200)
+            .map(|i| format!("{}\n", i % 7 + 1))
+            .collect::<String>();
+        let play = |seed| {
+            play_against(
+                &ai,
+                false,
+                BoardView::default(),
+                TieBreak::Random,
+                seed,
+                false,
+                &mut moves.as_bytes(),
+            )
+            .unwrap()
+            .positions
+        };
+        for seed in 0..5 {
+            assert_eq!(play(seed), play(seed));
+        }
+        assert!((0..5).any(|seed| play(seed) != play(seed + 5)));
+    }
+
+    #[test]
+    fn play_against_input_ends() {
+        let ai = crate::ai::RandomPlayer::new();
+        let played = play_against(
+            &ai,
+            false,
+            BoardView::default(),
+            TieBreak::First,
+            0,
+            false,
+            &mut "1\n".as_bytes(),
+        );
+        assert!(played.is_err());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        use rand::Rng;
+
+        assert_eq!([0; 11], Board::new().to_bytes());
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let mut board = Board::new();
+            let mut color = Spot::RED;
+            for _ in 0..rng.gen_range(0, 43) {
+                let legal = (0..7)
+                    .filter(|column| board.is_legal(*column))
+                    .collect::<Vec<_>>();
+                if legal.is_empty() {
+                    break;
+                }
+                board.insert_top(legal[rng.gen_range(0, legal.len())], color);
+                color = if color == Spot::RED {
+                    Spot::YELLOW
+                } else {
+                    Spot::RED
+                };
+            }
+
+            let bytes = board.to_bytes();
+            let decoded = Board::from_bytes(&bytes).unwrap();
+            assert_eq!(board.positions, decoded.positions);
+            assert_eq!(board.highest_pieces, decoded.highest_pieces);
+            assert_eq!(board.moves(), decoded.moves());
+            assert_eq!(bytes, decoded.to_bytes());
+            assert_eq!(bytes, <[u8; 11]>::from(&Board::try_from(&bytes).unwrap()));
+        }
+    }
+
+    #[test]
+    fn bytes_invalid() {
+        // Code 3 in the bottom spot of the first column
+        let mut bytes = [0; 11];
+        bytes[1] = 0b11 << 2;
+        assert_eq!(
+            Some(InvalidPosition::InvalidSpotCode { column: 0, row: 0 }),
+            Board::from_bytes(&bytes).err()
+        );
+        // Red in the top spot of the first column, with nothing below it
+        let mut bytes = [0; 11];
+        bytes[0] = 1;
+        assert_eq!(
+            Some(InvalidPosition::FloatingPiece { column: 0, row: 5 }),
+            Board::from_bytes(&bytes).err()
+        );
+    }
+
+    #[test]
+    fn play_move() {
+        let mut board = Board::new();
+        assert_eq!(Ok(None), board.play_move(0, Spot::RED));
+        assert_eq!(Err(MoveError::OutOfRange), board.play_move(7, Spot::RED));
+        for _ in 0..3 {
+            assert_eq!(Ok(None), board.play_move(1, Spot::YELLOW));
+            assert_eq!(Ok(None), board.play_move(1, Spot::RED));
+        }
+        assert_eq!(Err(MoveError::ColumnFull), board.play_move(1, Spot::RED));
+        for _ in 0..3 {
+            assert_eq!(Ok(None), board.play_move(2, Spot::YELLOW));
+        }
+        assert_eq!(Ok(Some(Spot::YELLOW)), board.play_move(2, Spot::YELLOW));
+        // Failed moves leave the board as it was
+        assert_eq!(11, board.moves());
+        assert_eq!(Some((2, 2)), board.last_move());
+    }
+
+    #[test]
+    fn play_input() {
+        let mut board = Board::new();
+        assert_eq!(Ok(None), super::play_input(&mut board, "4", Spot::RED));
+        assert_eq!(Spot::RED, board.positions[3][5]);
+        for input in ["0", "8", "four", ""] {
+            assert!(super::play_input(&mut board, input, Spot::RED).is_err());
+        }
+        assert_eq!(1, board.moves());
+    }
+
+    #[test]
+    fn load_latest_ai() {
+        use crate::ai::{agent::Evolvable, nn::Activation, NNPlayer};
+        let dir = std::env::temp_dir().join(format!("fourai_load_latest_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for gen in [5, 50] {
+            let agent = Agent::new(NNPlayer::new_from_param(
+                vec![42, gen, 7],
+                vec![Activation::Sigmoid, Activation::Sigmoid],
+            ));
+            let path = dir.join(format!("gen_{}", gen));
+            helpers::write_save(&path, &vec![agent], helpers::SaveFormat::Cbor).unwrap();
+        }
+
+        let (player, file, generation): (NNPlayer, _, _) =
+            super::load_latest_ai(&dir.join("gen")).unwrap();
+        assert_eq!(dir.join("gen_50"), file);
+        assert_eq!(50, generation);
+        assert_eq!("nn(42-50-7)", player.name());
+        assert!(super::load_latest_ai::<NNPlayer>(&dir.join("other")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn evaluate() {
+        assert_eq!(0, Board::new().evaluate());
+
+        let mut board = Board::new();
+        for (column, color) in [
+            (0, Spot::RED),
+            (4, Spot::YELLOW),
+            (1, Spot::RED),
+            (4, Spot::YELLOW),
+        ] {
+            board.insert_top(column, color);
+        }
+        assert_eq!(0, board.evaluate());
+        board.insert_top(2, Spot::RED);
+        // Open to the right, and the window to the left of column 4 holds three reds too
+        assert_eq!(1, board.evaluate());
+        board.insert_top(4, Spot::YELLOW);
+        // Yellow's vertical three makes up for it
+        assert_eq!(0, board.evaluate());
+    }
+
+    #[test]
+    fn threats_created_by_move() {
+        let mut board = Board::new();
+        for (column, color) in [
+            (1, Spot::RED),
+            (6, Spot::YELLOW),
+            (2, Spot::RED),
+            (6, Spot::YELLOW),
+        ] {
+            board.insert_top(column, color);
+        }
+        // Three in a row along the bottom, open on both ends: a fork
+        assert_eq!(2, board.threats_created_by_move(3, Spot::RED));
+        assert_eq!(1, board.threats_created_by_move(4, Spot::RED));
+        assert_eq!(0, board.threats_created_by_move(3, Spot::YELLOW));
+        // A third yellow piece on top of column 6 threatens the fourth
+        assert_eq!(1, board.threats_created_by_move(6, Spot::YELLOW));
+
+        board.insert_top(3, Spot::RED);
+        board.insert_top(0, Spot::YELLOW);
+        // The spot in column 4 is already a threat
+        assert_eq!(0, board.threats_created_by_move(5, Spot::RED));
+
+        for _ in 0..6 {
+            board.insert_top(5, Spot::YELLOW);
+        }
+        let full = board.clone();
+        assert_eq!(0, board.threats_created_by_move(5, Spot::RED));
+        assert_eq!(0, board.threats_created_by_move(7, Spot::RED));
+        assert_eq!(full.positions, board.positions);
+        assert_eq!(full.history(), board.history());
+    }
+
+    #[test]
+    fn creates_fork() {
+        let board = Board::new();
+        assert!((0..7).all(|column| !board.creates_fork(column, Spot::RED)));
+
+        let mut board = Board::new();
+        for (column, color) in [
+            (1, Spot::RED),
+            (6, Spot::YELLOW),
+            (2, Spot::RED),
+            (6, Spot::YELLOW),
+        ] {
+            board.insert_top(column, color);
+        }
+        // Red can then win in column 0 or 4
+        assert!(board.creates_fork(3, Spot::RED));
+        assert!(!board.creates_fork(4, Spot::RED));
+        assert!(!board.creates_fork(3, Spot::YELLOW));
+        assert!(!board.creates_fork(7, Spot::RED));
+
+        // Three red pieces in the second row threaten two spots that can't be played yet
+        let mut board = Board::new();
+        for (column, color) in [
+            (2, Spot::YELLOW),
+            (2, Spot::RED),
+            (3, Spot::YELLOW),
+            (3, Spot::RED),
+            (4, Spot::YELLOW),
+        ] {
+            board.insert_top(column, color);
+        }
+        assert_eq!(2, board.threats_created_by_move(4, Spot::RED));
+        assert!(!board.creates_fork(4, Spot::RED));
+        // Yellow's bottom row is open on both ends, but completing it wins outright
+        assert!(!board.creates_fork(1, Spot::YELLOW));
+    }
+
+    #[test]
+    fn piece_count_and_to_move() {
+        let mut board = Board::new();
+        assert_eq!(Spot::RED, board.to_move());
+        assert_eq!(0, board.piece_count(Spot::RED));
+
+        board.play_move(3, Spot::RED).unwrap();
+        assert_eq!(Spot::YELLOW, board.to_move());
+
+        for (i, column) in [2, 3, 4, 4, 0].iter().enumerate() {
+            board.play_move(*column, board.to_move()).unwrap();
+            assert_eq!(i + 2, board.moves());
+        }
+        assert_eq!(3, board.piece_count(Spot::RED));
+        assert_eq!(3, board.piece_count(Spot::YELLOW));
+        assert_eq!(36, board.piece_count(Spot::EMPTY));
+        assert_eq!(Spot::RED, board.to_move());
+    }
+
+    #[test]
+    fn render_styles() {
+        let mut board = Board::new();
+        board.play_move(3, Spot::RED).unwrap();
+        board.play_move(3, Spot::YELLOW).unwrap();
+        board.play_move(0, Spot::RED).unwrap();
+
+        let ascii = board.render(BoardStyle::Ascii);
+        let lines = ascii.lines().collect::<Vec<_>>();
+        assert_eq!(9, lines.len());
+        assert_eq!("  1   2   3   4   5   6   7", lines[0]);
+        assert_eq!("+---+---+---+---+---+---+---+", lines[1]);
+        assert_eq!("| . | . | . | Y | . | . | . |", lines[6]);
+        // The last piece played is lowercase
+        assert_eq!("| r | . | . | R | . | . | . |", lines[7]);
+        assert!(lines[1..].iter().all(|line| line.len() == lines[1].len()));
+
+        let emoji = board.render(BoardStyle::Emoji);
+        let lines = emoji.lines().collect::<Vec<_>>();
+        assert_eq!(7, lines.len());
+        assert_eq!("1 2 3 4 5 6 7", lines[0]);
+        assert_eq!("⚪⚪⚪🟡⚪⚪⚪", lines[5]);
+        assert_eq!("🔴⚪⚪🔴⚪⚪⚪", lines[6]);
+        assert!(lines[1..].iter().all(|line| line.chars().count() == 7));
+
+        let unicode = board.render(BoardStyle::Unicode);
+        assert_eq!(board.to_string(), unicode);
+        assert_eq!(9, unicode.lines().count());
+        assert_eq!(1, unicode.matches("▓▓").count());
+        assert_eq!(2, unicode.matches("██").count());
+    }
 }
@@ -2,17 +2,276 @@ use std::error::Error;
 use std::fs;
 use std::path;
 
-pub fn get_max_generation(file_path: &path::Path) -> Result<Option<fs::DirEntry>, Box<dyn Error>> {
-    Ok(
-        fs::read_dir(file_path.parent().unwrap_or(path::Path::new("./")))?
-            .map(|file| file.expect(concat!(RED!(), "File failed", RESET!())))
-            .max_by_key(|val| {
-                val.file_name()
-                    .to_str()?
-                    .split("_")
-                    .last()?
-                    .parse::<usize>()
-                    .ok()
-            }),
-    )
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Formats a generation can be saved in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaveFormat {
+    Cbor,
+    Json,
+}
+
+impl SaveFormat {
+    pub fn from_string(s: &str) -> SaveFormat {
+        match s {
+            "cbor" => SaveFormat::Cbor,
+            "json" => SaveFormat::Json,
+            _ => panic!("invalid save format: {}", s),
+        }
+    }
+}
+
+/// Reads a save in any of the `SaveFormat`s
+pub fn read_save<T: DeserializeOwned>(file_path: &path::Path) -> Result<T, Box<dyn Error>> {
+    let bytes = fs::read(file_path)?;
+    match serde_cbor::from_slice(&bytes) {
+        Ok(value) => Ok(value),
+        Err(cbor_error) => serde_json::from_slice(&bytes).map_err(|json_error| {
+            format!(
+                "{} is not a valid save (as CBOR: {}, as JSON: {})",
+                file_path.display(),
+                cbor_error,
+                json_error
+            )
+            .into()
+        }),
+    }
+}
+
+/// Path `write_atomic` writes to before moving the file to `file_path`
+fn temp_path(file_path: &path::Path) -> path::PathBuf {
+    let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    file_path.with_file_name(name)
+}
+
+/// Creates the file at `file_path` with `write`, going through a temporary file next to it
+/// that is only renamed to `file_path` once it is completely written. A crash while writing
+/// leaves any previous file at `file_path` intact instead of a truncated one.
+pub fn write_atomic<F>(file_path: &path::Path, write: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut fs::File) -> Result<(), Box<dyn Error>>,
+{
+    let temp = temp_path(file_path);
+    let mut file = fs::File::create(&temp)?;
+    let written = write(&mut file).and_then(|_| Ok(file.sync_all()?));
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    fs::rename(&temp, file_path)?;
+    Ok(())
+}
+
+pub fn write_save<T: Serialize>(
+    file_path: &path::Path,
+    value: &T,
+    format: SaveFormat,
+) -> Result<(), Box<dyn Error>> {
+    write_atomic(file_path, |file| {
+        match format {
+            SaveFormat::Cbor => serde_cbor::to_writer(file, value)?,
+            SaveFormat::Json => serde_json::to_writer(file, value)?,
+        }
+        Ok(())
+    })
+}
+
+/// Re-saves the save at `from` to `to` in `format`
+pub fn convert_save<T: Serialize + DeserializeOwned>(
+    from: &path::Path,
+    to: &path::Path,
+    format: SaveFormat,
+) -> Result<(), Box<dyn Error>> {
+    let value: T = read_save(from)?;
+    write_save(to, &value, format)
+}
+
+/// Generation number of a save file named `<prefix>_<generation>`
+fn generation_number(file_name: &str, prefix: &str) -> Option<usize> {
+    file_name
+        .strip_prefix(prefix)?
+        .strip_prefix("_")?
+        .parse::<usize>()
+        .ok()
+}
+
+/// Every numbered generation save of `file_path` with its generation, most recent first,
+/// ignoring any other files in the same folder (e.g. the `_config.json`)
+fn generations(file_path: &path::Path) -> Result<Vec<(usize, path::PathBuf)>, Box<dyn Error>> {
+    let prefix = file_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let mut generations = fs::read_dir(file_path.parent().unwrap_or(path::Path::new("./")))?
+        .filter_map(|file| {
+            let file = file.ok()?;
+            let gen = generation_number(file.file_name().to_str()?, prefix)?;
+            Some((gen, file.path()))
+        })
+        .collect::<Vec<_>>();
+    generations.sort_unstable_by_key(|(gen, _)| std::cmp::Reverse(*gen));
+    Ok(generations)
+}
+
+/// Generation, path and contents of a loaded save
+pub type LoadedSave<T> = (usize, path::PathBuf, T);
+
+/// Loads the save with the highest generation number for `file_path` that can be read,
+/// returning its generation, path and contents. Saves that fail to load, like ones truncated
+/// by a crash, are skipped with a warning in favor of the next most recent one.
+pub fn latest_valid_save<T: DeserializeOwned>(
+    file_path: &path::Path,
+) -> Result<Option<LoadedSave<T>>, Box<dyn Error>> {
+    for (gen, save_path) in generations(file_path)? {
+        match read_save(&save_path) {
+            Ok(value) => return Ok(Some((gen, save_path, value))),
+            Err(e) => eprintln!(
+                "{}Skipping generation {}, which failed to load: {}{}",
+                YELLOW!(),
+                gen,
+                e,
+                RESET!()
+            ),
+        }
+    }
+    Ok(None)
+}
+
+/// Deletes the save files of `file_path`, except for the `keep` most recent generations.
+/// Files that aren't numbered generations are left alone. Returns the number of files deleted.
+pub fn prune_generations(file_path: &path::Path, keep: usize) -> Result<usize, Box<dyn Error>> {
+    let generations = generations(file_path)?;
+    let mut removed = 0;
+    for (_, old_path) in generations.iter().skip(keep) {
+        fs::remove_file(old_path)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Builds the global rayon thread pool with every worker pinned to one of the CPUs this process
+/// may run on, so threads don't migrate between cores and lose their caches.
+#[cfg(target_os = "linux")]
+pub fn pin_rayon_threads() -> Result<(), Box<dyn Error>> {
+    let set_size = std::mem::size_of::<libc::cpu_set_t>();
+    let mut allowed: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sched_getaffinity(0, set_size, &mut allowed) } != 0 {
+        return Err("failed to read the CPU affinity of the process".into());
+    }
+    let cpus = (0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &allowed) })
+        .collect::<Vec<_>>();
+    if cpus.is_empty() {
+        return Err("no CPUs available to pin threads to".into());
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(cpus.len())
+        .start_handler(move |thread| unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(cpus[thread % cpus.len()], &mut set);
+            libc::sched_setaffinity(0, set_size, &set);
+        })
+        .build_global()?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_rayon_threads() -> Result<(), Box<dyn Error>> {
+    Err("CPU affinity is only supported on Linux".into())
+}
+
+#[cfg(test)]
+mod helpers_tests {
+    use super::*;
+    use crate::ai::{agent::Agent, agent::Evolvable, nn::Activation, NNPlayer};
+    use std::io::Write;
+
+    #[test]
+    fn convert_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fourai_convert_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let population = (0..3)
+            .map(|_| {
+                Agent::new(NNPlayer::new_from_param(
+                    vec![42, 8, 7],
+                    vec![Activation::ELU, Activation::Sigmoid],
+                ))
+            })
+            .collect::<Vec<_>>();
+        let original = dir.join("gen_1");
+        write_save(&original, &population, SaveFormat::Cbor).unwrap();
+
+        let json = dir.join("gen_1.json");
+        let cbor = dir.join("gen_1.cbor");
+        convert_save::<Vec<Agent<NNPlayer>>>(&original, &json, SaveFormat::Json).unwrap();
+        convert_save::<Vec<Agent<NNPlayer>>>(&json, &cbor, SaveFormat::Cbor).unwrap();
+        assert_eq!(fs::read(&original).unwrap(), fs::read(&cbor).unwrap());
+        assert!(serde_json::from_slice::<serde_json::Value>(&fs::read(&json).unwrap()).is_ok());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn population(size: usize) -> Vec<Agent<NNPlayer>> {
+        (0..size)
+            .map(|_| {
+                Agent::new(NNPlayer::new_from_param(
+                    vec![42, 8, 7],
+                    vec![Activation::ELU, Activation::Sigmoid],
+                ))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn atomic_write() {
+        let dir = std::env::temp_dir().join(format!("fourai_atomic_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gen_1");
+        write_save(&path, &population(2), SaveFormat::Cbor).unwrap();
+        let saved = fs::read(&path).unwrap();
+
+        // A write that fails halfway leaves the previous save as it was
+        let failed = write_atomic(&path, |file| {
+            file.write_all(&saved[..saved.len() / 2])?;
+            Err("interrupted".into())
+        });
+        assert!(failed.is_err());
+        assert_eq!(saved, fs::read(&path).unwrap());
+        assert!(!temp_path(&path).exists());
+
+        write_save(&path, &population(3), SaveFormat::Cbor).unwrap();
+        assert_eq!(3, read_save::<Vec<Agent<NNPlayer>>>(&path).unwrap().len());
+        assert!(!temp_path(&path).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn skips_corrupt_saves() {
+        let dir = std::env::temp_dir().join(format!("fourai_corrupt_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("gen");
+        write_save(&dir.join("gen_1"), &population(1), SaveFormat::Cbor).unwrap();
+        write_save(&dir.join("gen_2"), &population(2), SaveFormat::Cbor).unwrap();
+        // Truncated, as by a crash in the middle of writing it
+        let saved = fs::read(dir.join("gen_2")).unwrap();
+        fs::write(dir.join("gen_3"), &saved[..saved.len() / 2]).unwrap();
+
+        let (gen, path, agents) = latest_valid_save::<Vec<Agent<NNPlayer>>>(&file_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!((2, dir.join("gen_2"), 2), (gen, path, agents.len()));
+
+        fs::write(dir.join("gen_1"), "").unwrap();
+        fs::write(dir.join("gen_2"), "").unwrap();
+        assert!(latest_valid_save::<Vec<Agent<NNPlayer>>>(&file_path)
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
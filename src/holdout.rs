@@ -0,0 +1,212 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::ai::agent::{choose_move, MovePlayer, TieBreak};
+use crate::game::{Board, Spot};
+
+/// A test position and the columns that count as the right move in it
+#[derive(Clone, Debug)]
+pub struct HoldoutPosition {
+    pub board: Board,
+    /// 0-based columns, any of which is a right answer
+    pub best: Vec<usize>,
+}
+
+impl HoldoutPosition {
+    /// Checks the expected moves against `winning_moves`: when the side to move can win,
+    /// every expected move has to win, and otherwise, when the opponent threatens to win,
+    /// every expected move has to block a threat
+    fn validate(&self) -> Result<(), String> {
+        if self.best.is_empty() {
+            return Err("no expected move".to_string());
+        }
+        if let Some(column) = self.best.iter().find(|c| !self.board.is_legal(**c)) {
+            return Err(format!("expected move {} can't be played", column + 1));
+        }
+
+        let to_move = self.board.to_move();
+        let opponent = match to_move {
+            Spot::RED => Spot::YELLOW,
+            _ => Spot::RED,
+        };
+        let (wins, reason) = match self.board.winning_moves(to_move) {
+            wins if !wins.is_empty() => (wins, "win"),
+            _ => (self.board.winning_moves(opponent), "block"),
+        };
+        match self
+            .best
+            .iter()
+            .find(|c| !wins.is_empty() && !wins.contains(c))
+        {
+            Some(column) => Err(format!(
+                "expected move {} doesn't {}, while {} would",
+                column + 1,
+                reason,
+                wins.iter()
+                    .map(|c| (c + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a holdout set: entries separated by blank lines, each a board as 6 lines of 7
+/// spots (`.`, `R` or `Y`) from the top row down, followed by a `best` line listing the
+/// right columns, counted from 1. Lines starting with `#` are comments.
+///
+/// ```text
+/// # Red wins vertically
+/// .......
+/// .......
+/// .......
+/// R......
+/// RY.....
+/// RY....Y
+/// best 1
+/// ```
+pub fn parse_holdout(text: &str) -> Result<Vec<HoldoutPosition>, Box<dyn Error>> {
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>();
+
+    lines
+        .split(|line| line.is_empty())
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(i, entry)| {
+            parse_entry(entry).map_err(|e| format!("holdout position {}: {}", i + 1, e).into())
+        })
+        .collect()
+}
+
+/// Parses and validates the lines of a single entry of a holdout set
+fn parse_entry(entry: &[&str]) -> Result<HoldoutPosition, Box<dyn Error>> {
+    let (last, grid) = entry.split_last().ok_or("empty entry")?;
+    let best = last
+        .strip_prefix("best")
+        .ok_or("missing the `best` line")?
+        .split_whitespace()
+        .map(|column| match column.parse::<usize>() {
+            Ok(column @ 1..=7) => Ok(column - 1),
+            _ => Err(format!("invalid column {:?}", column)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let position = HoldoutPosition {
+        board: Board::from_grid(&grid.join("\n"))?,
+        best,
+    };
+    position.validate()?;
+    Ok(position)
+}
+
+/// Reads a holdout set from `path`, see `parse_holdout`
+pub fn load_holdout(path: &Path) -> Result<Vec<HoldoutPosition>, Box<dyn Error>> {
+    let positions = parse_holdout(&fs::read_to_string(path)?)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if positions.is_empty() {
+        return Err(format!("{} holds no positions", path.display()).into());
+    }
+    Ok(positions)
+}
+
+/// Number of `positions` where `player` picks one of the expected moves
+pub fn score(player: &dyn MovePlayer, positions: &[HoldoutPosition], tie_break: TieBreak) -> usize {
+    positions
+        .iter()
+        .filter(|position| {
+            let scores = player.get_move(position.board.positions);
+            choose_move(&scores, &position.board, tie_break)
+                .is_some_and(|column| position.best.contains(&column))
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod holdout_tests {
+    use super::*;
+    use crate::ai::{nn, NNPlayer, N};
+    use crate::game;
+
+    const HOLDOUT: &str = "\
+# Red wins vertically
+.......
+.......
+.......
+R......
+RY.....
+RY....Y
+best 1
+
+# Yellow has to block red's row
+.......
+.......
+.......
+.......
+.......
+RRR..YY
+best 4
+
+# Either end of red's open three wins
+.......
+.......
+.......
+.......
+.YY....
+.RRR..Y
+best 1 5
+";
+
+    /// Always prefers `self.0`, then the columns after it
+    struct ColumnPlayer(usize);
+    impl MovePlayer for ColumnPlayer {
+        fn get_move(&self, _: [[game::Spot; 6]; 7]) -> [N; 7] {
+            let mut scores = [0.0; 7];
+            for (column, score) in scores.iter_mut().enumerate() {
+                *score = -(((column + 7 - self.0) % 7) as N);
+            }
+            scores
+        }
+    }
+
+    #[test]
+    fn parse_and_score() {
+        let positions = parse_holdout(HOLDOUT).unwrap();
+        assert_eq!(3, positions.len());
+        assert_eq!(vec![0], positions[0].best);
+        assert_eq!(game::Spot::YELLOW, positions[1].board.to_move());
+        assert_eq!(vec![0, 4], positions[2].best);
+
+        assert_eq!(2, score(&ColumnPlayer(0), &positions, TieBreak::First));
+        assert_eq!(1, score(&ColumnPlayer(3), &positions, TieBreak::First));
+        assert_eq!(1, score(&ColumnPlayer(4), &positions, TieBreak::First));
+        assert_eq!(0, score(&ColumnPlayer(6), &positions, TieBreak::First));
+
+        // A network scoring every column the same plays the leftmost legal one
+        let constant = NNPlayer::constant(vec![42, 8, 7], vec![nn::Activation::Sigmoid; 2]);
+        assert_eq!(2, score(&constant, &positions, TieBreak::First));
+        assert_eq!(1, score(&constant, &positions, TieBreak::Center));
+    }
+
+    #[test]
+    fn invalid_holdout() {
+        let board = ".......\n".repeat(3) + "R......\nRY.....\nRY....Y\n";
+        assert!(parse_holdout(&board).is_err());
+        // Doesn't take the win
+        assert!(parse_holdout(&(board.clone() + "best 3")).is_err());
+        assert!(parse_holdout(&(board.clone() + "best 8")).is_err());
+        assert!(parse_holdout(&(board.clone() + "best")).is_err());
+        assert!(parse_holdout(&(board + "best 1")).is_ok());
+
+        // Quiet positions accept any legal move
+        let quiet = ".......\n".repeat(5) + "...R...\nbest 3 4";
+        assert!(parse_holdout(&quiet).is_ok());
+        let full_column = "R......\nY......\nR......\nY......\nR......\nY......\nbest 1";
+        assert!(parse_holdout(full_column).is_err());
+    }
+}
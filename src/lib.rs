@@ -6,7 +6,82 @@ mod color;
 #[macro_use]
 pub mod ai;
 
+mod alloc_profile;
+mod archive;
 mod game;
+pub use game::{Board, Spot};
 mod helpers;
+mod holdout;
 
 pub mod matrix;
+mod plot;
+mod selftest;
+mod tune;
+
+use ai::agent::{IllegalMove, MovePlayer, TieBreak};
+
+/// Outcome of a game played with `play_game`
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    /// The winner, `Spot::EMPTY` for a draw
+    pub winner: Spot,
+    /// The final board, whose `history` holds the moves played
+    pub board: Board,
+}
+
+/// Plays a whole game from the empty board between `red` and `yellow`, calling `on_move` with
+/// the board and the column after every move. Ties between equally scored columns go to the
+/// leftmost one.
+pub fn play_game(
+    red: &dyn MovePlayer,
+    yellow: &dyn MovePlayer,
+    on_move: impl FnMut(&Board, usize),
+) -> GameResult {
+    let (winner, board) = game::play_game_with(
+        Board::new(),
+        Spot::RED,
+        red,
+        yellow,
+        TieBreak::First,
+        IllegalMove::Skip,
+        on_move,
+    );
+    GameResult { winner, board }
+}
+
+#[cfg(test)]
+mod lib_tests {
+    use super::*;
+    use ai::N;
+
+    /// Plays the next of its columns on every move
+    struct ScriptedPlayer(Vec<usize>);
+
+    impl MovePlayer for ScriptedPlayer {
+        fn get_move(&self, board: [[Spot; 6]; 7]) -> [N; 7] {
+            let pieces = board
+                .iter()
+                .flatten()
+                .filter(|s| **s != Spot::EMPTY)
+                .count();
+            let mut scores = [0.0; 7];
+            scores[self.0[pieces / 2]] = 1.0;
+            scores
+        }
+    }
+
+    #[test]
+    fn play_game() {
+        let red = ScriptedPlayer(vec![3, 3, 3, 3]);
+        let yellow = ScriptedPlayer(vec![4, 4, 4]);
+        let mut moves = Vec::new();
+        let result = super::play_game(&red, &yellow, |board, column| {
+            assert_eq!(moves.len() + 1, board.moves());
+            assert_eq!(Some(column), board.history().last().cloned());
+            moves.push(column);
+        });
+        assert_eq!(vec![3, 4, 3, 4, 3, 4, 3], moves);
+        assert_eq!(Spot::RED, result.winner);
+        assert_eq!(&moves[..], result.board.history());
+    }
+}
@@ -3,10 +3,16 @@ mod color;
 
 #[macro_use]
 mod ai;
+mod alloc_profile;
+mod archive;
 mod game;
 mod helpers;
+mod holdout;
 
 mod matrix;
+mod plot;
+mod selftest;
+mod tune;
 
 extern crate rand;
 extern crate rayon;
@@ -14,13 +20,20 @@ extern crate serde;
 extern crate serde_cbor;
 
 use crate::ai::{
-    pool::{Pool, PoolProperties},
-    NNPlayer,
+    agent::{Agent, IllegalMove, TieBreak},
+    pool::{Architecture, FitnessAggregation, Pool, PoolProperties},
+    Book, BookPlayer, MinimaxPlayer, NNPlayer, N,
 };
 
-use ai::nn::Activation;
-use clap::Parser;
-use std::{fs::create_dir_all, path::PathBuf};
+use ai::{encoder::Encoding, nn::Activation};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueSource};
+use game::{Board, BoardStyle, BoardView, Variant};
+use std::{
+    error::Error,
+    fs::{create_dir_all, File},
+    path::PathBuf,
+    time::Duration,
+};
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 const AUTHOR: &'static str = env!("CARGO_PKG_AUTHORS");
@@ -39,15 +52,140 @@ struct Opts {
 #[derive(Parser, Debug)]
 enum Subcommands {
     #[clap(about = "Train the neural network")]
-    Train(Train),
+    Train(Box<Train>),
     #[clap(about = "Play against the neural network")]
     PlayAi(PlayAi),
     #[clap(about = "Play against another play, locallaly (no ai)")]
     PlayLocal(PlayLocal),
+    #[clap(about = "Experiment with boards and the neural network interactively")]
+    Repl(Repl),
+    #[clap(about = "Convert a saved generation to another format")]
+    Convert(Convert),
+    #[clap(about = "Check the latest generation against the random agent and simple tactics")]
+    Selftest(Selftest),
+    #[clap(about = "Export the weights of the latest generation's top network as images")]
+    ExportWeights(ExportWeights),
+    #[clap(about = "Time short training runs with a few settings and recommend the best one")]
+    Tune(Box<Tune>),
+    #[clap(about = "Bundle a training run's save folder into a single tar archive")]
+    Pack(Pack),
+    #[clap(about = "Restore a training run packed with `pack`")]
+    Unpack(Unpack),
+    #[clap(about = "Draw the learning curve of a run trained with `--stats-log` as an SVG")]
+    Plot(Plot),
+    #[clap(about = "Build an opening book by time-limited minimax search, not a perfect solver")]
+    GenerateBook(GenerateBook),
+}
+
+#[derive(Parser, Debug)]
+struct GenerateBook {
+    #[clap(long = "depth", default_value = "4")]
+    /// Number of opening moves the book covers: every position with fewer pieces than this
+    /// is searched. Has to be at least 1.
+    depth: usize,
+    #[clap(long = "move-time-ms", default_value = "1000")]
+    /// Time the minimax search gets for every position, in milliseconds. The book's moves
+    /// are only as strong as the search that found them.
+    move_time_ms: u64,
+    #[clap(short = 'o', long = "out", default_value = "./book.cbor")]
+    /// Book file to write
+    out: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct Plot {
+    #[clap(long = "stats", default_value = "./saves/gen_stats.jsonl")]
+    /// Stats log written by `train --stats-log`, next to the generation saves
+    stats: PathBuf,
+    #[clap(short = 'o', long = "out", default_value = "./learning_curve.svg")]
+    /// SVG image to write
+    out: PathBuf,
 }
 
 #[derive(Parser, Debug)]
-struct PlayLocal {}
+struct Pack {
+    #[clap(short = 'p', long = "save-path", default_value = "./saves/gen")]
+    /// Generation path of the run. Everything in its folder is packed: the generations,
+    /// checkpoints, snapshots and the `_config.json` with the run's properties.
+    save_path: PathBuf,
+    #[clap(short = 'o', long = "out", default_value = "./run.tar")]
+    /// Archive to write
+    out: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct Unpack {
+    #[clap(long = "archive")]
+    /// Archive written by `pack`
+    archive: PathBuf,
+    #[clap(short = 'o', long = "out-dir", default_value = "./saves")]
+    /// Folder to restore the run's files to
+    out_dir: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct Tune {
+    #[clap(long = "burst", default_value = "5")]
+    /// Number of generations to train every setting for
+    burst: usize,
+    #[clap(flatten)]
+    /// Every other training flag is used as given. Population size, surviving amount,
+    /// mutation probability and crossover size are set by each tried setting, and nothing is
+    /// saved or compared.
+    train: Train,
+}
+
+#[derive(Parser, Debug)]
+struct ExportWeights {
+    #[clap(short = 'p', long = "save-path", default_value = "./saves/gen")]
+    /// Generation path to load the network from. The latest generation is used.
+    save_path: PathBuf,
+    #[clap(short = 'o', long = "out-dir", default_value = "./weights")]
+    /// Folder to write one grayscale PGM image per layer to, named `layer_<index>.pgm`
+    out_dir: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct Selftest {
+    #[clap(short = 'p', long = "save-path", default_value = "./saves/gen")]
+    /// Generation path to load the network from. The latest generation is used.
+    save_path: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct Convert {
+    #[clap(long = "from")]
+    /// Saved generation to convert. Its format is detected automatically.
+    from: PathBuf,
+    #[clap(long = "to")]
+    /// File to write the converted generation to
+    to: PathBuf,
+    #[clap(long = "format", default_value = "json", possible_values = &["cbor", "json"])]
+    /// Format to convert to
+    format: String,
+}
+
+#[derive(Parser, Debug)]
+struct Repl {
+    #[clap(short = 'p', long = "save-path", default_value = "./saves/gen")]
+    /// Generation path to load the network from. The latest generation is used.
+    save_path: PathBuf,
+    #[clap(long = "format-board", default_value = "unicode", possible_values = &["unicode", "ascii", "emoji"])]
+    /// How the board is drawn: colored blocks (`unicode`), plain characters (`ascii`) or
+    /// colored circle emoji (`emoji`)
+    format_board: String,
+}
+
+#[derive(Parser, Debug)]
+struct PlayLocal {
+    #[clap(long = "svg")]
+    /// Keep an SVG image of the current position at this path, updated after every move
+    svg: Option<PathBuf>,
+    #[clap(long = "format-board", default_value = "unicode", possible_values = &["unicode", "ascii", "emoji"])]
+    /// How the board is drawn: colored blocks (`unicode`), plain characters (`ascii`) or
+    /// colored circle emoji (`emoji`)
+    format_board: String,
+}
 
 #[derive(Parser, Debug)]
 struct PlayAi {
@@ -59,14 +197,72 @@ struct PlayAi {
     /// Make the AI go first (i.e. play as yellow)
     ai_first: bool,
 
+    #[clap(long = "tie-break", default_value = "first", possible_values = &["first", "center", "random"])]
+    /// How the AI picks between columns it scores equally
+    tie_break: String,
+
+    #[clap(long = "minimax")]
+    /// Play against a minimax search instead of the saved network
+    minimax: bool,
+
+    #[clap(long = "book")]
+    /// Play against the opening book at this path, written by `generate-book`, instead of the
+    /// saved network. Out of book, the minimax search plays with `--move-time-ms` and
+    /// `--blunder-rate`.
+    book: Option<PathBuf>,
+
+    #[clap(long = "move-time-ms", default_value = "1000")]
+    /// Time the minimax search gets for every move, in milliseconds. It searches one move
+    /// deeper at a time and plays the best move of the deepest search that finished.
+    move_time_ms: u64,
+
+    #[clap(long = "show-search-stats")]
+    /// Show the number of positions the minimax search visited and the time it took for
+    /// every move
+    show_search_stats: bool,
+
+    #[clap(long = "blunder-rate", default_value = "0")]
+    /// Probability that the minimax search plays a random worse move instead of the best one,
+    /// to make it beatable. `0` always plays the best move found.
+    blunder_rate: N,
+
+    #[clap(long = "red-perspective")]
+    /// When the network plays yellow, show it the board with the colors swapped, so it always
+    /// sees itself as red
+    red_perspective: bool,
+
+    #[clap(long = "replay-seed")]
+    /// Seed for the AI's random choices, such as `--tie-break random`. The seed is printed
+    /// during the game, so passing it again with the same moves replays the same game.
+    /// A random seed is used when not given.
+    replay_seed: Option<u64>,
+
     #[clap(short = 'p', long = "save-path", default_value = "./saves/gen")]
     /// Generation path to load from. Generation number is added to the end of the filename.
     /// E.g. `./saves/gen2500` is loaded for generation 2500 if `save-path` is `./saves/gen`
     save_path: PathBuf,
+
+    #[clap(long = "svg")]
+    /// Keep an SVG image of the current position at this path, updated after every move
+    svg: Option<PathBuf>,
+
+    #[clap(long = "format-board", default_value = "unicode", possible_values = &["unicode", "ascii", "emoji"])]
+    /// How the board is drawn: colored blocks (`unicode`), plain characters (`ascii`) or
+    /// colored circle emoji (`emoji`)
+    format_board: String,
+
+    #[clap(long = "quiet")]
+    /// Don't print which file, generation and network were loaded before the game starts
+    quiet: bool,
 }
 
 #[derive(Parser, Debug)]
 struct Train {
+    #[clap(long = "config")]
+    /// JSON file of training properties to start from, in the format of the `_config.json`
+    /// file written next to the saves. Flags given on the command line override its values.
+    config: Option<PathBuf>,
+
     #[clap(short = 'p', long = "save-path", default_value = "./saves/gen")]
     /// Generation save path.
     ///
@@ -77,33 +273,269 @@ struct Train {
     #[clap(short = 's', long = "surviving", default_value = "5")]
     /// The surviving population that lives into the next generation
     surviving: usize,
+    #[clap(long = "elitism", default_value = "0")]
+    /// Number of the fittest survivors carried into the next generation unchanged, without
+    /// mutation or crossover. At most `surviving`.
+    elitism: usize,
     #[clap(short = 'M', long = "mutation-range", default_value = "0.015")]
     /// Mutation range, i.e. how much to mutate each weight by
     mutation_range: f32,
     #[clap(short = 'P', long = "mutation-prob", default_value = "0.05")]
     /// Probablity of mutation, i.e. how often to mutate each weight
     mutation_prob: f32,
+    #[clap(long = "antithetic")]
+    /// Mutate the two copies of every survivor with opposite perturbations (antithetic
+    /// sampling), to reduce the noise in comparing mutations
+    antithetic: bool,
+    #[clap(long = "layer-mutation")]
+    /// Only mutate one randomly picked layer of each offspring, leaving the rest of its
+    /// network intact. Has no effect with `--antithetic`.
+    layer_mutation: bool,
+    #[clap(long = "freeze-layers", multiple_values = true)]
+    /// Weight layers to keep unchanged by mutation and crossover, counted from 0 for the layer
+    /// after the inputs, e.g. `--freeze-layers 0 1` to only evolve the later layers of a
+    /// network seeded with `--seed-from-best`
+    frozen_layers: Vec<usize>,
     #[clap(short = 'c', long = "crossover-size", default_value = "30")]
     /// Number of agents that result from crossover
     crossover_size: usize,
-    #[clap(short = 'p', long = "population-size", default_value = "200")]
+    #[clap(long = "disable-crossover")]
+    /// Fill the population only with mutated copies of the survivors, without any crossover
+    disable_crossover: bool,
+    #[clap(long = "seed-population-random-fraction", default_value = "0")]
+    /// Share of every new generation replaced with freshly initialised random networks
+    /// (random immigrants) instead of offspring of the weakest survivors, between 0 and 1
+    immigrant_fraction: N,
+    #[clap(short = 'n', long = "population-size", default_value = "200")]
     /// Total population size
     population_size: usize,
+    #[clap(long = "population-schedule", multiple_values = true, parse(try_from_str = parse_schedule_point))]
+    /// Population sizes to switch to during training, as `generation:size` pairs, e.g.
+    /// `--population-schedule 100:400 500:100` trains `--population-size` agents until
+    /// generation 100, then 400 until generation 500 and 100 after that
+    population_schedule: Vec<(usize, usize)>,
     #[clap(short = 'g', long = "generations", default_value = "-1")]
     /// Number of generations to train for.
     /// Use `-1` to train indefinitely, until stopped (i.e. interrupt)
     generations: isize,
+    #[clap(long = "max-runtime", parse(try_from_str = parse_duration))]
+    /// Wall-clock time to stop training after, as a number with a unit of `s`, `m`, `h` or
+    /// `d`, e.g. `30m` or `2h`. The last generation is saved before stopping.
+    max_runtime: Option<Duration>,
+    #[clap(long = "seed")]
+    /// Seed of the random initial networks, mutations and crossovers, to repeat a run exactly.
+    /// Random tie breaks are not covered. Seeded from the OS when not given.
+    seed: Option<u64>,
     #[clap(short = 'i', long = "save-interval", default_value = "250")]
     /// Interval to save the generations.
     /// Use `-1` to never save.
     save_interval: isize,
+    #[clap(long = "save-best-only")]
+    /// Only save the top agent of each saved generation, instead of all surviving agents.
+    /// Produces much smaller files, and is enough for `play-ai`.
+    save_best_only: bool,
+    #[clap(long = "checkpoint-full")]
+    /// Also save the whole population every saved generation to `<save-path>_checkpoint`, and
+    /// resume from it exactly when it exists. Without this, resuming rebuilds the population
+    /// from the saved survivors.
+    checkpoint_full: bool,
+    #[clap(long = "keep-last", default_value = "-1")]
+    /// Only keep this many of the most recent generation files, deleting older ones after
+    /// every save. Use `-1` to keep all of them; `0` is rejected.
+    keep_last: isize,
+    #[clap(long = "seed-from-best")]
+    /// If no numbered generation exists to resume from, but a `<save-path>_best` file does,
+    /// start from generation 0 with a population cloned and mutated from its agents. The file
+    /// has the saved generation format, so a copy of any saved generation works.
+    seed_from_best: bool,
+    #[clap(long = "preserve-fitness", default_value = "0")]
+    /// When resuming from a saved generation, number of generations during which offspring
+    /// start with a share of their parent's saved fitness, decaying linearly to none, so the
+    /// loaded ranking isn't discarded right away. Use `0` to start every agent from zero.
+    preserve_fitness: usize,
     #[clap(short = 'I', long = "compare-interval", default_value = "100")]
     /// Interval to compare the neural network population to a random agent.
     /// Use `-1` to never compare.
     compare_interval: isize,
+    #[clap(long = "compare-games", default_value = "1")]
+    /// Number of game sets against a random agent to average the comparison over. Every set
+    /// plays a differently seeded random agent, so more sets make the comparison less noisy.
+    compare_games: usize,
+    #[clap(long = "opening-report")]
+    /// When comparing, also play the top agent against the random agent from each of the 7
+    /// possible first moves (as both colors) and report its win-rate per opening
+    opening_report: bool,
+    #[clap(long = "compare-both-colors")]
+    /// When comparing, also report the fitness against the random agent separately for the
+    /// games played as red (moving first) and as yellow, for the survivors and the top agent
+    compare_both_colors: bool,
+    #[clap(long = "show-best-game")]
+    /// When comparing, also print the move list and final board of the top agent's game as
+    /// red against the random agent, to see how it wins
+    show_best_game: bool,
+    #[clap(long = "red-perspective")]
+    /// When comparing, swap the colors of the board whenever an agent plays yellow, so its
+    /// network always sees itself as red. Training is unchanged; pair with
+    /// `--compare-both-colors` to see whether agents do better as yellow this way.
+    red_perspective: bool,
+    #[clap(long = "curriculum-generations", default_value = "0")]
+    /// Number of generations at the start of training during which agents are also evaluated
+    /// against the random agent. Use `0` for pure self-play from the start.
+    curriculum_generations: usize,
+    #[clap(long = "curriculum-weight", default_value = "10")]
+    /// Fitness multiplier for games against the random agent in the first generation.
+    /// Decays linearly to 0 over `curriculum-generations`.
+    curriculum_weight: i32,
+    #[clap(long = "curriculum-minimax-from", default_value = "0")]
+    /// Generation from which agents are also evaluated against a minimax opponent that takes
+    /// every win in one move, with `curriculum-weight`, for the rest of training. Use `0` to
+    /// never add it.
+    curriculum_minimax_from: usize,
+    #[clap(long = "reference-games", default_value = "0")]
+    /// Number of games each agent plays against a fixed reference agent every generation.
+    /// Unlike self-play, these results are on an absolute scale, so the printed mean is
+    /// comparable between generations. The results are also added to each agent's fitness.
+    /// Use `0` to disable.
+    reference_games: usize,
+    #[clap(long = "draw-penalty", default_value = "0")]
+    /// Fitness taken away from both agents for every drawn game, to push agents to play for
+    /// wins instead of stalling for draws. Use `0` to score draws neutrally.
+    draw_penalty: i32,
+    #[clap(long = "draw-position-bonus", default_value = "0")]
+    /// Fitness given in a drawn game to the agent that came closer to winning (more lines of
+    /// four where it has three pieces), on top of the draw score. Use `0` to score both sides
+    /// of a draw the same.
+    draw_position_bonus: i32,
+    #[clap(long = "fitness-aggregation", default_value = "sum", possible_values = &["sum", "average", "win-rate"])]
+    /// How each agent's self-play results become its fitness: the sum over all games, the
+    /// average per game, or the share of games won (both in thousandths). Win-rate counts
+    /// draws as losses.
+    fitness_aggregation: String,
+    #[clap(long = "opponent-weighted")]
+    /// Scale the fitness of every self-play game by the opponent's flat fitness that
+    /// generation, so that beating strong agents counts more than beating weak ones, and losing
+    /// to them costs less
+    opponent_weighted: bool,
+    #[clap(long = "warn-on-saturation")]
+    /// When comparing, also print the share of saturated (sigmoid near 0 or 1) or dead (ReLU
+    /// always 0) units in every layer of the top agent over a set of random boards, and warn
+    /// about layers where more than half of the units are
+    warn_on_saturation: bool,
+    #[clap(long = "move-histogram")]
+    /// When comparing, also print how often the top agent picks each column over 1000 random
+    /// boards. A network that always plays the same column spikes on one of them.
+    move_histogram: bool,
+    #[clap(long = "constant-output-penalty", default_value = "0")]
+    /// Fitness taken away from every agent whose network scores all columns the same on every
+    /// opening position, since it ignores the board. Use `0` to disable the check.
+    constant_output_penalty: i32,
+    #[clap(long = "report-outcomes")]
+    /// Print the number of red wins, yellow wins and draws over all games of each generation
+    report_outcomes: bool,
+    #[clap(long = "stats-interval", default_value = "1")]
+    /// Interval to print the summary line (fitness, draw rate, time) of a generation, on its
+    /// own schedule from saving and comparing. Use `-1` to never print it.
+    stats_interval: isize,
+    #[clap(long = "eval-block-size", default_value = "0")]
+    /// Evaluate self-play in tiles of this many by this many agents, so each thread keeps
+    /// reusing a small set of networks instead of reading the whole population. Helps large
+    /// populations. Use `0` to evaluate one agent per task.
+    eval_block_size: usize,
+    #[clap(long = "batched-eval")]
+    /// Play all self-play games of a generation together, one move at a time, so every
+    /// network scores the boards of all its games with one matrix multiplication per layer.
+    /// Gives the same results up to float rounding, which can flip a close move. Replaces
+    /// `--eval-block-size` and `--fitness-cache`, and is ignored with `--max-opponents`.
+    batched_eval: bool,
+    #[clap(long = "max-opponents", default_value = "0")]
+    /// Number of randomly picked opponents every agent plays per generation, instead of the
+    /// whole population, to keep large populations from taking quadratic time. Every agent
+    /// plays the same number of games; `--fitness-aggregation average` keeps fitness on the
+    /// same scale for any cap. Use `0` to play everyone.
+    max_opponents: usize,
+    #[clap(long = "asymmetric-eval")]
+    /// Play each pairing only once, with the first agent as red, instead of once with each
+    /// color. Self-play still has every agent play both colors, but games against the random
+    /// and reference agents are only played as red, so they include red's first-move
+    /// advantage.
+    asymmetric_eval: bool,
+    #[clap(long = "games-per-color", default_value = "1")]
+    /// Number of times each pairing is played with every color assignment, summing the
+    /// results. Only reduces noise when games vary, e.g. with `--tie-break random`.
+    games_per_color: usize,
+    #[clap(long = "cpu-affinity")]
+    /// Pin each worker thread to its own CPU core (Linux only)
+    cpu_affinity: bool,
+    #[clap(long = "estimate-time")]
+    /// Train a single generation in memory, print how long the requested number of
+    /// generations would take at that speed, and exit without saving anything
+    estimate_time: bool,
+    #[clap(long = "print-config")]
+    /// Print the training properties as JSON before training, with the defaults, the
+    /// `--config` file and the flags given on the command line all applied
+    print_config: bool,
+    #[clap(long = "dry-save")]
+    /// Instead of training, save the initial population to `<save-path>_0`, check that it
+    /// loads back unchanged and delete it, to catch an unwritable save path or a broken save
+    /// format before a long run
+    dry_save: bool,
+    #[clap(long = "verify-determinism", value_name = "GENERATIONS")]
+    /// Instead of training, train two pools with the same seed (0 without `--seed`) for this
+    /// many generations in memory, one on every thread and one on a single thread, and check
+    /// that their populations stay identical. Exits with an error at the first generation
+    /// they differ.
+    verify_determinism: Option<usize>,
+    #[clap(long = "tie-break", default_value = "first", possible_values = &["first", "center", "random"])]
+    /// How agents pick between columns their network scores equally: the leftmost one, the
+    /// one closest to the center, or a random one (for more varied self-play)
+    tie_break: String,
+    #[clap(long = "illegal-move", default_value = "skip", possible_values = &["skip", "lose"])]
+    /// What happens when an agent's network scores a full column highest: it plays the best
+    /// column that isn't full (`skip`), or it loses the game (`lose`), which pushes the
+    /// networks to learn which columns are full
+    illegal_move: String,
+    #[clap(long = "snapshot-at", default_value = "-1")]
+    /// Write the fitness and a weight checksum of every agent at this generation to
+    /// `<save-path>_snapshot_<gen>.csv`. Comparing the snapshots of two seeded runs shows
+    /// whether they diverged. Use `-1` to never write one.
+    snapshot_at: isize,
+    #[clap(long = "dump-games", default_value = "0")]
+    /// Write this many self-play games of generation 0 to `<save-path>_games/game_<n>.txt`,
+    /// listing both players, the columns played and the winner, to see how the freshly
+    /// created networks play. Use `0` to write none.
+    dump_games: usize,
+    #[clap(long = "fitness-cache")]
+    /// Reuse the previous generation's result for self-play pairings where neither agent
+    /// changed, instead of replaying them. Has no effect with `--tie-break random`.
+    fitness_cache: bool,
+    #[clap(long = "profile-memory")]
+    /// Print the heap memory allocated while evaluating, breeding and saving every
+    /// generation. Needs a build with `--features profile-alloc`.
+    profile_memory: bool,
+    #[clap(long = "stats-log")]
+    /// Append the top and mean fitness of every generation, and the fitness against the
+    /// random agent when comparing, to `<save-path>_stats.jsonl`. Draw it with `plot`.
+    stats_log: bool,
+    #[clap(long = "eval-opponent-from")]
+    /// Saved generation file of a separately trained agent. Every compare interval, the
+    /// surviving agents are also compared against it, to track progress against a known
+    /// opponent
+    eval_opponent_from: Option<PathBuf>,
+    #[clap(long = "benchmark-against")]
+    /// Saved generation file of an earlier checkpoint of this run. Every compare interval,
+    /// each surviving agent plays its top agent, and the number of survivors that beat it is
+    /// printed. A falling count means the population may be regressing.
+    benchmark_against: Option<PathBuf>,
+    #[clap(long = "holdout")]
+    /// File of test positions with their right moves: 6 lines of `.`, `R` and `Y` from the
+    /// top row down, then a line like `best 4` listing the right columns, with a blank line
+    /// between positions. Every compare interval, the share of positions where the top agent
+    /// plays a right move is printed.
+    holdout: Option<PathBuf>,
     #[clap(short = 'S', long = "structure", multiple_values=true, default_values = &["42", "128", "256", "128", "7"])]
-    /// Structure of the neural network. Must begin with 42 and end with 7 (board input and
-    /// outputs)
+    /// Structure of the neural network. Must begin with the encoding's input size (42 for
+    /// `scalar`) and end with 7 (board input and outputs). When not given, the first and last
+    /// layer of the default are sized for `--variant` and `--encoding`.
     structure: Vec<usize>,
     #[clap(
         short = 'a',
@@ -115,42 +547,390 @@ struct Train {
     /// Activation functions to use between layers.
     /// Must be the same length as the structure minus 1.
     activations: Vec<String>,
+    #[clap(long = "extra-architecture", multiple_occurrences = true, parse(try_from_str = parse_architecture))]
+    /// Another network architecture to train in the same population, as layer sizes and
+    /// activations, e.g. `42,64,7:relu,sigmoid`. Can be given several times; the agents are
+    /// split evenly between `--structure` and every extra architecture, and only agents of the
+    /// same architecture are crossed over.
+    extra_architecture: Vec<Architecture>,
+    #[clap(long = "encoding", default_value = "scalar", possible_values = &["scalar", "two-plane"])]
+    /// How the board is fed to the network. `scalar` gives one input per spot (42 inputs),
+    /// `two-plane` one input per spot and color (84 inputs). The structure has to begin with
+    /// the matching number of inputs.
+    encoding: String,
+    #[clap(long = "variant", default_value = "standard", possible_values = &["standard"])]
+    /// Board preset. Only `standard` (7x6) can be played so far. Unless `--structure` is
+    /// given, its first and last layer are sized for the board and encoding.
+    variant: String,
+}
+
+/// Name of the `PoolProperties` field set by a `Train` argument. Arguments are named after
+/// their long flag, e.g. `mutation-range`.
+fn property_name(arg: &str) -> String {
+    match arg {
+        "surviving" => "surviving_amount".to_string(),
+        "save-path" => "file_path".to_string(),
+        // Set to the negation of the flag by `cli_train_props`
+        "disable-crossover" => "crossover_enabled".to_string(),
+        "asymmetric-eval" => "symmetric_eval".to_string(),
+        "extra-architecture" => "extra_architectures".to_string(),
+        other => other.replace('-', "_"),
+    }
+}
+
+/// Builds the training properties from the command line, starting from the `--config` file if
+/// one is given. Only arguments that were explicitly passed override the file's values.
+fn train_props(config: Train, matches: &ArgMatches) -> Result<PoolProperties, Box<dyn Error>> {
+    let config_path = config.config.clone();
+    let variant = Variant::from_string(&config.variant);
+    let mut cli_props = cli_train_props(config);
+    // A given structure that doesn't fit is reported by `check_structure`
+    if matches.value_source("structure") != Some(ValueSource::CommandLine) {
+        cli_props.structure = variant.fit_structure(&cli_props.structure, cli_props.encoding);
+    }
+    let config_path = match config_path {
+        Some(path) => path,
+        None => return Ok(cli_props),
+    };
+
+    let mut props: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_reader(File::open(config_path)?)?;
+    let cli_props = match serde_json::to_value(cli_props)? {
+        serde_json::Value::Object(map) => map,
+        _ => unreachable!(),
+    };
+    for arg in Train::command().get_arguments() {
+        let name = property_name(arg.get_id());
+        if let Some(value) = cli_props.get(&name) {
+            if matches.value_source(arg.get_id()) == Some(ValueSource::CommandLine) {
+                props.insert(name, value.clone());
+            }
+        }
+    }
+    for (name, value) in cli_props {
+        props.entry(name).or_insert(value);
+    }
+
+    Ok(serde_json::from_value(serde_json::Value::Object(props))?)
+}
+
+/// Checks that the network structure and activations fit together and fit the board, since
+/// building the networks doesn't check it in release builds
+fn check_structure(props: &PoolProperties) -> Result<(), String> {
+    let structure = &props.structure;
+    if structure.len() < 2 {
+        return Err(format!(
+            "--structure needs at least an input and an output layer, got {:?}",
+            structure
+        ));
+    }
+    if props.activations.len() != structure.len() - 1 {
+        return Err(format!(
+            "--activations needs one activation per layer after the first: the structure has {} \
+             layers, so {} activations, but {} were given",
+            structure.len(),
+            structure.len() - 1,
+            props.activations.len()
+        ));
+    }
+    if !Variant::Standard.matches_structure(structure, props.encoding) {
+        let (inputs, outputs) = Variant::Standard.structure_endpoints(props.encoding);
+        return Err(format!(
+            "--structure has to begin with {} (for the {:?} encoding) and end with {}, got {:?}",
+            inputs, props.encoding, outputs, structure
+        ));
+    }
+    for (structure, activations) in &props.extra_architectures {
+        if activations.len() + 1 != structure.len() {
+            return Err(format!(
+                "--extra-architecture {:?} needs one activation per layer after the first, got {}",
+                structure,
+                activations.len()
+            ));
+        }
+        if !Variant::Standard.matches_structure(structure, props.encoding) {
+            return Err(format!(
+                "--extra-architecture {:?} needs the same inputs and outputs as --structure {:?}",
+                structure, props.structure
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Parses an architecture given as layer sizes and activations, e.g. `42,64,7:relu,sigmoid`
+fn parse_architecture(s: &str) -> Result<Architecture, String> {
+    let (structure, activations) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected <sizes>:<activations>, got {}", s))?;
+    let structure = structure
+        .split(',')
+        .map(|size| {
+            size.parse::<usize>()
+                .map_err(|_| format!("invalid layer size: {}", size))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let activations = activations
+        .split(',')
+        .map(|activation| match activation {
+            "sigmoid" | "elu" | "relu" => Ok(Activation::from_string(activation)),
+            _ => Err(format!("invalid activation: {}", activation)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((structure, activations))
+}
+
+/// Parses a population schedule breakpoint given as `generation:size`, e.g. `100:400`
+fn parse_schedule_point(s: &str) -> Result<(usize, usize), String> {
+    let (generation, size) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected <generation>:<size>, got {}", s))?;
+    let generation = generation
+        .parse::<usize>()
+        .map_err(|_| format!("invalid generation: {}", generation))?;
+    let size = size
+        .parse::<usize>()
+        .map_err(|_| format!("invalid population size: {}", size))?;
+    Ok((generation, size))
+}
+
+/// Parses a duration given as a number of seconds, minutes, hours or days, e.g. `90s`, `30m`,
+/// `2h` or `1.5d`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (amount, unit) = s.split_at(s.find(char::is_alphabetic).unwrap_or(s.len()));
+    let seconds = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 60.0 * 60.0,
+        "d" => 24.0 * 60.0 * 60.0,
+        _ => return Err(format!("expected a unit of s, m, h or d, got {}", s)),
+    };
+    let amount = amount
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| amount.is_finite() && *amount >= 0.0)
+        .ok_or_else(|| format!("invalid duration: {}", s))?;
+    Duration::try_from_secs_f64(amount * seconds).map_err(|_| format!("duration too long: {}", s))
+}
+
+/// Exits if the board of `variant` can't be played yet. `--variant` only offers `standard`
+/// so far, so this can't fail from the command line; it guards against offering a preset
+/// before `Board::for_variant` supports it.
+fn exit_on_unsupported_variant(variant: &str) {
+    if let Err(e) = Board::for_variant(Variant::from_string(variant)) {
+        eprintln!("{}Unsupported board variant: {}{}", RED!(), e, RESET!());
+        std::process::exit(2);
+    }
+}
+
+/// Exits with a usage error if `props` fails `check_structure`
+fn exit_on_invalid_structure(props: &PoolProperties) {
+    if let Err(e) = check_structure(props) {
+        eprintln!("{}error: {}{}", RED!(), e, RESET!());
+        std::process::exit(2);
+    }
+}
+
+/// The training properties as pretty JSON, in the format of `--config` files
+fn effective_config(props: &PoolProperties) -> String {
+    serde_json::to_string_pretty(props).expect("training properties are valid JSON")
+}
+
+/// Prints how long `generations` generations take when each takes `per_generation`
+fn print_estimate(per_generation: Duration, generations: isize) {
+    println!(
+        "{}One generation took {:.3}s.{}",
+        BLUE!(),
+        per_generation.as_secs_f32(),
+        RESET!()
+    );
+    if generations < 0 {
+        println!(
+            "{}Training runs until stopped, 1000 generations would take {}.{}",
+            GREEN!(),
+            describe_estimate(per_generation, 1000),
+            RESET!()
+        );
+    } else {
+        println!(
+            "{}{} generations would take {}.{}",
+            GREEN!(),
+            generations,
+            describe_estimate(per_generation, generations as usize),
+            RESET!()
+        );
+    }
+}
+
+/// How long `generations` generations take when each takes `per_generation`, computed in
+/// seconds so that no number of generations overflows
+fn describe_estimate(per_generation: Duration, generations: usize) -> String {
+    match Duration::try_from_secs_f64(per_generation.as_secs_f64() * generations as f64) {
+        Ok(total) => format!("about {}", tune::format_duration(total)),
+        Err(_) => format!("more than {}", tune::format_duration(Duration::MAX)),
+    }
+}
+
+fn cli_train_props(config: Train) -> PoolProperties {
+    let activations = config
+        .activations
+        .into_iter()
+        .map(|a_str| Activation::from_string(&a_str))
+        .collect::<Vec<_>>();
+
+    PoolProperties {
+        population_size: config.population_size,
+        population_schedule: config.population_schedule,
+        mutation_prob: config.mutation_prob,
+        antithetic: config.antithetic,
+        layer_mutation: config.layer_mutation,
+        frozen_layers: config.frozen_layers,
+        surviving_amount: config.surviving,
+        elitism: config.elitism,
+        mutation_range: config.mutation_range,
+        crossover_size: config.crossover_size,
+        crossover_enabled: !config.disable_crossover,
+        immigrant_fraction: config.immigrant_fraction,
+        structure: config.structure,
+        activations,
+        extra_architectures: config.extra_architecture,
+        encoding: Encoding::from_string(&config.encoding),
+        generations: config.generations,
+        max_runtime: config.max_runtime,
+        seed: config.seed,
+        save_interval: config.save_interval,
+        save_best_only: config.save_best_only,
+        checkpoint_full: config.checkpoint_full,
+        keep_last: config.keep_last,
+        seed_from_best: config.seed_from_best,
+        preserve_fitness: config.preserve_fitness,
+        compare_interval: config.compare_interval,
+        compare_games: config.compare_games,
+        opening_report: config.opening_report,
+        compare_both_colors: config.compare_both_colors,
+        show_best_game: config.show_best_game,
+        red_perspective: config.red_perspective,
+        curriculum_generations: config.curriculum_generations,
+        curriculum_weight: config.curriculum_weight,
+        curriculum_minimax_from: config.curriculum_minimax_from,
+        reference_games: config.reference_games,
+        draw_penalty: config.draw_penalty,
+        draw_position_bonus: config.draw_position_bonus,
+        fitness_aggregation: FitnessAggregation::from_string(&config.fitness_aggregation),
+        opponent_weighted: config.opponent_weighted,
+        warn_on_saturation: config.warn_on_saturation,
+        move_histogram: config.move_histogram,
+        constant_output_penalty: config.constant_output_penalty,
+        report_outcomes: config.report_outcomes,
+        stats_interval: config.stats_interval,
+        eval_block_size: config.eval_block_size,
+        batched_eval: config.batched_eval,
+        max_opponents: config.max_opponents,
+        symmetric_eval: !config.asymmetric_eval,
+        games_per_color: config.games_per_color,
+        tie_break: TieBreak::from_string(&config.tie_break),
+        illegal_move: IllegalMove::from_string(&config.illegal_move),
+        snapshot_at: config.snapshot_at,
+        dump_games: config.dump_games,
+        fitness_cache: config.fitness_cache,
+        profile_memory: config.profile_memory,
+        stats_log: config.stats_log,
+        eval_opponent_from: config.eval_opponent_from,
+        benchmark_against: config.benchmark_against,
+        holdout: config.holdout,
+        file_path: config.save_path,
+    }
 }
 
 fn main() {
-    let opt = Opts::parse();
+    let matches = Opts::command().get_matches();
+    let opt = Opts::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     match opt.subcmd {
         Subcommands::Train(config) => {
+            if config.cpu_affinity {
+                if let Err(e) = helpers::pin_rayon_threads() {
+                    eprintln!("{}Failed to pin threads: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            }
+            let estimate_time = config.estimate_time;
+            let print_config = config.print_config;
+            let dry_save = config.dry_save;
+            let verify_determinism = config.verify_determinism;
+            exit_on_unsupported_variant(&config.variant);
+            let props = match train_props(*config, matches.subcommand_matches("train").unwrap()) {
+                Ok(props) => props,
+                Err(e) => {
+                    eprintln!("{}Failed to read config: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            };
+            exit_on_invalid_structure(&props);
+            if print_config {
+                println!("{}", effective_config(&props));
+            }
+            if estimate_time {
+                match tune::time_generation::<NNPlayer>(&props) {
+                    Ok(duration) => print_estimate(duration, props.generations),
+                    Err(e) => {
+                        eprintln!("{}Failed: {}", RED!(), e);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+            if let Some(generations) = verify_determinism {
+                let threads = [rayon::current_num_threads(), 1];
+                match tune::verify_determinism::<NNPlayer>(&props, generations, threads) {
+                    Ok(None) => println!(
+                        "{}Both runs stayed identical for {} generations on {} and {} threads{}",
+                        GREEN!(),
+                        generations,
+                        threads[0],
+                        threads[1],
+                        RESET!()
+                    ),
+                    Ok(Some(divergence)) => {
+                        eprintln!(
+                            "{}The runs diverged in generation {}, first at agent {}{}",
+                            RED!(),
+                            divergence.generation,
+                            divergence.agent,
+                            RESET!()
+                        );
+                        std::process::exit(1);
+                    }
+                    Err(e) => {
+                        eprintln!("{}Failed: {}", RED!(), e);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
             create_dir_all(
-                config
-                    .save_path
+                props
+                    .file_path
                     .parent()
                     .expect("Invalid save path provided"),
             )
             .expect("Failed create new saves folder");
 
-            let activations = config
-                .activations
-                .into_iter()
-                .map(|a_str| Activation::from_string(&a_str))
-                .collect::<Vec<_>>();
-
-            let props = PoolProperties {
-                population_size: config.population_size,
-                mutation_prob: config.mutation_prob,
-                surviving_amount: config.surviving,
-                mutation_range: config.mutation_range,
-                crossover_size: config.crossover_size,
-                structure: config.structure,
-                activations: activations,
-                generations: config.generations,
-                save_interval: config.save_interval,
-                compare_interval: config.compare_interval,
-                file_path: config.save_path,
-            };
-
-            let mut pool: Pool<NNPlayer> = Pool::new(props);
-            match pool.start() {
+            if dry_save {
+                match Pool::try_new(props).and_then(|pool: Pool<NNPlayer>| pool.dry_save()) {
+                    Ok(path) => println!(
+                        "{}Saved and loaded back the initial population at {}{}",
+                        GREEN!(),
+                        path.display(),
+                        RESET!()
+                    ),
+                    Err(e) => {
+                        eprintln!("{}Failed: {}", RED!(), e);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+            match Pool::try_new(props).and_then(|mut pool: Pool<NNPlayer>| pool.start()) {
                 Ok(_) => {}
                 Err(e) => {
                     eprintln!("{}Failed: {}", RED!(), e);
@@ -159,7 +939,50 @@ fn main() {
             }
         }
         Subcommands::PlayAi(config) => {
-            match game::play_against_ai::<NNPlayer>(&config.save_path, config.ai_first) {
+            let tie_break = TieBreak::from_string(&config.tie_break);
+            let seed = config.replay_seed.unwrap_or_else(rand::random);
+            let view = BoardView {
+                style: BoardStyle::from_string(&config.format_board),
+                svg_path: config.svg.as_deref(),
+            };
+            let minimax = MinimaxPlayer::new(Duration::from_millis(config.move_time_ms))
+                .with_blunders(config.blunder_rate, seed);
+            let played = if let Some(book_path) = &config.book {
+                Book::load(book_path).and_then(|book| {
+                    game::play_against(
+                        &BookPlayer::new(book, minimax),
+                        config.ai_first,
+                        view,
+                        tie_break,
+                        seed,
+                        config.show_search_stats,
+                        &mut std::io::stdin().lock(),
+                    )
+                    .map(|_| ())
+                })
+            } else if config.minimax {
+                game::play_against(
+                    &minimax,
+                    config.ai_first,
+                    view,
+                    tie_break,
+                    seed,
+                    config.show_search_stats,
+                    &mut std::io::stdin().lock(),
+                )
+                .map(|_| ())
+            } else {
+                game::play_against_ai::<NNPlayer>(
+                    &config.save_path,
+                    config.ai_first,
+                    view,
+                    tie_break,
+                    config.red_perspective,
+                    seed,
+                    config.quiet,
+                )
+            };
+            match played {
                 Ok(_) => {}
                 Err(e) => {
                     eprintln!("{}Failed: {}", RED!(), e);
@@ -167,8 +990,370 @@ fn main() {
                 }
             };
         }
-        Subcommands::PlayLocal(_) => {
-            game::start_two_player();
+        Subcommands::PlayLocal(config) => {
+            let view = BoardView {
+                style: BoardStyle::from_string(&config.format_board),
+                svg_path: config.svg.as_deref(),
+            };
+            if let Err(e) = game::start_two_player(view) {
+                eprintln!("{}Failed: {}", RED!(), e);
+                std::process::exit(1);
+            }
+        }
+        Subcommands::Repl(config) => {
+            let style = BoardStyle::from_string(&config.format_board);
+            if let Err(e) = game::start_repl::<NNPlayer>(&config.save_path, style) {
+                eprintln!("{}Failed: {}", RED!(), e);
+                std::process::exit(1);
+            }
         }
+        Subcommands::Selftest(config) => match selftest::selftest::<NNPlayer>(&config.save_path) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{}Failed: {}", RED!(), e);
+                std::process::exit(1);
+            }
+        },
+        Subcommands::ExportWeights(config) => {
+            let exported = game::load_ai::<NNPlayer>(&config.save_path)
+                .and_then(|player| player.export_weights(&config.out_dir));
+            match exported {
+                Ok(paths) => {
+                    for layer_path in paths {
+                        println!("Wrote {}", layer_path.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}Failed: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Subcommands::Tune(config) => {
+            exit_on_unsupported_variant(&config.train.variant);
+            let base = match train_props(config.train, matches.subcommand_matches("tune").unwrap())
+            {
+                Ok(props) => props,
+                Err(e) => {
+                    eprintln!("{}Failed to read config: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            };
+            exit_on_invalid_structure(&base);
+            match tune::tune::<NNPlayer>(&base, tune::CANDIDATES, config.burst) {
+                Ok(results) => tune::report(&results),
+                Err(e) => {
+                    eprintln!("{}Failed: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Subcommands::Pack(config) => {
+            let dir = config
+                .save_path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| std::path::Path::new("."));
+            match archive::pack(dir, &config.out) {
+                Ok(files) => println!("Packed {} files into {}", files, config.out.display()),
+                Err(e) => {
+                    eprintln!("{}Failed: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Subcommands::Unpack(config) => match archive::unpack(&config.archive, &config.out_dir) {
+            Ok(files) => println!("Unpacked {} files into {}", files, config.out_dir.display()),
+            Err(e) => {
+                eprintln!("{}Failed: {}", RED!(), e);
+                std::process::exit(1);
+            }
+        },
+        Subcommands::Plot(config) => match plot::plot(&config.stats, &config.out) {
+            Ok(generations) => println!(
+                "Plotted {} generations to {}",
+                generations,
+                config.out.display()
+            ),
+            Err(e) => {
+                eprintln!("{}Failed: {}", RED!(), e);
+                std::process::exit(1);
+            }
+        },
+        Subcommands::GenerateBook(config) => {
+            let move_time = Duration::from_millis(config.move_time_ms);
+            let book = match ai::generate_book(config.depth, move_time) {
+                Ok(book) => book,
+                Err(e) => {
+                    eprintln!("{}Failed: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            };
+            match book.save(&config.out) {
+                Ok(_) => println!("Wrote {} positions to {}", book.len(), config.out.display()),
+                Err(e) => {
+                    eprintln!("{}Failed: {}", RED!(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Subcommands::Convert(config) => {
+            let format = helpers::SaveFormat::from_string(&config.format);
+            if let Err(e) =
+                helpers::convert_save::<Vec<Agent<NNPlayer>>>(&config.from, &config.to, format)
+            {
+                eprintln!("{}Failed: {}", RED!(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod main_tests {
+    use super::*;
+
+    fn parse_train(args: &[&str]) -> PoolProperties {
+        let matches = Opts::command()
+            .try_get_matches_from(args)
+            .expect("Invalid arguments");
+        match Opts::from_arg_matches(&matches).unwrap().subcmd {
+            Subcommands::Train(config) => {
+                train_props(*config, matches.subcommand_matches("train").unwrap()).unwrap()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn config_file(name: &str, props: &PoolProperties) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("fourai_{}_{}.json", name, std::process::id()));
+        serde_json::to_writer(File::create(&path).unwrap(), props).unwrap();
+        path
+    }
+
+    #[test]
+    fn config_matches_cli() {
+        let cli_args = [
+            "fourai", "train", "-s", "7", "-M", "0.5", "-g", "12", "-S", "42", "16", "7", "-a",
+            "relu", "elu",
+        ];
+        let cli_props = parse_train(&cli_args);
+        let path = config_file("config_matches_cli", &cli_props);
+
+        let file_props = parse_train(&["fourai", "train", "--config", path.to_str().unwrap()]);
+        assert_eq!(
+            serde_json::to_value(&cli_props).unwrap(),
+            serde_json::to_value(&file_props).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn effective_config_round_trip() {
+        let props = parse_train(&[
+            "fourai",
+            "train",
+            "-s",
+            "7",
+            "-a",
+            "relu",
+            "elu",
+            "sigmoid",
+            "relu",
+            "--max-runtime",
+            "2h",
+            "--eval-opponent-from",
+            "saves/gen100",
+        ]);
+        let parsed: PoolProperties = serde_json::from_str(&effective_config(&props)).unwrap();
+        assert!(props == parsed);
+    }
+
+    #[test]
+    fn cli_overrides_config() {
+        let mut props = parse_train(&["fourai", "train"]);
+        props.surviving_amount = 9;
+        props.mutation_prob = 0.25;
+        let path = config_file("cli_overrides_config", &props);
+
+        let merged = parse_train(&[
+            "fourai",
+            "train",
+            "--config",
+            path.to_str().unwrap(),
+            "--surviving",
+            "3",
+            "--mutation-range",
+            "0.5",
+            "--save-path",
+            "elsewhere/gen",
+        ]);
+        assert_eq!(3, merged.surviving_amount);
+        assert_eq!(0.5, merged.mutation_range);
+        assert_eq!(PathBuf::from("elsewhere/gen"), merged.file_path);
+        assert_eq!(0.25, merged.mutation_prob);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn disable_crossover_overrides_config() {
+        let props = parse_train(&["fourai", "train"]);
+        assert!(props.crossover_enabled);
+        let path = config_file("disable_crossover_overrides_config", &props);
+
+        let merged = parse_train(&[
+            "fourai",
+            "train",
+            "--config",
+            path.to_str().unwrap(),
+            "--disable-crossover",
+        ]);
+        assert!(!merged.crossover_enabled);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn asymmetric_eval_overrides_config() {
+        let props = parse_train(&["fourai", "train"]);
+        assert!(props.symmetric_eval);
+        let path = config_file("asymmetric_eval_overrides_config", &props);
+
+        let merged = parse_train(&[
+            "fourai",
+            "train",
+            "--config",
+            path.to_str().unwrap(),
+            "--asymmetric-eval",
+        ]);
+        assert!(!merged.symmetric_eval);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn estimates() {
+        let second = Duration::from_secs(1);
+        assert_eq!("about 16m 40s", describe_estimate(second, 1000));
+        // More generations than a `u32` holds
+        assert_eq!(
+            "about 49710d 6h 28m 16s",
+            describe_estimate(second, 1 << 32)
+        );
+        assert!(describe_estimate(Duration::from_secs(u64::MAX), 2).starts_with("more than "));
+    }
+
+    #[test]
+    fn variant_sizes_default_structure() {
+        let props = parse_train(&["fourai", "train", "--encoding", "two-plane"]);
+        assert_eq!(vec![84, 128, 256, 128, 7], props.structure);
+        let props = parse_train(&["fourai", "train", "-S", "42", "16", "7"]);
+        assert_eq!(vec![42, 16, 7], props.structure);
+        // Boards that can't be played aren't offered
+        assert!(Opts::command()
+            .try_get_matches_from(["fourai", "train", "--variant", "small"])
+            .is_err());
+    }
+
+    #[test]
+    fn structure_checks() {
+        assert_eq!(Ok(()), check_structure(&parse_train(&["fourai", "train"])));
+        let two_plane = parse_train(&["fourai", "train", "--encoding", "two-plane"]);
+        assert_eq!(Ok(()), check_structure(&two_plane));
+
+        for args in [
+            // One activation short
+            &["-S", "42", "16", "7", "-a", "relu"][..],
+            // One activation too many
+            &["-S", "42", "7", "-a", "relu", "elu"][..],
+            // Wrong input size
+            &["-S", "84", "16", "7", "-a", "relu", "elu"][..],
+            // Wrong output size
+            &["-S", "42", "16", "6", "-a", "relu", "elu"][..],
+            &["-S", "42"][..],
+        ] {
+            let props = parse_train(&[&["fourai", "train"][..], args].concat());
+            assert!(check_structure(&props).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn extra_architectures() {
+        let props = parse_train(&[
+            "fourai",
+            "train",
+            "--extra-architecture",
+            "42,16,7:relu,sigmoid",
+            "--extra-architecture",
+            "42,7:elu",
+        ]);
+        let extra = &props.extra_architectures;
+        assert_eq!(2, extra.len());
+        assert_eq!(vec![42, 16, 7], extra[0].0);
+        assert!(matches!(
+            extra[0].1[..],
+            [Activation::RELU, Activation::Sigmoid]
+        ));
+        assert_eq!(vec![42, 7], extra[1].0);
+        assert!(matches!(extra[1].1[..], [Activation::ELU]));
+        assert_eq!(Ok(()), check_structure(&props));
+
+        assert!(parse_architecture("42,16,7").is_err());
+        assert!(parse_architecture("42,x,7:relu,relu").is_err());
+        assert!(parse_architecture("42,7:tanh").is_err());
+        for architecture in ["42,16,7:relu", "84,16,7:relu,relu", "42,16,6:relu,relu"] {
+            let props = parse_train(&["fourai", "train", "--extra-architecture", architecture]);
+            assert!(check_structure(&props).is_err(), "{}", architecture);
+        }
+
+        // Given next to a config file without any
+        let path = config_file("extra_architectures", &parse_train(&["fourai", "train"]));
+        let merged = parse_train(&[
+            "fourai",
+            "train",
+            "--config",
+            path.to_str().unwrap(),
+            "--extra-architecture",
+            "42,7:elu",
+        ]);
+        assert_eq!(1, merged.extra_architectures.len());
+        assert_eq!(vec![42, 7], merged.extra_architectures[0].0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn population_schedule() {
+        let props = parse_train(&[
+            "fourai",
+            "train",
+            "--population-schedule",
+            "100:400",
+            "500:100",
+        ]);
+        assert_eq!(vec![(100, 400), (500, 100)], props.population_schedule);
+        assert_eq!(200, props.population_at(99));
+        assert_eq!(400, props.population_at(100));
+        assert_eq!(100, props.population_at(1000));
+        assert!(parse_train(&["fourai", "train"])
+            .population_schedule
+            .is_empty());
+
+        assert!(parse_schedule_point("100").is_err());
+        assert!(parse_schedule_point("x:100").is_err());
+        assert!(parse_schedule_point("100:-5").is_err());
+    }
+
+    #[test]
+    fn max_runtime() {
+        assert_eq!(None, parse_train(&["fourai", "train"]).max_runtime);
+        let props = parse_train(&["fourai", "train", "--max-runtime", "30m"]);
+        assert_eq!(Some(Duration::from_secs(30 * 60)), props.max_runtime);
+        assert_eq!(Ok(Duration::from_secs(90)), parse_duration("1.5m"));
+        assert_eq!(Ok(Duration::from_secs(2 * 60 * 60)), parse_duration("2h"));
+        assert_eq!(Ok(Duration::from_secs(45)), parse_duration("45s"));
+        assert!(parse_duration("45").is_err());
+        assert!(parse_duration("2 weeks").is_err());
+        assert!(parse_duration("-1h").is_err());
+        assert!(parse_duration("300000000000000000000s").is_err());
     }
 }
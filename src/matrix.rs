@@ -1,4 +1,5 @@
 use libc::c_int;
+use rand::{distributions::Distribution, rngs::StdRng, SeedableRng};
 use rblas::attribute::Transpose;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Mul};
@@ -28,7 +29,7 @@ where
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Matrix<T>
 where
     T: Add<Output = T>,
@@ -38,6 +39,26 @@ where
     pub cols: usize,
 }
 
+impl<T> Clone for Matrix<T>
+where
+    T: Add<Output = T> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            values: self.values.clone(),
+            rows: self.rows,
+            cols: self.cols,
+        }
+    }
+
+    /// Reuses the allocation of `self`, which the derived implementation doesn't
+    fn clone_from(&mut self, source: &Self) {
+        self.values.clone_from(&source.values);
+        self.rows = source.rows;
+        self.cols = source.cols;
+    }
+}
+
 impl<T> Matrix<T>
 where
     T: Add<Output = T> + std::ops::AddAssign + Default + Clone,
@@ -73,6 +94,18 @@ where
         }
     }
 
+    /// Same as `from_rand`, but draws the values from `dist` with a `StdRng` seeded from `seed`,
+    /// so the same seed always gives the same matrix
+    pub fn from_seeded_rand<D: Distribution<T>>(
+        rows: usize,
+        columns: usize,
+        seed: u64,
+        dist: D,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self::from_rand(rows, columns, &mut || dist.sample(&mut rng))
+    }
+
     #[inline]
     pub fn alloca(rows: usize, columns: usize) -> Self {
         let values = vec![Default::default(); columns * rows];
@@ -83,6 +116,41 @@ where
         }
     }
 
+    /// Stacks single-column matrices side by side, so column `j` of the result is `columns[j]`.
+    /// All columns must have the same number of rows.
+    pub fn concat_cols(columns: &[Matrix<T>]) -> Self {
+        let rows = columns.first().map_or(0, |column| column.rows);
+        let mut c = Self::alloca(rows, columns.len());
+        for (j, column) in columns.iter().enumerate() {
+            assert_eq!(column.cols, 1, "concat_cols expects single-column matrices");
+            assert_eq!(
+                column.rows, rows,
+                "concat_cols expects columns of equal length"
+            );
+            for i in 0..rows {
+                let c_idx = c.cidx(i, j);
+                c.values[c_idx] = column.values[i].clone();
+            }
+        }
+        c
+    }
+
+    /// Stacks single-row matrices on top of each other, so row `i` of the result is `rows[i]`.
+    /// All rows must have the same number of columns.
+    pub fn concat_rows(rows: &[Matrix<T>]) -> Self {
+        let cols = rows.first().map_or(0, |row| row.cols);
+        let mut c = Self::alloca(rows.len(), cols);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.rows, 1, "concat_rows expects single-row matrices");
+            assert_eq!(row.cols, cols, "concat_rows expects rows of equal length");
+            for j in 0..cols {
+                let c_idx = c.cidx(i, j);
+                c.values[c_idx] = row.values[j].clone();
+            }
+        }
+        c
+    }
+
     #[allow(non_snake_case)]
     #[inline]
     pub fn T(self) -> Self {
@@ -135,6 +203,56 @@ where
     }
 }
 
+impl<T> Matrix<T>
+where
+    T: Add<Output = T> + std::ops::Sub<Output = T> + PartialOrd + Copy,
+{
+    /// Whether both matrices have the same dimensions and every pair of values is at most
+    /// `epsilon` apart. Results of float arithmetic that ran in a different order can differ
+    /// in the last bits, which exact equality doesn't allow for.
+    pub fn approx_eq(&self, other: &Matrix<T>, epsilon: T) -> bool {
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self.values.iter().zip(other.values.iter()).all(|(&a, &b)| {
+                let difference = if a > b { a - b } else { b - a };
+                difference <= epsilon
+            })
+    }
+}
+
+impl Matrix<f32> {
+    /// Renders the matrix as a plain (`P2`) grayscale PGM image with one pixel per value,
+    /// `cols` wide and `rows` high. The smallest value is black and the largest is white.
+    pub fn to_pgm(&self) -> String {
+        let min = self.values.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = self
+            .values
+            .iter()
+            .cloned()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let range = max - min;
+
+        let mut pgm = format!("P2\n{} {}\n255\n", self.cols, self.rows);
+        for row in self.values.chunks(self.cols.max(1)) {
+            let line = row
+                .iter()
+                .map(|x| {
+                    if range > 0.0 {
+                        (((x - min) / range) * 255.0).round() as u8
+                    } else {
+                        128
+                    }
+                    .to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            pgm += &line;
+            pgm += "\n";
+        }
+        pgm
+    }
+}
+
 impl<T> Add<Matrix<T>> for Matrix<T>
 where
     T: Add<Output = T> + std::ops::AddAssign,
@@ -372,4 +490,62 @@ mod matrix_tests {
         let mat = mat![1, 2; 3, 4; 5, 6];
         assert_eq!(mat.T(), mat![1, 3, 5; 2, 4, 6]);
     }
+
+    #[test]
+    fn from_seeded_rand_deterministic() {
+        let dist = rand::distributions::Uniform::new(-1.0, 1.0);
+        let first: Matrix<f32> = Matrix::from_seeded_rand(3, 4, 42, dist);
+        assert_eq!(first, Matrix::from_seeded_rand(3, 4, 42, dist));
+        assert_eq!((3, 4, 12), (first.rows, first.cols, first.values.len()));
+        assert_ne!(first, Matrix::from_seeded_rand(3, 4, 43, dist));
+    }
+
+    #[test]
+    fn to_pgm() {
+        let matrix: Matrix<f32> = mat![-1.0, 0.0, 1.0; 1.0, 1.0, 1.0];
+        assert_eq!("P2\n3 2\n255\n0 128 255\n255 255 255\n", matrix.to_pgm());
+        let constant: Matrix<f32> = mat![2.0; 2.0];
+        assert_eq!("P2\n1 2\n255\n128\n128\n", constant.to_pgm());
+    }
+
+    #[test]
+    fn concat_cols_1() {
+        let columns = [mat![1; 2; 3], mat![4; 5; 6]];
+        assert_eq!(Matrix::concat_cols(&columns), mat![1, 4; 2, 5; 3, 6]);
+    }
+
+    #[test]
+    fn concat_cols_2() {
+        let columns = [mat![1; 2]];
+        assert_eq!(Matrix::concat_cols(&columns), mat![1; 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn concat_cols_mismatched() {
+        Matrix::concat_cols(&[mat![1; 2; 3], mat![4; 5]]);
+    }
+
+    #[test]
+    fn concat_rows_1() {
+        let rows = [mat![1, 2, 3], mat![4, 5, 6]];
+        assert_eq!(Matrix::concat_rows(&rows), mat![1, 2, 3; 4, 5, 6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn concat_rows_not_single_row() {
+        Matrix::concat_rows(&[mat![1, 2; 3, 4]]);
+    }
+
+    #[test]
+    fn approx_eq() {
+        let matrix = mat![0.1, 0.2; 0.3, 0.4];
+        let close = mat![0.1 + 1e-7, 0.2; 0.3, 0.4 - 1e-7];
+        assert!(matrix.approx_eq(&close, 1e-6));
+        assert!(close.approx_eq(&matrix, 1e-6));
+        assert!(!matrix.approx_eq(&mat![0.1, 0.2; 0.3, 0.4 + 1e-5], 1e-6));
+        // Same values in a different shape
+        assert!(!matrix.approx_eq(&mat![0.1, 0.2, 0.3, 0.4], 1e-6));
+    }
 }
@@ -0,0 +1,193 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::ai::{pool::StatsRecord, N};
+
+const WIDTH: N = 800.0;
+const HEIGHT: N = 400.0;
+/// Space around the plot area for the axis labels and the legend
+const MARGIN: N = 50.0;
+
+/// Name, color and `(generation, value)` points of one line of the chart
+type Series = (&'static str, &'static str, Vec<(usize, N)>);
+
+/// Reads a stats log written with `stats_log`, one record per line. Blank lines are skipped.
+pub fn read_stats(path: &Path) -> Result<Vec<StatsRecord>, Box<dyn Error>> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("line {} of {}: {}", i + 1, path.display(), e).into())
+        })
+        .collect()
+}
+
+/// Line chart of the top and mean fitness of every generation in `records`, and of the
+/// fitness against the random agent in the generations that compared, as an SVG image
+pub fn learning_curve_svg(records: &[StatsRecord]) -> String {
+    let series: [Series; 3] = [
+        (
+            "top fitness",
+            "#d6201f",
+            records
+                .iter()
+                .map(|record| (record.generation, record.top_fitness as N))
+                .collect(),
+        ),
+        (
+            "mean fitness",
+            "#1e4fc2",
+            records
+                .iter()
+                .map(|record| (record.generation, record.mean_fitness))
+                .collect(),
+        ),
+        (
+            "against random",
+            "#2a9d3f",
+            records
+                .iter()
+                .filter_map(|record| Some((record.generation, record.random_fitness?)))
+                .collect(),
+        ),
+    ];
+
+    let generations = records.iter().map(|record| record.generation);
+    let first = generations.clone().min().unwrap_or(0);
+    let last = generations.max().unwrap_or(0).max(first + 1);
+    let values = series
+        .iter()
+        .flat_map(|(_, _, points)| points.iter().map(|(_, value)| *value));
+    let low = values.clone().fold(N::INFINITY, N::min).min(0.0);
+    let high = values.fold(N::NEG_INFINITY, N::max).max(low + 1.0);
+
+    let x = |generation: usize| {
+        MARGIN + (generation - first) as N / (last - first) as N * (WIDTH - 2.0 * MARGIN)
+    };
+    let y = |value: N| HEIGHT - MARGIN - (value - low) / (high - low) * (HEIGHT - 2.0 * MARGIN);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        WIDTH, HEIGHT
+    );
+    svg += &format!(
+        "  <rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+        WIDTH, HEIGHT
+    );
+    // Axes, with the range of each at its ends
+    svg += &format!(
+        "  <polyline points=\"{0},{1} {0},{2} {3},{2}\" fill=\"none\" stroke=\"black\"/>\n",
+        MARGIN,
+        MARGIN,
+        HEIGHT - MARGIN,
+        WIDTH - MARGIN
+    );
+    for (text, tx, ty, anchor) in [
+        (format!("{:.1}", high), MARGIN - 5.0, MARGIN, "end"),
+        (format!("{:.1}", low), MARGIN - 5.0, HEIGHT - MARGIN, "end"),
+        (first.to_string(), MARGIN, HEIGHT - MARGIN + 20.0, "middle"),
+        (
+            last.to_string(),
+            WIDTH - MARGIN,
+            HEIGHT - MARGIN + 20.0,
+            "middle",
+        ),
+        (
+            "generation".to_string(),
+            WIDTH / 2.0,
+            HEIGHT - MARGIN + 20.0,
+            "middle",
+        ),
+    ] {
+        svg += &format!(
+            "  <text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"{}\">{}</text>\n",
+            tx, ty, anchor, text
+        );
+    }
+
+    for (i, (name, color, points)) in series.iter().enumerate() {
+        if points.is_empty() {
+            continue;
+        }
+        let points = points
+            .iter()
+            .map(|(generation, value)| format!("{:.1},{:.1}", x(*generation), y(*value)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        svg += &format!(
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+            points, color
+        );
+        svg += &format!(
+            "  <text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"{}\">{}</text>\n",
+            MARGIN + 10.0 + i as N * 130.0,
+            MARGIN - 20.0,
+            color,
+            name
+        );
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+/// Renders the stats log at `stats_path` to an SVG learning curve at `out`, returning the
+/// number of generations plotted
+pub fn plot(stats_path: &Path, out: &Path) -> Result<usize, Box<dyn Error>> {
+    let records = read_stats(stats_path)?;
+    if records.is_empty() {
+        return Err(format!("{} holds no generations", stats_path.display()).into());
+    }
+    fs::write(out, learning_curve_svg(&records))?;
+    Ok(records.len())
+}
+
+#[cfg(test)]
+mod plot_tests {
+    use super::*;
+
+    /// Vertex count of every data line of `svg`, leaving out the axes
+    fn line_vertices(svg: &str) -> Vec<usize> {
+        svg.lines()
+            .filter(|line| line.contains("<polyline") && line.contains("stroke-width"))
+            .map(|line| {
+                let points = line.split("points=\"").nth(1).unwrap();
+                points[..points.find('"').unwrap()].split(' ').count()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn learning_curve() {
+        let path = std::env::temp_dir().join(format!(
+            "fourai_learning_curve_{}.jsonl",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "{\"generation\":0,\"top_fitness\":4,\"mean_fitness\":0.5,\"random_fitness\":null}\n\
+             {\"generation\":1,\"top_fitness\":6,\"mean_fitness\":1.0,\"random_fitness\":null}\n\
+             \n\
+             {\"generation\":2,\"top_fitness\":9,\"mean_fitness\":2.5,\"random_fitness\":1.5}\n\
+             {\"generation\":3,\"top_fitness\":7,\"mean_fitness\":3.0,\"random_fitness\":null}\n",
+        )
+        .unwrap();
+
+        let records = read_stats(&path).unwrap();
+        assert_eq!(4, records.len());
+        assert_eq!(Some(1.5), records[2].random_fitness);
+        // Top and mean fitness for every generation, the random comparison only once
+        assert_eq!(vec![4, 4, 1], line_vertices(&learning_curve_svg(&records)));
+
+        // Only the lines that have points are drawn
+        let svg = learning_curve_svg(&records[..1]);
+        assert_eq!(vec![1, 1], line_vertices(&svg));
+        assert!(!svg.contains("NaN"));
+
+        fs::write(&path, "{\"generation\":0}\n").unwrap();
+        assert!(read_stats(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
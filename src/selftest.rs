@@ -0,0 +1,186 @@
+use std::error::Error;
+use std::path;
+
+use serde::de::DeserializeOwned;
+
+use crate::ai::{
+    agent::{choose_move, IllegalMove, MovePlayer, Player, TieBreak},
+    encoder, RandomPlayer, N,
+};
+use crate::game::{self, Board, Spot};
+
+/// Share of games against the random agent that have to be won for the check to pass
+const RANDOM_WIN_RATE: N = 0.9;
+
+/// Positions where the side to move can win immediately, as the columns played from an
+/// empty board
+const TACTICS: &[(&str, &[usize])] = &[
+    ("vertical", &[0, 1, 0, 1, 0, 2]),
+    ("horizontal", &[0, 6, 1, 6, 2, 5]),
+    ("diagonal", &[0, 1, 1, 2, 2, 3, 2, 3, 6, 3]),
+    ("vertical as yellow", &[0, 1, 0, 1, 6, 1, 5]),
+    ("horizontal as yellow", &[6, 0, 6, 1, 5, 2, 0]),
+];
+
+/// Plays the moves of a tactic from an empty board
+fn tactic_board(moves: &[usize]) -> Board {
+    let mut board = Board::new();
+    for (i, column) in moves.iter().enumerate() {
+        let color = match i % 2 {
+            0 => Spot::RED,
+            _ => Spot::YELLOW,
+        };
+        board.insert_top(*column, color);
+    }
+    board
+}
+
+/// Win-rate of `player` against the random agent over every opening, playing both colors
+fn random_win_rate(player: &dyn MovePlayer) -> N {
+    let random = RandomPlayer::new();
+    let mut wins = 0;
+    for column in 0..7 {
+        let mut board = Board::new();
+        board.insert_top(column, Spot::RED);
+        let (winner_as_red, _) = game::play_from(
+            board.clone(),
+            Spot::YELLOW,
+            player,
+            &random,
+            TieBreak::First,
+            IllegalMove::Skip,
+        );
+        let (winner_as_yellow, _) = game::play_from(
+            board,
+            Spot::YELLOW,
+            &random,
+            player,
+            TieBreak::First,
+            IllegalMove::Skip,
+        );
+        wins += (winner_as_red == Spot::RED) as usize + (winner_as_yellow == Spot::YELLOW) as usize;
+    }
+    wins as N / 14.0
+}
+
+fn report(name: &str, passed: bool, detail: String) {
+    if passed {
+        println!("{}PASS{} {}: {}", GREEN!(), RESET!(), name, detail);
+    } else {
+        println!("{}FAIL{} {}: {}", RED!(), RESET!(), name, detail);
+    }
+}
+
+/// Runs every check against `player`, printing the results. Returns whether all of them passed.
+pub fn run_checks(player: &dyn MovePlayer) -> bool {
+    // Run both, so that every result is printed
+    let beats_random = check_random(player);
+    check_tactics(player) && beats_random
+}
+
+fn check_random(player: &dyn MovePlayer) -> bool {
+    let win_rate = random_win_rate(player);
+    let passed = win_rate >= RANDOM_WIN_RATE;
+    report(
+        "random agent",
+        passed,
+        format!(
+            "won {:.0}% of games, expected at least {:.0}%",
+            win_rate * 100.0,
+            RANDOM_WIN_RATE * 100.0
+        ),
+    );
+    passed
+}
+
+/// Checks that the player never misses a one-move win in any of the `TACTICS`
+fn check_tactics(player: &dyn MovePlayer) -> bool {
+    let mut passed = true;
+    for (name, moves) in TACTICS {
+        let board = tactic_board(moves);
+        let to_move = encoder::side_to_move(&board.positions);
+        let winning = board.winning_moves(to_move);
+        let chosen = choose_move(&player.get_move(board.positions), &board, TieBreak::First);
+        let found = chosen.is_some_and(|column| winning.contains(&column));
+        report(
+            &format!("one-move win ({})", name),
+            found,
+            format!(
+                "played column {}, winning {}",
+                chosen.map_or("none".to_string(), |column| (column + 1).to_string()),
+                winning
+                    .iter()
+                    .map(|column| (column + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ),
+        );
+        passed &= found;
+    }
+
+    passed
+}
+
+/// Loads the latest generation at `ai_path` and runs every check against its top agent
+pub fn selftest<Plr: Player + DeserializeOwned>(
+    ai_path: &path::Path,
+) -> Result<bool, Box<dyn Error>> {
+    let player: Plr = game::load_ai(ai_path)?;
+    Ok(run_checks(&player))
+}
+
+#[cfg(test)]
+mod selftest_tests {
+    use super::*;
+
+    /// Takes any immediate win, and otherwise plays the leftmost open column
+    struct TacticalPlayer;
+
+    impl MovePlayer for TacticalPlayer {
+        fn get_move(&self, positions: [[Spot; 6]; 7]) -> [N; 7] {
+            let mut board = Board::new();
+            for (column, spots) in positions.iter().enumerate() {
+                for spot in spots.iter().rev().take_while(|spot| **spot != Spot::EMPTY) {
+                    board.insert_top(column, *spot);
+                }
+            }
+            let mut scores = [0.0, -1.0, -2.0, -3.0, -4.0, -5.0, -6.0];
+            for column in board.winning_moves(encoder::side_to_move(&positions)) {
+                scores[column] = 10.0;
+            }
+            scores
+        }
+    }
+
+    #[test]
+    fn tactics_are_wins() {
+        for (name, moves) in TACTICS {
+            let board = tactic_board(moves);
+            let to_move = encoder::side_to_move(&board.positions);
+            assert!(!board.winning_moves(to_move).is_empty(), "{}", name);
+            // No side has already won
+            let mut replay = Board::new();
+            for (i, column) in moves.iter().enumerate() {
+                let color = match i % 2 {
+                    0 => Spot::RED,
+                    _ => Spot::YELLOW,
+                };
+                assert_eq!((true, None), replay.insert_top(*column, color), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn tactics_check() {
+        assert!(check_tactics(&TacticalPlayer));
+        // The random agent always plays the rightmost open column
+        assert!(!check_tactics(&RandomPlayer::new()));
+    }
+
+    #[test]
+    fn random_check() {
+        // Playing the same fixed moves as the random agent only wins when it moves first
+        assert_eq!(0.5, random_win_rate(&RandomPlayer::new()));
+        assert!(!check_random(&RandomPlayer::new()));
+    }
+}
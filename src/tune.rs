@@ -0,0 +1,384 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::ai::{
+    agent::Player,
+    pool::{Pool, PoolProperties},
+    N,
+};
+
+/// A setting tried by `tune`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    pub population_size: usize,
+    pub surviving_amount: usize,
+    pub mutation_prob: N,
+}
+
+/// Settings tried by default: three population sizes, each keeping the top 10% and mutating
+/// with two probabilities around the default. Kept small, since every candidate trains for
+/// a full burst.
+pub const CANDIDATES: &[Candidate] = &[
+    Candidate {
+        population_size: 50,
+        surviving_amount: 5,
+        mutation_prob: 0.05,
+    },
+    Candidate {
+        population_size: 50,
+        surviving_amount: 5,
+        mutation_prob: 0.1,
+    },
+    Candidate {
+        population_size: 100,
+        surviving_amount: 10,
+        mutation_prob: 0.05,
+    },
+    Candidate {
+        population_size: 100,
+        surviving_amount: 10,
+        mutation_prob: 0.1,
+    },
+    Candidate {
+        population_size: 200,
+        surviving_amount: 20,
+        mutation_prob: 0.05,
+    },
+    Candidate {
+        population_size: 200,
+        surviving_amount: 20,
+        mutation_prob: 0.1,
+    },
+];
+
+/// How a candidate did over its burst
+#[derive(Clone, Copy, Debug)]
+pub struct TuneResult {
+    pub candidate: Candidate,
+    pub generations_per_second: N,
+    /// Change per generation of the population's mean fitness against the random agent
+    pub improvement_per_generation: N,
+}
+
+impl TuneResult {
+    /// Improvement against the random agent per second of training, which the results are
+    /// ranked by
+    pub fn score(&self) -> N {
+        self.improvement_per_generation * self.generations_per_second
+    }
+}
+
+/// Properties for training `generations` generations of `base` in memory: nothing is saved,
+/// compared or snapshotted
+fn in_memory_props(base: &PoolProperties, generations: usize) -> PoolProperties {
+    let mut props = base.clone();
+    props.generations = generations as isize;
+    props.max_runtime = None;
+    props.save_interval = -1;
+    props.compare_interval = -1;
+    props.checkpoint_full = false;
+    props.snapshot_at = -1;
+    props.dump_games = 0;
+    props.stats_log = false;
+    props.eval_opponent_from = None;
+    props.benchmark_against = None;
+    props.holdout = None;
+    props
+}
+
+/// Properties for a burst of `candidate`, based on `base`. Nothing is saved or compared, and
+/// the crossover share of the population matches the training default (30 of 200).
+fn burst_props(base: &PoolProperties, candidate: &Candidate, generations: usize) -> PoolProperties {
+    let mut props = in_memory_props(base, generations);
+    props.population_size = candidate.population_size;
+    props.population_schedule = Vec::new();
+    props.surviving_amount = candidate.surviving_amount;
+    props.mutation_prob = candidate.mutation_prob;
+    props.crossover_size = candidate.population_size * 3 / 20;
+    props
+}
+
+/// Trains a single generation of `base` in memory and returns how long it took. The
+/// population size stays the same from one generation to the next, so every generation takes
+/// about as long.
+pub fn time_generation<Plr>(base: &PoolProperties) -> Result<Duration, Box<dyn Error>>
+where
+    Plr: Player + Clone + Serialize + DeserializeOwned + Sync + Send,
+{
+    let mut pool: Pool<Plr> = Pool::try_new(in_memory_props(base, 1))?;
+    match pool.generations(0).next() {
+        Some(report) => Ok(report?.duration),
+        None => Err("no generation was trained".into()),
+    }
+}
+
+/// Where two runs of `verify_determinism` first went apart
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Divergence {
+    pub generation: usize,
+    /// First agent that differs after that generation
+    pub agent: usize,
+}
+
+/// Trains two pools of `base`, seeded alike, side by side in memory for `generations`
+/// generations: the first on a rayon thread pool of `threads[0]` threads and the second on
+/// one of `threads[1]`. Their populations are compared after every generation, and the first
+/// generation after which they differ is returned. Without a seed, both are seeded with 0.
+pub fn verify_determinism<Plr>(
+    base: &PoolProperties,
+    generations: usize,
+    threads: [usize; 2],
+) -> Result<Option<Divergence>, Box<dyn Error>>
+where
+    Plr: Player + Clone + Serialize + DeserializeOwned + Sync + Send + PartialEq,
+{
+    let mut props = in_memory_props(base, generations);
+    props.seed = Some(props.seed.unwrap_or(0));
+
+    let mut runs = Vec::with_capacity(2);
+    for threads in threads {
+        let workers = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        runs.push((workers, Pool::<Plr>::try_new(props.clone())?));
+    }
+
+    for gen in 0..generations {
+        for (workers, pool) in runs.iter_mut() {
+            // Errors aren't `Send`, so they leave the thread pool as their message
+            workers.install(|| match pool.generations(gen).next() {
+                Some(Err(e)) => Err(e.to_string()),
+                _ => Ok(()),
+            })?;
+        }
+        if let Some(agent) = runs[0].1.first_difference(&runs[1].1) {
+            return Ok(Some(Divergence {
+                generation: gen,
+                agent,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Formats `duration` as days, hours, minutes and seconds, leaving out leading zero units,
+/// e.g. `2h 0m 5s`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let units = [
+        (seconds / 86400, "d"),
+        ((seconds / 3600) % 24, "h"),
+        ((seconds / 60) % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let first = units
+        .iter()
+        .position(|(amount, _)| *amount > 0)
+        .unwrap_or(units.len() - 1);
+    units[first..]
+        .iter()
+        .map(|(amount, unit)| format!("{}{}", amount, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Trains every candidate for `generations` generations in memory, returning the results
+/// ranked best first
+pub fn tune<Plr>(
+    base: &PoolProperties,
+    candidates: &[Candidate],
+    generations: usize,
+) -> Result<Vec<TuneResult>, Box<dyn Error>>
+where
+    Plr: Player + Clone + Serialize + DeserializeOwned + Sync + Send,
+{
+    let mut results = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        println!(
+            "{}Trying a population of {}, keeping {}, mutating with probability {}{}",
+            BLUE!(),
+            candidate.population_size,
+            candidate.surviving_amount,
+            candidate.mutation_prob,
+            RESET!()
+        );
+        let mut pool: Pool<Plr> = Pool::try_new(burst_props(base, candidate, generations))?;
+        let before = pool.mean_fitness_against_random();
+        let start = Instant::now();
+        pool.training_loop(0)?;
+        let elapsed = start.elapsed().as_secs_f32();
+        let after = pool.mean_fitness_against_random();
+
+        results.push(TuneResult {
+            candidate: *candidate,
+            generations_per_second: generations as N / elapsed.max(N::EPSILON),
+            improvement_per_generation: (after - before) / generations.max(1) as N,
+        });
+    }
+
+    results.sort_by(|a, b| b.score().partial_cmp(&a.score()).unwrap());
+    Ok(results)
+}
+
+/// Prints `results` as a table, best first, followed by the recommended setting
+pub fn report(results: &[TuneResult]) {
+    println!(
+        "{:>4} {:>10} {:>9} {:>13} {:>10} {:>13} {:>10}",
+        "rank", "population", "surviving", "mutation prob", "gens/s", "improvement/g", "score"
+    );
+    for (rank, result) in results.iter().enumerate() {
+        println!(
+            "{:>4} {:>10} {:>9} {:>13} {:>10.3} {:>13.3} {:>10.3}",
+            rank + 1,
+            result.candidate.population_size,
+            result.candidate.surviving_amount,
+            result.candidate.mutation_prob,
+            result.generations_per_second,
+            result.improvement_per_generation,
+            result.score()
+        );
+    }
+    if let Some(best) = results.first() {
+        println!(
+            "{}Recommended: train -n {} -s {} -P {}{}",
+            GREEN!(),
+            best.candidate.population_size,
+            best.candidate.surviving_amount,
+            best.candidate.mutation_prob,
+            RESET!()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tune_tests {
+    use super::*;
+    use crate::ai::NNPlayer;
+    use crate::ai::{agent::Evolvable, agent::MovePlayer, nn, pool::pool_tests::test_props};
+    use crate::game;
+    use rand::RngCore;
+    use serde::Deserialize;
+
+    /// A network that remembers how many threads mutated it last, like a player whose
+    /// results depend on how the work was split between threads
+    #[derive(Clone, Serialize, Deserialize, PartialEq)]
+    struct ThreadCountPlayer {
+        player: NNPlayer,
+        threads: usize,
+    }
+    impl Evolvable for ThreadCountPlayer {
+        fn new_from_param(structure: Vec<usize>, activations: Vec<nn::Activation>) -> Self {
+            ThreadCountPlayer {
+                player: NNPlayer::new_from_param(structure, activations),
+                threads: 0,
+            }
+        }
+        fn new_from_param_with(
+            structure: Vec<usize>,
+            activations: Vec<nn::Activation>,
+            rng: &mut dyn RngCore,
+        ) -> Self {
+            ThreadCountPlayer {
+                player: NNPlayer::new_from_param_with(structure, activations, rng),
+                threads: 0,
+            }
+        }
+        fn mutate(&mut self, mutation_range: N, mutation_prob: N, rng: &mut dyn RngCore) {
+            self.player.mutate(mutation_range, mutation_prob, rng);
+            self.threads = rayon::current_num_threads();
+        }
+    }
+    impl MovePlayer for ThreadCountPlayer {
+        fn get_move(&self, board: [[game::Spot; 6]; 7]) -> [N; 7] {
+            self.player.get_move(board)
+        }
+    }
+
+    #[test]
+    fn burst_is_in_memory() {
+        let mut base = test_props(std::path::PathBuf::from("unused"));
+        base.save_interval = 10;
+        base.checkpoint_full = true;
+        base.snapshot_at = 3;
+        let props = burst_props(&base, &CANDIDATES[2], 4);
+        assert_eq!(100, props.population_size);
+        assert_eq!(10, props.surviving_amount);
+        assert_eq!(15, props.crossover_size);
+        assert_eq!(4, props.generations);
+        assert_eq!(-1, props.save_interval);
+        assert_eq!(-1, props.compare_interval);
+        assert_eq!(-1, props.snapshot_at);
+        assert!(!props.checkpoint_full);
+    }
+
+    #[test]
+    fn ranks_candidates() {
+        let candidates = [
+            Candidate {
+                population_size: 6,
+                surviving_amount: 2,
+                mutation_prob: 0.05,
+            },
+            Candidate {
+                population_size: 10,
+                surviving_amount: 3,
+                mutation_prob: 0.1,
+            },
+        ];
+        let base = test_props(std::path::PathBuf::from("unused"));
+        let results = tune::<NNPlayer>(&base, &candidates, 2).unwrap();
+        assert_eq!(2, results.len());
+        assert!(results[0].score() >= results[1].score());
+        assert!(results
+            .iter()
+            .all(|result| result.generations_per_second > 0.0));
+        assert!(candidates.contains(&results[0].candidate));
+        assert!(candidates.contains(&results[1].candidate));
+    }
+
+    #[test]
+    fn time_generation_in_memory() {
+        let dir =
+            std::env::temp_dir().join(format!("fourai_time_generation_{}", std::process::id()));
+        let mut base = test_props(dir.join("gen"));
+        base.save_interval = 1;
+        base.generations = 1000;
+        let duration = time_generation::<NNPlayer>(&base).unwrap();
+        assert!(duration > Duration::ZERO);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn determinism() {
+        let mut base = test_props(std::path::PathBuf::from("unused"));
+        base.seed = Some(3);
+        assert_eq!(
+            None,
+            verify_determinism::<NNPlayer>(&base, 3, [1, 3]).unwrap()
+        );
+        // Runs on as many threads as each other agree
+        assert_eq!(
+            None,
+            verify_determinism::<ThreadCountPlayer>(&base, 3, [2, 2]).unwrap()
+        );
+        let divergence = verify_determinism::<ThreadCountPlayer>(&base, 3, [1, 3])
+            .unwrap()
+            .unwrap();
+        assert_eq!(0, divergence.generation);
+        assert!(divergence.agent < base.population_size);
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!("0s", format_duration(Duration::from_millis(300)));
+        assert_eq!("59s", format_duration(Duration::from_secs(59)));
+        assert_eq!("2h 0m 5s", format_duration(Duration::from_secs(7205)));
+        assert_eq!(
+            "3d 1h 0m 0s",
+            format_duration(Duration::from_secs(3 * 86400 + 3600))
+        );
+    }
+}